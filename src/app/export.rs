@@ -0,0 +1,235 @@
+use iced::futures::channel::oneshot;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+// Side of one cell in exported images, in pixels
+pub const EXPORT_CELL_PX: usize = 4;
+// Cells whose state has no color (e.g. a removed state) export in the canvas' fallback red
+const MISSING_STATE_RGB: [u8; 3] = [255, 0, 0];
+// Caption bar: a twelfth of the image height, but at least one line of the 3x5 font
+const CAPTION_HEIGHT_DIVISOR: usize = 12;
+const CAPTION_MIN_PX: usize = 7;
+const CAPTION_BACKGROUND_RGB: [u8; 3] = [0, 0, 0];
+const CAPTION_TEXT_RGB: [u8; 3] = [255, 255, 255];
+
+// Everything an export needs, copied out of the simulator so encoding can run on
+// another thread while the UI keeps going
+pub struct ImageExport {
+    pub path: PathBuf,
+    pub width: usize,
+    pub height: usize,
+    pub palette: HashMap<u8, [u8; 3]>, // State id -> color
+    pub frames: Vec<Vec<u8>>,          // Row-major state ids, one entry per frame
+    pub frame_delay_ms: u64,           // GIF only
+    pub caption: Option<String>,       // PNG only, burned into a bar below the grid
+}
+
+// Runs `encode` on its own thread; the returned future resolves with the written path
+pub async fn run_in_background(
+    export: ImageExport,
+    encode: fn(&ImageExport) -> Result<(), String>,
+) -> Result<PathBuf, String> {
+    let (sender, receiver) = oneshot::channel();
+    std::thread::spawn(move || {
+        let result = encode(&export).map(|()| export.path.clone());
+        let _ = sender.send(result);
+    });
+    receiver
+        .await
+        .map_err(|_| "Export thread stopped unexpectedly".to_string())?
+}
+
+// First frame as an RGB PNG
+pub fn encode_png(export: &ImageExport) -> Result<(), String> {
+    let cells = export.frames.first().ok_or("Nothing to export")?;
+    let (img_width, img_height) = image_size(export);
+
+    let caption_height = export.caption.as_ref().map_or(0, |_| {
+        (img_height / CAPTION_HEIGHT_DIVISOR).max(CAPTION_MIN_PX)
+    });
+
+    let mut pixels = Vec::with_capacity(img_width * (img_height + caption_height) * 3);
+    for y in 0..img_height {
+        let row = y / EXPORT_CELL_PX;
+        for x in 0..img_width {
+            let id = cells[row * export.width + x / EXPORT_CELL_PX];
+            pixels.extend_from_slice(export.palette.get(&id).unwrap_or(&MISSING_STATE_RGB));
+        }
+    }
+    if let Some(caption) = &export.caption {
+        pixels.extend(render_caption(caption, img_width, caption_height));
+    }
+
+    let file = File::create(&export.path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        img_width as u32,
+        (img_height + caption_height) as u32,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(&pixels).map_err(|e| e.to_string())
+}
+
+// All frames as a looping, palette-indexed GIF
+pub fn encode_gif(export: &ImageExport) -> Result<(), String> {
+    if export.frames.is_empty() {
+        return Err("No frames recorded".into());
+    }
+    let (img_width, img_height) = image_size(export);
+    if img_width > u16::MAX as usize || img_height > u16::MAX as usize {
+        return Err("Grid is too large for a GIF".into());
+    }
+
+    // One palette entry per state, plus the fallback color at the end
+    let mut ids: Vec<u8> = export.palette.keys().copied().collect();
+    ids.sort_unstable();
+    ids.truncate(255);
+    let mut global_palette: Vec<u8> = ids.iter().flat_map(|id| export.palette[id]).collect();
+    global_palette.extend_from_slice(&MISSING_STATE_RGB);
+    let missing_index = ids.len() as u8;
+    let index_of: HashMap<u8, u8> = ids
+        .iter()
+        .enumerate()
+        .map(|(index, &id)| (id, index as u8))
+        .collect();
+
+    let file = File::create(&export.path).map_err(|e| e.to_string())?;
+    let mut encoder = gif::Encoder::new(
+        BufWriter::new(file),
+        img_width as u16,
+        img_height as u16,
+        &global_palette,
+    )
+    .map_err(|e| e.to_string())?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(|e| e.to_string())?;
+
+    for cells in &export.frames {
+        let mut buffer = Vec::with_capacity(img_width * img_height);
+        for y in 0..img_height {
+            let row = y / EXPORT_CELL_PX;
+            for x in 0..img_width {
+                let id = cells[row * export.width + x / EXPORT_CELL_PX];
+                buffer.push(*index_of.get(&id).unwrap_or(&missing_index));
+            }
+        }
+
+        let frame = gif::Frame {
+            width: img_width as u16,
+            height: img_height as u16,
+            delay: (export.frame_delay_ms / 10).max(2) as u16, // Hundredths of a second
+            buffer: buffer.into(),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn image_size(export: &ImageExport) -> (usize, usize) {
+    (
+        export.width * EXPORT_CELL_PX,
+        export.height * EXPORT_CELL_PX,
+    )
+}
+
+// RGB rows of a caption bar with `caption` written left-aligned in the 3x5 font,
+// scaled up to fill the bar unless the text would run off the image
+fn render_caption(caption: &str, width: usize, height: usize) -> Vec<u8> {
+    let mut pixels: Vec<u8> = CAPTION_BACKGROUND_RGB
+        .iter()
+        .copied()
+        .cycle()
+        .take(width * height * 3)
+        .collect();
+    let chars: Vec<char> = caption.to_uppercase().chars().collect();
+
+    // A glyph is 3 units wide plus 1 of spacing, 5 units tall plus 1 of margin each side
+    let units_wide = chars.len() * 4 + 1;
+    let scale = (height / 7).min(width / units_wide.max(1)).max(1);
+    let top = height.saturating_sub(5 * scale) / 2;
+
+    for (n, &c) in chars.iter().enumerate() {
+        let x0 = scale + n * 4 * scale;
+        for (gy, bits) in glyph(c).iter().enumerate() {
+            for gx in 0..3 {
+                if bits & (0b100 >> gx) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let (x, y) = (x0 + gx * scale + dx, top + gy * scale + dy);
+                        if x < width && y < height {
+                            let i = (y * width + x) * 3;
+                            pixels[i..i + 3].copy_from_slice(&CAPTION_TEXT_RGB);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    pixels
+}
+
+// Rows of a 3x5 glyph, top first, leftmost pixel in the high bit
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '|' => [0b010, 0b010, 0b010, 0b010, 0b010],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010], // '?'
+    }
+}
@@ -1,2 +1,5 @@
+pub mod export;
+pub mod simulation;
 pub mod simulator;
+pub mod sweep;
 pub use simulator::CASimulator;
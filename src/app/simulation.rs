@@ -0,0 +1,474 @@
+use crate::state::ca_grid::{BoundaryMode, CAGrid, DirectionMask, NeighborCounts, Neighborhood};
+use crate::state::transition_rule::{
+    BlockRule, ConditionExpr, NoMatchPolicy, RuleEvalMode, TransitionRule, NOT_SELF_ID,
+};
+use crate::state::CAState;
+use rand::rngs::SmallRng;
+use rand::{Rng, RngCore, SeedableRng};
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+// Grids with at least this many cells are stepped in parallel
+const PARALLEL_STEP_THRESHOLD: usize = 10_000;
+
+// Flat copies of the grid a step reads from and writes to. Kept between steps so a
+// running simulation reuses the same memory instead of allocating two grids per tick
+#[derive(Debug, Clone, Default)]
+pub struct StepBuffers {
+    pub current: Vec<u8>,
+    pub next: Vec<u8>,
+    // Generation before `current`, valid only when `has_previous` is set
+    previous: Vec<u8>,
+    has_previous: bool,
+}
+
+impl StepBuffers {
+    // Copies `grid` into `current` and sizes `next` to match. The old `current` becomes
+    // `previous` when `grid` is exactly the last step's result, so an edit in between
+    // leaves the step without a previous generation
+    fn load(&mut self, grid: &CAGrid) {
+        self.has_previous = !self.current.is_empty()
+            && self.next.len() == self.current.len()
+            && self.next.iter().eq(grid.cells.iter().flatten());
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+        self.current.extend(grid.cells.iter().flatten());
+        self.next.resize(self.current.len(), 0);
+    }
+
+    // Total room in the buffers; it only grows on the first steps or after a resize
+    #[cfg(feature = "step-timing")]
+    fn capacity(&self) -> usize {
+        self.current.capacity() + self.next.capacity() + self.previous.capacity()
+    }
+}
+
+// What a step needs to know about a rule beyond the rule itself, worked out once
+// per generation instead of once per cell
+pub struct PreparedRule {
+    rule_idx: usize, // Position of the rule in the rule list
+    tree: ConditionExpr,
+    // False when the rule's global condition fails for this generation
+    enabled: bool,
+    // Chance the rule fires once its conditions hold
+    probability: f32,
+}
+
+// `population` is the histogram of the generation about to be stepped. With
+// `deterministic`, every rule fires as if its probability were 1.0. The rules come
+// back in the order `eval_mode` tries them, so the first that fires decides
+pub fn prepare_rules(
+    rules: &[TransitionRule],
+    population: &[u32],
+    deterministic: bool,
+    eval_mode: RuleEvalMode,
+) -> Vec<PreparedRule> {
+    let mut prepared: Vec<PreparedRule> = rules
+        .iter()
+        .enumerate()
+        .map(|(rule_idx, rule)| PreparedRule {
+            rule_idx,
+            tree: rule.condition_tree(),
+            enabled: rule
+                .global_condition
+                .as_ref()
+                .is_none_or(|global| global.holds(population)),
+            probability: if deterministic { 1.0 } else { rule.probability },
+        })
+        .collect();
+    if eval_mode == RuleEvalMode::LastMatch {
+        prepared.reverse();
+    }
+    prepared
+}
+
+// Random source for one cell in one step. Derived from the seed rather than shared, so a
+// run replays exactly from the same seed however rayon splits the grid between threads
+pub fn cell_rng(seed: u64, generation: u64, idx: usize) -> SmallRng {
+    let mut h = seed;
+    for v in [generation, idx as u64] {
+        h = (h ^ v).wrapping_mul(0x9E37_79B9_7F4A_7C15).rotate_left(31);
+    }
+    SmallRng::seed_from_u64(h)
+}
+
+// `cell_rng`, seeded on the first draw. Most cells of a deterministic model never
+// draw, so the step loop doesn't pay for seeding an RNG per cell
+struct LazyCellRng {
+    seed: u64,
+    generation: u64,
+    idx: usize,
+    rng: Option<SmallRng>,
+}
+
+impl LazyCellRng {
+    fn new(seed: u64, generation: u64, idx: usize) -> Self {
+        LazyCellRng {
+            seed,
+            generation,
+            idx,
+            rng: None,
+        }
+    }
+
+    fn get(&mut self) -> &mut SmallRng {
+        let (seed, generation, idx) = (self.seed, self.generation, self.idx);
+        self.rng
+            .get_or_insert_with(|| cell_rng(seed, generation, idx))
+    }
+}
+
+impl RngCore for LazyCellRng {
+    fn next_u32(&mut self) -> u32 {
+        self.get().next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.get().next_u64()
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        self.get().fill_bytes(dst)
+    }
+}
+
+// What a step reads besides the model and grid
+#[derive(Debug, Clone, Copy)]
+pub struct StepSettings {
+    pub no_match_policy: NoMatchPolicy,
+    pub background_state_id: u8,
+    pub seed: u64,
+    pub generation: u64, // Generation being stepped, which picks each cell's random draws
+    pub deterministic: bool,
+    pub eval_mode: RuleEvalMode,
+}
+
+// The generation after `grid`, as row-major state ids, with the default settings:
+// the first matching rule wins and cells no rule fires for keep their state. The
+// step's random draws are seeded from `rng`. Nothing carries over between calls, so
+// rules on the previous state never fire; use `compute_step` to run a simulation
+#[cfg(test)]
+pub fn step<R: Rng + ?Sized>(
+    grid: &CAGrid,
+    rules: &[TransitionRule],
+    states: &[CAState],
+    rng: &mut R,
+) -> Vec<u8> {
+    let settings = StepSettings {
+        no_match_policy: NoMatchPolicy::default(),
+        background_state_id: states.first().map_or(0, |s| s.id),
+        seed: rng.random(),
+        generation: 0,
+        deterministic: false,
+        eval_mode: RuleEvalMode::default(),
+    };
+    let mut buffers = StepBuffers::default();
+    compute_step(
+        grid,
+        rules,
+        &[],
+        states,
+        settings,
+        &mut NeighborCounts::default(),
+        &mut buffers,
+    );
+    buffers.next
+}
+
+// Computes the generation after `grid` into `buffers.next`, leaving `grid` itself
+// in `buffers.current`. `neighbor_counts` is brought up to date with `grid` first.
+// Standalone so it can run without the simulator, e.g. from the command line; pass
+// the same buffers every step so rules see the previous generation. Without states
+// every cell keeps its state. In the Margolus neighborhood `block_rules` are applied
+// instead of `rules`
+pub fn compute_step(
+    grid: &CAGrid,
+    rules: &[TransitionRule],
+    block_rules: &[BlockRule],
+    states: &[CAState],
+    settings: StepSettings,
+    neighbor_counts: &mut NeighborCounts,
+    buffers: &mut StepBuffers,
+) {
+    if grid.neighborhood == Neighborhood::Margolus {
+        compute_block_step(grid, block_rules, settings.generation, buffers);
+        return;
+    }
+
+    #[cfg(feature = "step-timing")]
+    let (started, capacity) = (std::time::Instant::now(), buffers.capacity());
+    let width = grid.width;
+    let grid_size = width * grid.height;
+
+    buffers.load(grid);
+    let current_grid_flat = &buffers.current;
+    let previous_grid_flat = buffers.has_previous.then_some(&buffers.previous);
+    let next_grid_flat = &mut buffers.next;
+    let Some(largest_id) = states.iter().map(|s| s.id as usize).max() else {
+        next_grid_flat.copy_from_slice(current_grid_flat);
+        return;
+    };
+
+    // Indexed by state id, so sized by the largest id rather than the number of states
+    neighbor_counts.refresh(grid, largest_id + 1);
+    let neighbor_counts = &neighbor_counts.counts;
+
+    // Histogram of the current generation, before any cell changes
+    let prepared = prepare_rules(
+        rules,
+        &grid.population(),
+        settings.deterministic,
+        settings.eval_mode,
+    );
+    let offsets = grid.neighborhood.offsets();
+    let next_state_for = |idx: usize| {
+        let rng = &mut LazyCellRng::new(settings.seed, settings.generation, idx);
+        let current_cell_state_id = current_grid_flat[idx];
+        let cell = (idx / width, idx % width);
+        // count(!self) and directional counts are only counted for cells whose
+        // rules ask for them
+        let neighbor_count = |state_id: u8, mask: DirectionMask| match state_id {
+            NOT_SELF_ID => grid.count_differing_neighbors(
+                &offsets,
+                cell.0,
+                cell.1,
+                current_cell_state_id,
+                mask,
+            ),
+            _ if mask != DirectionMask::ALL => {
+                grid.count_neighbors_masked(&offsets, cell.0, cell.1, state_id, mask)
+            }
+            _ => neighbor_counts
+                .get(state_id as usize)
+                .map_or(0, |counts| counts[idx]),
+        };
+        evaluate_cell(
+            rules,
+            &prepared,
+            cell,
+            current_cell_state_id,
+            previous_grid_flat.map(|previous| previous[idx]),
+            neighbor_count,
+            rng,
+        )
+        .map_or_else(
+            || {
+                settings
+                    .no_match_policy
+                    .fallback_state(current_cell_state_id, settings.background_state_id)
+            },
+            |rule_idx| rules[rule_idx].sample_next_state(rng),
+        )
+    };
+
+    if grid_size >= PARALLEL_STEP_THRESHOLD {
+        next_grid_flat
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(idx, cell)| *cell = next_state_for(idx));
+    } else {
+        for (idx, cell) in next_grid_flat.iter_mut().enumerate() {
+            *cell = next_state_for(idx);
+        }
+    }
+
+    #[cfg(feature = "step-timing")]
+    println!(
+        "[DEBUG] step {}: {:?}, buffers grew: {}",
+        settings.generation,
+        started.elapsed(),
+        buffers.capacity() != capacity
+    );
+}
+
+// Margolus step: the grid is cut into 2x2 blocks, shifted one cell down and right on
+// odd generations, and each block matching a rule's input is replaced by its output.
+// Blocks without a rule stay as they are, and so do blocks cut off by the grid's
+// edge unless a toroidal grid of even size lets them wrap around
+fn compute_block_step(
+    grid: &CAGrid,
+    block_rules: &[BlockRule],
+    generation: u64,
+    buffers: &mut StepBuffers,
+) {
+    buffers.load(grid);
+    buffers.next.copy_from_slice(&buffers.current);
+
+    let table: HashMap<[u8; 4], [u8; 4]> = block_rules
+        .iter()
+        .map(|rule| (rule.input, rule.output))
+        .collect();
+    let wraps = grid.boundary == BoundaryMode::Toroidal;
+    // Second row or column of a block starting at `i`, if the block fits
+    let second = |i: usize, len: usize| match i + 1 {
+        j if j < len => Some(j),
+        _ if wraps && len.is_multiple_of(2) => Some(0),
+        _ => None,
+    };
+
+    let (width, offset) = (grid.width, (generation % 2) as usize);
+    for r in (offset..grid.height).step_by(2) {
+        let Some(r2) = second(r, grid.height) else {
+            continue;
+        };
+        for c in (offset..width).step_by(2) {
+            let Some(c2) = second(c, width) else {
+                continue;
+            };
+            let block = [
+                r * width + c,
+                r * width + c2,
+                r2 * width + c,
+                r2 * width + c2,
+            ];
+            if let Some(output) = table.get(&block.map(|idx| buffers.current[idx])) {
+                for (idx, &state_id) in block.into_iter().zip(output) {
+                    buffers.next[idx] = state_id;
+                }
+            }
+        }
+    }
+}
+
+// Index of the first rule that fires for the cell at `cell` (row, col) in
+// `current_state_id`, if any. `previous_state_id` is the cell's state one generation
+// earlier, None when there is no such generation. `neighbor_count` returns how many
+// neighbors of the cell are in the given state. `prepared` comes from `prepare_rules`
+// and sets the order rules are tried in.
+pub fn evaluate_cell<R: Rng + ?Sized>(
+    rules: &[TransitionRule],
+    prepared: &[PreparedRule],
+    cell: (usize, usize),
+    current_state_id: u8,
+    previous_state_id: Option<u8>,
+    neighbor_count: impl Fn(u8, DirectionMask) -> u16,
+    rng: &mut R,
+) -> Option<usize> {
+    for prepared_rule in prepared.iter().filter(|p| p.enabled) {
+        let rule_idx = prepared_rule.rule_idx;
+        let Some(rule) = rules.get(rule_idx) else {
+            continue;
+        };
+        if rule.current_state_id != current_state_id {
+            continue;
+        }
+
+        if rule
+            .prev_state_id
+            .is_some_and(|prev| Some(prev) != previous_state_id)
+        {
+            continue;
+        }
+
+        if rule
+            .region
+            .is_some_and(|region| !region.contains(cell.0, cell.1))
+        {
+            continue;
+        }
+
+        // Certain rules skip the roll, so they never make the cell seed its RNG
+        let probability = prepared_rule.probability;
+        if probability < 1.0 && rng.random::<f32>() > probability {
+            continue;
+        }
+
+        // A condition missing its operator or threshold never holds; this
+        // runs inside the rayon closure, so it must not index out of bounds
+        let holds = |i: usize| match (
+            rule.neighbor_state_id_to_count.get(i),
+            rule.operator.get(i),
+            rule.neighbor_count_threshold.get(i),
+        ) {
+            (Some(&neighbor_state_id), Some(op), Some(&threshold)) => op.evaluate(
+                neighbor_count(neighbor_state_id, rule.direction_mask(i)),
+                threshold,
+            ),
+            _ => false,
+        };
+
+        // No conditions means the rule always matches
+        if prepared_rule.tree.evaluate(&rule.combiner, &holds) {
+            return Some(rule_idx);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::exemple::{life_like_rules, life_like_states};
+    use rand::rngs::StdRng;
+
+    // Grid from rows of '#' (alive, id 1) and '.' (dead, id 0)
+    fn life_grid(rows: &[&str]) -> CAGrid {
+        CAGrid {
+            width: rows[0].len(),
+            height: rows.len(),
+            cells: rows
+                .iter()
+                .map(|row| row.chars().map(|c| u8::from(c == '#')).collect())
+                .collect(),
+            neighborhood: Neighborhood::Moore,
+            boundary: BoundaryMode::Bounded,
+        }
+    }
+
+    fn step_life(rows: &[&str]) -> Vec<u8> {
+        let states = life_like_states();
+        let rules = life_like_rules(&states[0], &states[1], &[3], &[2, 3]);
+        step(
+            &life_grid(rows),
+            &rules,
+            &states,
+            &mut StdRng::seed_from_u64(0),
+        )
+    }
+
+    #[test]
+    fn blinker_turns_horizontal() {
+        let next = step_life(&[".....", "..#..", "..#..", "..#..", "....."]);
+        let expected = life_grid(&[".....", ".....", ".###.", ".....", "....."]);
+        assert_eq!(next, expected.cells.concat());
+    }
+
+    #[test]
+    fn glider_moves_one_phase() {
+        let next = step_life(&["......", "..#...", "...#..", ".###..", "......", "......"]);
+        let expected = life_grid(&["......", "......", ".#.#..", "..##..", "..#...", "......"]);
+        assert_eq!(next, expected.cells.concat());
+    }
+
+    // Life's rules overlap: a live cell with two neighbors matches both "survive on 2"
+    // and the later, unconditional "die". Only the evaluation order decides it
+    #[test]
+    fn eval_mode_picks_between_overlapping_rules() {
+        let states = life_like_states();
+        let rules = life_like_rules(&states[0], &states[1], &[3], &[2, 3]);
+        let grid = life_grid(&["...", "###", "..."]);
+        let center_after = |eval_mode| {
+            let settings = StepSettings {
+                no_match_policy: NoMatchPolicy::default(),
+                background_state_id: 0,
+                seed: 0,
+                generation: 0,
+                deterministic: true,
+                eval_mode,
+            };
+            let mut buffers = StepBuffers::default();
+            compute_step(
+                &grid,
+                &rules,
+                &[],
+                &states,
+                settings,
+                &mut NeighborCounts::default(),
+                &mut buffers,
+            );
+            buffers.next[4]
+        };
+        assert_eq!(center_after(RuleEvalMode::FirstMatch), 1);
+        assert_eq!(center_after(RuleEvalMode::LastMatch), 0);
+    }
+}
@@ -1,14 +1,35 @@
+use crate::app::export::{self, ImageExport};
+use crate::app::simulation::{
+    cell_rng, compute_step, evaluate_cell, prepare_rules, StepBuffers, StepSettings,
+};
+use crate::app::sweep::{results_csv, shannon_entropy, sweep_values, SweepResult, SweepRun};
 use crate::messages::Message;
-use crate::state::ca_grid::{CAGrid, Neighborhood};
+use crate::state::ca_grid::{
+    line_cells, BoundaryMode, CAGrid, DirectionMask, NeighborCounts, Neighborhood,
+};
 use crate::state::ca_state::CAState;
-use crate::state::exemple::ExampleModel;
-use crate::state::transition_rule::{ConditionCombiner, RelationalOperator, TransitionRule};
+use crate::state::exemple::{
+    ant_state_id, brians_brain_rules, brians_brain_states, forest_fire_rules, forest_fire_states,
+    langtons_ant_rules, langtons_ant_states, life_like_rules, life_like_states,
+    majority_vote_rules, majority_vote_states, parse_count_list, parse_life_like, ExampleModel,
+    ForestFireParams,
+};
+use crate::state::pattern::{parse_cells, parse_rle, Pattern, PatternPlacement};
+use crate::state::session::{ExperimentConfig, SavedGrid, Session, ViewState};
+use crate::state::transition_rule::{
+    billiard_ball_rules, validate_groups, BlockRule, CellRegion, ConditionCombiner,
+    GlobalCondition, NoMatchPolicy, RelationalOperator, RuleEvalMode, TransitionRule, NOT_SELF_ID,
+    NOT_SELF_NAME,
+};
+use crate::view::modal::{self, ModalLayer};
 use iced::widget::canvas::Cache;
 use iced::widget::{button, column, row, text};
 use iced::{executor, theme, Application, Color, Command, Element, Point, Subscription, Theme};
-use rand::Rng;
-use rayon::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
 use std::time::{Duration, Instant};
 
 pub struct ConditionForm {
@@ -16,22 +37,223 @@ pub struct ConditionForm {
     pub operator: Option<RelationalOperator>,
     pub threshold: String,
     pub combiner: Option<ConditionCombiner>,
+    pub directions: DirectionMask,
+    pub errors: Vec<String>, // Shown under this condition's row
 }
 
+// One weighted next state of a stochastic rule being created
+pub struct OutcomeForm {
+    pub state: Option<CAState>,
+    pub probability: String,
+    pub error: Option<String>,
+}
+
+// Editable parameter of a configurable example model
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExampleParam {
+    ForestBurnOut,
+    ForestSpread,
+    ForestGrowth,
+    ForestLightning,
+    LifeBirth,
+    LifeSurvival,
+}
+
+impl std::fmt::Display for ExampleParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExampleParam::ForestBurnOut => write!(f, "burn_out"),
+            ExampleParam::ForestSpread => write!(f, "spread"),
+            ExampleParam::ForestGrowth => write!(f, "growth"),
+            ExampleParam::ForestLightning => write!(f, "lightning"),
+            ExampleParam::LifeBirth => write!(f, "birth"),
+            ExampleParam::LifeSurvival => write!(f, "survival"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TabId {
     Definition,
     Simulation,
 }
 
+// What a click on the canvas does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanvasTool {
+    Paint,
+    Annotate,
+    Poke,      // Apply the rules to a single cell
+    Line,      // Two clicks paint a straight line between the cells
+    Rectangle, // Drag from corner to corner, painted on release
+}
+
+impl CanvasTool {
+    pub const ALL: [CanvasTool; 5] = [
+        CanvasTool::Paint,
+        CanvasTool::Line,
+        CanvasTool::Rectangle,
+        CanvasTool::Annotate,
+        CanvasTool::Poke,
+    ];
+}
+
+impl std::fmt::Display for CanvasTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CanvasTool::Paint => write!(f, "Paint"),
+            CanvasTool::Annotate => write!(f, "Annotate"),
+            CanvasTool::Poke => write!(f, "Poke (step one cell)"),
+            CanvasTool::Line => write!(f, "Line"),
+            CanvasTool::Rectangle => write!(f, "Rectangle"),
+        }
+    }
+}
+
+// How the width of the lines between cells reacts to zooming
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderMode {
+    ScaleWithZoom, // Thicker when zoomed out, thinner when zoomed in, within limits
+    FixedScreen,   // Always the configured number of screen pixels
+}
+
+impl BorderMode {
+    pub const ALL: [BorderMode; 2] = [BorderMode::ScaleWithZoom, BorderMode::FixedScreen];
+}
+
+impl std::fmt::Display for BorderMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BorderMode::ScaleWithZoom => write!(f, "Scale with zoom"),
+            BorderMode::FixedScreen => write!(f, "Fixed screen pixels"),
+        }
+    }
+}
+
+// Dialog shown over the whole window until the user answers it
+#[derive(Debug, Clone)]
+pub enum Modal {
+    Info {
+        title: String,
+        message: String,
+    },
+    // `on_confirm` is handled as if sent by the UI once the user presses OK
+    Confirm {
+        title: String,
+        message: String,
+        on_confirm: Box<Message>,
+    },
+}
+
+// What makes RunUntil stop, besides the MAX_RUN_UNTIL_STEPS safety bound
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopCondition {
+    Population(GlobalCondition), // e.g. total(Tree) == 0 for extinction
+    Stable,                      // A step leaves every cell as it was
+    Cycle,                       // The grid comes back to an earlier generation
+    Generation(u64),             // The generation counter reaches this value
+}
+
+// Kinds of StopCondition, for the picker; the form fills in the rest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopConditionKind {
+    Population,
+    Stable,
+    Cycle,
+    Generation,
+}
+
+impl StopConditionKind {
+    pub const ALL: [StopConditionKind; 4] = [
+        StopConditionKind::Population,
+        StopConditionKind::Stable,
+        StopConditionKind::Cycle,
+        StopConditionKind::Generation,
+    ];
+}
+
+impl std::fmt::Display for StopConditionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopConditionKind::Population => write!(f, "Population reaches"),
+            StopConditionKind::Stable => write!(f, "Grid stops changing"),
+            StopConditionKind::Cycle => write!(f, "Grid repeats"),
+            StopConditionKind::Generation => write!(f, "Generation"),
+        }
+    }
+}
+
+// What ImportRules does with the model that is already loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    Replace,
+    Merge,
+}
+
+impl ImportMode {
+    pub const ALL: [ImportMode; 2] = [ImportMode::Replace, ImportMode::Merge];
+}
+
+impl std::fmt::Display for ImportMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportMode::Replace => write!(f, "Replace model"),
+            ImportMode::Merge => write!(f, "Merge into model"),
+        }
+    }
+}
+
 const DEFAULT_GRID_WIDTH: usize = 50;
 const DEFAULT_GRID_HEIGHT: usize = 40;
 const DEFAULT_STATE_ID: u8 = 1;
+const DEFAULT_BACKGROUND_STATE_ID: u8 = 0;
+const DEFAULT_AUTOSAVE_INTERVAL_SECS: u64 = 60;
+const DEFAULT_PAN_STEP_PX: f32 = 20.0;
+// "Find Interesting Seed": random grids tried, and steps each one is run for
+const SEED_SEARCH_CANDIDATES: usize = 8;
+const SEED_SEARCH_STEPS: usize = 20;
+// Oldest GIF frames are dropped past this many, to bound memory
+const MAX_GIF_FRAMES: usize = 300;
+// Generations kept for Step Back
+const MAX_STEP_HISTORY: usize = 100;
+// Model edits that can be undone
+const MAX_UNDO_DEPTH: usize = 50;
+// Generations shown in the population chart, which scrolls once it is full
+pub const MAX_POPULATION_HISTORY: usize = 300;
+// Width of the lines between cells, in screen pixels
+const DEFAULT_BORDER_WIDTH: f32 = 1.5;
+const MAX_BORDER_WIDTH: f32 = 5.0;
+const DEFAULT_SWEEP_STEPS: usize = 100;
+const MAX_SWEEP_VALUES: usize = 1000;
+// RunUntil gives up after this many steps if its condition never holds
+pub const MAX_RUN_UNTIL_STEPS: u64 = 10_000;
+pub const MAX_BRUSH_SIZE: u8 = 15;
+const KEYBOARD_ZOOM_STEP: f32 = 0.1;
+// Ring and Moore radius inputs' starting values; see MAX_RING_RADIUS for the caps
+const DEFAULT_RING_INNER: u8 = 2;
+const DEFAULT_RING_OUTER: u8 = 3;
+const DEFAULT_MOORE_RADIUS: u8 = 3;
+const MIN_AUTOSAVE_INTERVAL_SECS: u64 = 5;
+const AUTOSAVE_FILE_NAME: &str = "cellular_automata_autosave.json";
+pub const TRANSITION_FLASH_STEPS: u8 = 3; // Generations a transition highlight takes to fade
+
+// Drops a `#` comment (outside quoted state names) and surrounding whitespace
+fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    for (i, ch) in line.char_indices() {
+        match ch {
+            '\'' => in_quotes = !in_quotes,
+            '#' if !in_quotes => return line[..i].trim(),
+            _ => {}
+        }
+    }
+    line.trim()
+}
 
 fn parse_rule(line: &str, states: &[CAState]) -> Result<TransitionRule, String> {
     // println!("\n[DEBUG] Parsing rule line: {}", line);
 
-    let line = line.trim();
+    let line = strip_comment(line);
 
     if !line.starts_with("IF current is") {
         return Err("Line does not start with IF current is".into());
@@ -93,17 +315,68 @@ fn parse_rule(line: &str, states: &[CAState]) -> Result<TransitionRule, String>
         (then_part.to_string(), 1.0)
     };
 
-    // --- extrai next state (entre aspas) ---
-    let next_name = if let Some(start) = then_core.find('\'') {
-        if let Some(rel_end) = then_core[start + 1..].find('\'') {
-            then_core[start + 1..start + 1 + rel_end].trim().to_string()
-        } else {
-            return Err("Malformed next state (missing closing quote)".into());
+    // --- extrai região (se houver) ---
+    let region = if let Some(region_pos) = then_part.find("IN REGION") {
+        let region_str = then_part[region_pos + "IN REGION".len()..]
+            .trim()
+            .trim_start_matches('(');
+        let region_str = match region_str.find(')') {
+            Some(end) => &region_str[..end],
+            None => return Err("Malformed region (missing closing parenthesis)".into()),
+        };
+        let bounds: Vec<usize> = region_str
+            .split(',')
+            .map(|v| v.trim().parse::<usize>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("Invalid region bounds: ({})", region_str))?;
+        if bounds.len() != 4 {
+            return Err(format!(
+                "Region needs 4 bounds (row, col, row, col): ({})",
+                region_str
+            ));
         }
+        Some(CellRegion::from_corners(
+            bounds[0], bounds[1], bounds[2], bounds[3],
+        ))
+    } else {
+        None
+    };
+
+    // --- extrai condição global (se houver): WHEN total(<estado>) <op> <valor> ---
+    let global_condition = if let Some(when_pos) = then_part.find("WHEN total(") {
+        let rest = &then_part[when_pos + "WHEN total(".len()..];
+        let close = rest
+            .find(')')
+            .ok_or("Malformed global condition (missing closing parenthesis)")?;
+        let state_name = rest[..close].trim().to_string();
+        let mut tokens = rest[close + 1..].split_whitespace();
+        let operator = tokens
+            .next()
+            .and_then(RelationalOperator::from_symbol)
+            .ok_or_else(|| format!("Invalid operator in WHEN total({})", state_name))?;
+        let threshold = tokens
+            .next()
+            .and_then(|t| t.parse::<u32>().ok())
+            .ok_or_else(|| format!("Invalid threshold in WHEN total({})", state_name))?;
+        let state_id = states
+            .iter()
+            .find(|s| s.name == state_name)
+            .map(|s| s.id)
+            .ok_or_else(|| format!("Unknown state in global condition: {}", state_name))?;
+        Some(GlobalCondition {
+            state_id,
+            state_name,
+            operator,
+            threshold,
+        })
     } else {
-        return Err("Malformed next state (missing opening quote)".into());
+        None
     };
 
+    // --- extrai next state (entre aspas), ou resultados com peso: 'A' (2), 'B' (1) ---
+    let outcomes = parse_next_outcomes(&then_core)?;
+    let next_name = outcomes[0].0.clone();
+
     // println!("[DEBUG] next_name = '{}'", next_name);
 
     // --- extrai current state ---
@@ -134,12 +407,57 @@ fn parse_rule(line: &str, states: &[CAState]) -> Result<TransitionRule, String>
         .map(|s| s.id)
         .ok_or_else(|| format!("Unknown next state: {}", next_name))?;
 
+    // Weights are normalized, so "(2), (1)" reads the same as "(0.667), (0.333)"
+    let mut next_distribution: Vec<(u8, f32)> = Vec::new();
+    let mut next_distribution_names: Vec<String> = Vec::new();
+    if outcomes.iter().any(|(_, weight)| weight.is_some()) {
+        let mut weights = Vec::new();
+        for (name, weight) in &outcomes {
+            let weight = weight.ok_or_else(|| format!("Outcome '{}' has no weight", name))?;
+            let state_id = states
+                .iter()
+                .find(|s| &s.name == name)
+                .map(|s| s.id)
+                .ok_or_else(|| format!("Unknown next state: {}", name))?;
+            next_distribution.push((state_id, 0.0));
+            next_distribution_names.push(name.clone());
+            weights.push(weight);
+        }
+        let total: f32 = weights.iter().sum();
+        if total <= 0.0 {
+            return Err("Outcome weights add up to 0".into());
+        }
+        for ((_, p), weight) in next_distribution.iter_mut().zip(weights) {
+            *p = weight / total;
+        }
+    } else if outcomes.len() > 1 {
+        return Err("Several next states need a weight each: 'A' (1), 'B' (1)".into());
+    }
+
+    // --- estado anterior opcional: AND previous is 'Name' ---
+    let (prev_state_id, prev_state_name, cond_substr) =
+        match cond_substr.strip_prefix("AND previous is '") {
+            Some(rest) => {
+                let (name, after) = rest
+                    .split_once('\'')
+                    .ok_or("Malformed previous state (missing closing quote)")?;
+                let id = states
+                    .iter()
+                    .find(|s| s.name == name)
+                    .map(|s| s.id)
+                    .ok_or_else(|| format!("Unknown previous state: {}", name))?;
+                (Some(id), name.to_string(), after.trim().to_string())
+            }
+            None => (None, String::new(), cond_substr),
+        };
+
     // --- parse conditions (igual ao seu código atual) ---
     let mut neighbor_state_id_to_count: Vec<u8> = Vec::new();
-    let mut neighbor_count_threshold: Vec<u8> = Vec::new();
+    let mut neighbor_count_threshold: Vec<u16> = Vec::new();
     let mut operator: Vec<RelationalOperator> = Vec::new();
     let mut combiner: Vec<ConditionCombiner> = Vec::new();
     let mut neighbor_state_names: Vec<String> = Vec::new();
+    let mut directions: Vec<DirectionMask> = Vec::new();
 
     let cond_trimmed = if cond_substr.starts_with("AND") {
         cond_substr[3..].trim().to_string()
@@ -147,25 +465,53 @@ fn parse_rule(line: &str, states: &[CAState]) -> Result<TransitionRule, String>
         cond_substr.trim().to_string()
     };
 
+    let mut groups: Vec<(usize, usize)> = Vec::new();
     if !cond_trimmed.is_empty() && cond_trimmed != "(no conditions)" {
-        let tokens: Vec<&str> = cond_trimmed.split_whitespace().collect();
+        // Parentheses may touch the neighboring token: "(count(A) == 1" or "2)"
+        let mut tokens: Vec<&str> = Vec::new();
+        for raw in cond_trimmed.split_whitespace() {
+            let mut tok = raw;
+            while let Some(rest) = tok.strip_prefix('(') {
+                tokens.push("(");
+                tok = rest;
+            }
+            let mut closes = 0;
+            if !tok.contains('(') {
+                while let Some(rest) = tok.strip_suffix(')') {
+                    closes += 1;
+                    tok = rest;
+                }
+            }
+            if !tok.is_empty() {
+                tokens.push(tok);
+            }
+            tokens.extend(std::iter::repeat_n(")", closes));
+        }
+        let mut open_groups: Vec<usize> = Vec::new();
         //println!("[DEBUG] condition tokens = {:?}", tokens);
 
         let mut i = 0usize;
         while i < tokens.len() {
             let tok = tokens[i];
             if tok.starts_with("count(") {
-                let name = tok
-                    .trim_start_matches("count(")
-                    .trim_end_matches(')')
-                    .to_string();
+                let counted = tok.trim_start_matches("count(").trim_end_matches(')');
+                // count(Name@N,NE) only looks at neighbors in those directions
+                let (name, mask) = match counted.rsplit_once('@') {
+                    Some((name, mask)) => (name.to_string(), mask.parse::<DirectionMask>()?),
+                    None => (counted.to_string(), DirectionMask::ALL),
+                };
                 neighbor_state_names.push(name.clone());
+                directions.push(mask);
 
-                let neighbor_id = states
-                    .iter()
-                    .find(|s| s.name == name)
-                    .map(|s| s.id)
-                    .unwrap_or(0u8);
+                let neighbor_id = if name == NOT_SELF_NAME {
+                    NOT_SELF_ID
+                } else {
+                    states
+                        .iter()
+                        .find(|s| s.name == name)
+                        .map(|s| s.id)
+                        .unwrap_or(0u8)
+                };
                 neighbor_state_id_to_count.push(neighbor_id);
 
                 if i + 1 < tokens.len() {
@@ -187,7 +533,7 @@ fn parse_rule(line: &str, states: &[CAState]) -> Result<TransitionRule, String>
                 if i + 2 < tokens.len() {
                     let thr_tok = tokens[i + 2];
                     let thr_clean = thr_tok.trim_end_matches(',').trim();
-                    let thr = thr_clean.parse::<u8>().unwrap_or(0u8);
+                    let thr = thr_clean.parse::<u16>().unwrap_or(0);
                     neighbor_count_threshold.push(thr);
                 } else {
                     neighbor_count_threshold.push(0);
@@ -208,13 +554,31 @@ fn parse_rule(line: &str, states: &[CAState]) -> Result<TransitionRule, String>
                         combiner.push(ConditionCombiner::Xor);
                         i += 1;
                     }
+                    "(" => {
+                        open_groups.push(neighbor_state_id_to_count.len());
+                        i += 1;
+                    }
+                    ")" => {
+                        let start = open_groups.pop().ok_or("Unbalanced ')' in conditions")?;
+                        if start == neighbor_state_id_to_count.len() {
+                            return Err("Empty '( )' group in conditions".into());
+                        }
+                        groups.push((start, neighbor_state_id_to_count.len() - 1));
+                        i += 1;
+                    }
                     _ => i += 1,
                 }
             }
         }
+        if !open_groups.is_empty() {
+            return Err("Unclosed '(' in conditions".into());
+        }
+    }
+    if directions.iter().all(|&mask| mask == DirectionMask::ALL) {
+        directions.clear();
     }
 
-    Ok(TransitionRule {
+    let rule = TransitionRule {
         current_state_id,
         neighbor_state_id_to_count,
         operator,
@@ -225,7 +589,180 @@ fn parse_rule(line: &str, states: &[CAState]) -> Result<TransitionRule, String>
         neighbor_state_names,
         next_state_name: next_name.to_string(),
         probability,
-    })
+        region,
+        next_distribution,
+        next_distribution_names,
+        groups,
+        global_condition,
+        directions,
+        prev_state_id,
+        prev_state_name,
+    };
+    rule.validate()?;
+    Ok(rule)
+}
+
+// Next states after "THEN next is": one quoted name, or several separated by commas,
+// each followed by its weight in parentheses. Stops at the first token that isn't an
+// outcome, such as IN REGION when WITH PROB is left out
+fn parse_next_outcomes(text: &str) -> Result<Vec<(String, Option<f32>)>, String> {
+    let mut outcomes = Vec::new();
+    let mut rest = text.trim();
+    loop {
+        let Some(quoted) = rest.strip_prefix('\'') else {
+            if outcomes.is_empty() {
+                return Err("Malformed next state (missing opening quote)".into());
+            }
+            break;
+        };
+        let end = quoted
+            .find('\'')
+            .ok_or("Malformed next state (missing closing quote)")?;
+        let name = quoted[..end].trim().to_string();
+        rest = quoted[end + 1..].trim_start();
+
+        let weight = match rest.strip_prefix('(') {
+            Some(inner) => {
+                let close = inner
+                    .find(')')
+                    .ok_or_else(|| format!("Missing ')' after the weight of '{}'", name))?;
+                let raw = inner[..close].trim();
+                let weight = raw
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|w| w.is_finite() && *w >= 0.0)
+                    .ok_or_else(|| format!("Invalid weight for '{}': {}", name, raw))?;
+                rest = inner[close + 1..].trim_start();
+                Some(weight)
+            }
+            None => None,
+        };
+        outcomes.push((name, weight));
+
+        match rest.strip_prefix(',') {
+            Some(after) => rest = after.trim_start(),
+            None => break,
+        }
+    }
+    Ok(outcomes)
+}
+
+// Probability as written to rule files: three decimals, trailing zeros trimmed
+// (keeping one), when that reads back as the same value. Anything finer, like
+// 0.0004, is written in full so it survives the round trip
+fn format_export_probability(p: f32) -> String {
+    let fixed = format!("{:.3}", p);
+    let trimmed = fixed.trim_end_matches('0');
+    let short = if trimmed.ends_with('.') {
+        format!("{}0", trimmed)
+    } else {
+        trimmed.to_string()
+    };
+    if short.parse::<f32>() == Ok(p) {
+        short
+    } else {
+        // Display prints the shortest text that parses back to `p`
+        p.to_string()
+    }
+}
+
+// Lowest id none of `states` uses, if any is left
+fn free_state_id(states: &[CAState]) -> Option<u8> {
+    let mut new_id = 0u8;
+    let mut used_ids: Vec<u8> = states.iter().map(|s| s.id).collect();
+    used_ids.sort_unstable();
+    for id in used_ids {
+        if id == new_id {
+            new_id += 1;
+        } else if id > new_id {
+            break;
+        }
+    }
+    (new_id != NOT_SELF_ID).then_some(new_id)
+}
+
+// Asks for a saved JSON grid and loads it. `None` when the dialog was cancelled,
+// otherwise the grid or why the file was rejected
+fn pick_grid_file() -> Option<Result<SavedGrid, String>> {
+    let path = rfd::FileDialog::new()
+        .add_filter("JSON", &["json"])
+        .pick_file()?;
+    let loaded = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read file: {}", e))
+        .and_then(|data| {
+            serde_json::from_str::<SavedGrid>(&data)
+                .map_err(|e| format!("Failed to parse grid JSON: {}", e))
+        })
+        .and_then(|saved| {
+            saved
+                .grid
+                .validate_dimensions()
+                .map(|()| saved)
+                .map_err(|e| format!("Rejected malformed grid: {}", e))
+        });
+    Some(loaded)
+}
+
+// Recovery file written by the autosave timer
+fn autosave_path() -> std::path::PathBuf {
+    std::env::temp_dir().join(AUTOSAVE_FILE_NAME)
+}
+
+// A step handed to another thread, owning copies of everything it reads
+struct StepJob {
+    grid: CAGrid,
+    rules: Vec<TransitionRule>,
+    block_rules: Vec<BlockRule>,
+    states: Vec<CAState>,
+    settings: StepSettings,
+    model_revision: u64,
+    neighbor_counts: NeighborCounts,
+    buffers: StepBuffers,
+}
+
+// Finished background step: `buffers.current` is the grid it started from and
+// `buffers.next` the generation after it
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    width: usize,
+    model_revision: u64, // The simulator's revision when the step started
+    neighbor_counts: NeighborCounts,
+    buffers: StepBuffers,
+}
+
+// The model before a definition tab edit, restored by Undo and Redo. The grid is only
+// kept for edits that change it too, so undoing a rule doesn't undo painting
+#[derive(Debug, Clone)]
+struct ModelSnapshot {
+    states: Vec<CAState>,
+    rules: Vec<TransitionRule>,
+    background_state_id: u8,
+    grid: Option<CAGrid>,
+}
+
+// Runs `job` on its own thread, like the image exports
+async fn run_step_in_background(mut job: StepJob) -> Result<StepResult, String> {
+    let (sender, receiver) = iced::futures::channel::oneshot::channel();
+    std::thread::spawn(move || {
+        compute_step(
+            &job.grid,
+            &job.rules,
+            &job.block_rules,
+            &job.states,
+            job.settings,
+            &mut job.neighbor_counts,
+            &mut job.buffers,
+        );
+        let _ = sender.send(StepResult {
+            width: job.grid.width,
+            model_revision: job.model_revision,
+            neighbor_counts: job.neighbor_counts,
+            buffers: job.buffers,
+        });
+    });
+    receiver
+        .await
+        .map_err(|_| "Step thread stopped unexpectedly".to_string())
 }
 
 pub struct CASimulator {
@@ -233,6 +770,10 @@ pub struct CASimulator {
     pub active_tab: TabId,
     pub states: Vec<CAState>,
     pub rules: Vec<TransitionRule>,
+    pub block_rules: Vec<BlockRule>, // Replace `rules` in the Margolus neighborhood
+    pub background_state_id: u8,     // Used when the grid can't be filled by weights
+    pub no_match_policy: NoMatchPolicy,
+    pub rule_eval_mode: RuleEvalMode,
     pub grid: CAGrid,
     pub grid_cache: Cache,
     pub simulation_timer: Option<Instant>,
@@ -242,6 +783,21 @@ pub struct CASimulator {
     pub offset: Cell<Point>,
     pub right_mouse_pressed: Cell<bool>, // panning
     pub last_mouse_pos: RefCell<Option<Point>>,
+    pub show_transition_flashes: bool,
+    pub transition_flash: Vec<u8>, // Flat, remaining highlight steps per cell
+    pub cell_ages: Vec<u32>,       // Flat, steps each cell has held its current state
+    pub step_history: VecDeque<Vec<Vec<u8>>>, // Cells before each recent step, newest last
+    undo_stack: VecDeque<ModelSnapshot>, // Newest last
+    redo_stack: Vec<ModelSnapshot>,
+    pub stop_at_generation: Option<u64>, // Running pauses on reaching it
+    pub stop_at_input: String,
+    pub stop_on_stable: bool, // Running pauses once the grid is a still life or period-2 cycle
+    pub stable_report: Option<String>,
+    pub population_history: VecDeque<Vec<usize>>, // Cells per state id after each step, newest last
+    pub show_age_shading: bool,
+    pub border_width: f32, // Screen pixels at zoom 1; 0 hides the lines between cells
+    pub border_width_input: String,
+    pub border_mode: BorderMode,
 
     // --- UI Input State ---
     // State creation
@@ -249,22 +805,127 @@ pub struct CASimulator {
     pub new_state_color_r: String, // Store as string for input, parse later
     pub new_state_color_g: String,
     pub new_state_color_b: String,
+    pub show_rgb_fields: bool, // Type the new state's color instead of picking it
 
     // Rule creation
     pub rule_form_current_state: Option<CAState>,
     pub rule_form_next_state: Option<CAState>,
     pub rule_form_error: Option<String>,
+    pub state_form_error: Option<String>,
+    // A state's name as typed in the list while it differs from the name in use (by
+    // state id): invalid, or untrimmed. Rules keep the last valid name meanwhile
+    pub state_name_edit: Option<(u8, String)>,
+    // Per-field errors shown next to the widget that caused them
+    pub rule_form_current_error: Option<String>,
+    pub rule_form_next_error: Option<String>,
+    pub rule_form_probability_error: Option<String>,
+    pub rule_form_region_error: Option<String>,
+    pub rule_form_outcomes: Vec<OutcomeForm>, // Replaces the single next state when not empty
+    pub rule_form_outcomes_error: Option<String>,
     pub rule_form_conditions: Vec<ConditionForm>,
+    pub form_warning: Option<String>, // Unsaved or discarded rule form, shown above the tabs
+    pub rule_form_groups: Vec<(usize, usize)>, // Condition index ranges in parentheses
+    pub rule_form_group_inputs: [String; 2], // First and last condition, 1-based
+    pub rule_form_group_error: Option<String>,
+    pub import_mode: ImportMode,
+    pub import_errors: Vec<String>, // Rule lines skipped by the last import/paste
+    pub model_header: Vec<String>,  // Leading `#` comments of the imported file, re-exported
     pub rule_form_probability: String,
+    pub probability_as_percent: bool, // Show/enter probabilities as 0-100%
+    pub rule_form_region_enabled: bool,
+    // Global population condition: (state, operator, threshold) over the whole grid
+    pub rule_form_global_enabled: bool,
+    pub rule_form_global_state: Option<CAState>,
+    pub rule_form_global_operator: Option<RelationalOperator>,
+    pub rule_form_global_threshold: String,
+    pub rule_form_global_error: Option<String>,
+    // State the cell must have been in one generation earlier
+    pub rule_form_prev_enabled: bool,
+    pub rule_form_prev_state: Option<CAState>,
+    pub rule_form_prev_error: Option<String>,
+    pub rule_form_region: [String; 4], // row_min, col_min, row_max, col_max
+    pub editing_rule_index: Option<usize>, // Rule that Add Rule replaces instead of appending
 
     // Grid dimensions input
     pub grid_width_input: String,
     pub grid_height_input: String,
+    pub preserve_grid_contents: bool, // Apply Size keeps the overlapping cells
+
+    // Session autosave
+    pub autosave_enabled: bool,
+    pub autosave_interval_input: String, // Seconds
+    pub pan_step_input: String,          // Pixels per arrow key press
+    pub recovery_available: bool,
+    pub seed: u64, // Seeds random grids and the random draws of every step
+    pub seed_input: String,
+    pub force_deterministic: bool, // Step as if every rule had probability 1.0
+    pub grid_seed: Option<u64>,    // Seed the current grid was generated from, if it was
+    pub pattern_placement: PatternPlacement, // Where imported pattern files land
+    pub neighbor_counts: NeighborCounts, // Carried between steps, see `NeighborCounts`
+    pub step_buffers: StepBuffers,
+    pub step_in_flight: bool, // A timer step is running in the background
+    // Bumped by every edit that changes how the next step is computed, so a background
+    // step started before it is known to be stale
+    model_revision: u64,
+    // Parameter sweep over the Forest Fire preset
+    pub sweep_param: ExampleParam,
+    pub sweep_inputs: [String; 4], // From, to, number of values, steps per run
+    pub sweep_error: Option<String>,
+    pub sweep: Option<SweepRun>, // Kept after it finishes so its results can be exported
+    pub sweep_running: bool,
+    pub active_modal: Option<Modal>,
+    // Run until a condition holds
+    pub run_until_kind: StopConditionKind,
+    pub run_until_state: Option<CAState>,
+    pub run_until_operator: Option<RelationalOperator>,
+    pub run_until_value: String, // Population threshold or target generation
+    pub run_until_report: Option<String>,
+    // Parameters of the configurable example models
+    pub selected_example: Option<ExampleModel>,
+    pub forest_burn_out_input: String,
+    pub forest_spread_input: String,
+    pub forest_growth_input: String,
+    pub forest_lightning_input: String,
+    pub life_birth_input: String,    // e.g. "3"
+    pub life_survival_input: String, // e.g. "2,3"
+    pub example_params_error: Option<String>,
+    pub life_like_input: String, // B/S notation, e.g. "B36/S23"
+    pub life_like_error: Option<String>,
+    pub ring_inner_input: String,
+    pub ring_outer_input: String,
+    pub moore_radius_input: String,
+    pub generation: u64, // Steps taken since the grid was last generated or loaded
+    pub export_caption: bool,
+    pub gif_recording: bool,
+    pub gif_frames: Vec<Vec<u8>>, // Row-major cells of each recorded generation
+    pub export_in_progress: bool,
+    pub export_status: Option<String>,
+    pub comparison_grid: Option<CAGrid>, // Second grid for the diff overlay
+    pub show_comparison_diff: bool,
+    pub seed_search_report: Option<String>,
+    pub model_issues: Option<Vec<String>>, // Last "Check Model" result, until dismissed
 
     // For picking next state on canvas click
+    pub canvas_tool: CanvasTool,
+    pub poke_report: Option<String>,
     pub selected_paint_state_id: u8,
     pub mouse_pressed: Cell<bool>,
+    pub pause_while_painting: bool,
+    pub brush_size: usize, // Side of the painted square, always odd
     pub last_painted_cell: RefCell<Option<(usize, usize)>>,
+    pub hovered_cell: Cell<Option<(usize, usize)>>, // Cell under the cursor, for the readout
+    pub line_anchor: Option<(usize, usize)>,        // First click of the Line tool
+    pub rect_start: Cell<Option<(usize, usize)>>,   // Corner where the rectangle drag began
+    pub rect_filled: bool,                          // Otherwise only the outline is painted
+
+    // Cell annotations (text labels anchored to grid coordinates)
+    pub annotations: HashMap<(usize, usize), String>,
+    pub selected_annotation_cell: Option<(usize, usize)>,
+    pub annotation_input: String,
+
+    // Bulk replace / swap
+    pub bulk_from_state: Option<CAState>,
+    pub bulk_to_state: Option<CAState>,
 }
 
 impl Application for CASimulator {
@@ -288,11 +949,14 @@ impl Application for CASimulator {
                 weight: 5,
             },
         ];
-        let grid = CAGrid::new(
+        let seed: u64 = rand::random();
+        let grid = CAGrid::new_with_rng(
             DEFAULT_GRID_WIDTH,
             DEFAULT_GRID_HEIGHT,
             initial_states.clone(),
             Neighborhood::Moore,
+            DEFAULT_BACKGROUND_STATE_ID,
+            &mut StdRng::seed_from_u64(seed),
         );
         let initial_rules = vec![
             // Alive -> Alive (if neighbors == 2)
@@ -307,6 +971,14 @@ impl Application for CASimulator {
                 neighbor_state_names: vec!["Alive".into()],
                 next_state_name: "Alive".into(),
                 probability: 1.0,
+                region: None,
+                next_distribution: Vec::new(),
+                next_distribution_names: Vec::new(),
+                groups: Vec::new(),
+                global_condition: None,
+                directions: Vec::new(),
+                prev_state_id: None,
+                prev_state_name: String::new(),
             },
             // Alive -> Alive (if neighbors == 3)
             TransitionRule {
@@ -320,6 +992,14 @@ impl Application for CASimulator {
                 neighbor_state_names: vec!["Alive".into()],
                 next_state_name: "Alive".into(),
                 probability: 1.0,
+                region: None,
+                next_distribution: Vec::new(),
+                next_distribution_names: Vec::new(),
+                groups: Vec::new(),
+                global_condition: None,
+                directions: Vec::new(),
+                prev_state_id: None,
+                prev_state_name: String::new(),
             },
             // Dead -> Alive (if neighbors == 3)
             TransitionRule {
@@ -333,6 +1013,14 @@ impl Application for CASimulator {
                 neighbor_state_names: vec!["Alive".into()],
                 next_state_name: "Alive".into(),
                 probability: 1.0,
+                region: None,
+                next_distribution: Vec::new(),
+                next_distribution_names: Vec::new(),
+                groups: Vec::new(),
+                global_condition: None,
+                directions: Vec::new(),
+                prev_state_id: None,
+                prev_state_name: String::new(),
             },
             // Alive -> Dead (if neighbors < 2)
             TransitionRule {
@@ -346,6 +1034,14 @@ impl Application for CASimulator {
                 neighbor_state_names: vec!["Alive".into()],
                 next_state_name: "Dead".into(),
                 probability: 1.0,
+                region: None,
+                next_distribution: Vec::new(),
+                next_distribution_names: Vec::new(),
+                groups: Vec::new(),
+                global_condition: None,
+                directions: Vec::new(),
+                prev_state_id: None,
+                prev_state_name: String::new(),
             },
             // Alive -> Dead (if neighbors > 3)
             TransitionRule {
@@ -359,6 +1055,14 @@ impl Application for CASimulator {
                 neighbor_state_names: vec!["Alive".into()],
                 next_state_name: "Dead".into(),
                 probability: 1.0,
+                region: None,
+                next_distribution: Vec::new(),
+                next_distribution_names: Vec::new(),
+                groups: Vec::new(),
+                global_condition: None,
+                directions: Vec::new(),
+                prev_state_id: None,
+                prev_state_name: String::new(),
             },
         ];
         (
@@ -367,6 +1071,10 @@ impl Application for CASimulator {
                 active_tab: TabId::Definition,
                 states: initial_states,
                 rules: initial_rules,
+                block_rules: Vec::new(),
+                background_state_id: DEFAULT_BACKGROUND_STATE_ID,
+                no_match_policy: NoMatchPolicy::default(),
+                rule_eval_mode: RuleEvalMode::default(),
                 grid,
                 grid_cache: Cache::new(),
                 simulation_timer: None,
@@ -376,23 +1084,135 @@ impl Application for CASimulator {
                 offset: Cell::new(Point::new(0.0, 0.0)),
                 right_mouse_pressed: Cell::new(false),
                 last_mouse_pos: RefCell::new(None),
+                show_transition_flashes: false,
+                transition_flash: Vec::new(),
+                cell_ages: Vec::new(),
+                step_history: VecDeque::new(),
+                undo_stack: VecDeque::new(),
+                redo_stack: Vec::new(),
+                stop_at_generation: None,
+                stop_at_input: String::new(),
+                stop_on_stable: false,
+                stable_report: None,
+                population_history: VecDeque::new(),
+                show_age_shading: false,
+                border_width: DEFAULT_BORDER_WIDTH,
+                border_width_input: DEFAULT_BORDER_WIDTH.to_string(),
+                border_mode: BorderMode::ScaleWithZoom,
 
                 new_state_name: String::new(),
                 new_state_color_r: "0".to_string(),
                 new_state_color_g: "0".to_string(),
                 new_state_color_b: "0".to_string(),
+                show_rgb_fields: false,
                 rule_form_probability: "1.0".to_string(),
+                probability_as_percent: false,
+                rule_form_region_enabled: false,
+                rule_form_global_enabled: false,
+                rule_form_global_state: None,
+                rule_form_global_operator: None,
+                rule_form_global_threshold: String::new(),
+                rule_form_global_error: None,
+                rule_form_prev_enabled: false,
+                rule_form_prev_state: None,
+                rule_form_prev_error: None,
+                rule_form_region: Default::default(),
+                editing_rule_index: None,
 
                 rule_form_current_state: None,
                 rule_form_next_state: None,
                 rule_form_error: None,
+                state_form_error: None,
+                state_name_edit: None,
+                rule_form_current_error: None,
+                rule_form_next_error: None,
+                rule_form_probability_error: None,
+                rule_form_region_error: None,
+                rule_form_outcomes: vec![],
+                rule_form_outcomes_error: None,
                 rule_form_conditions: vec![],
+                form_warning: None,
+                rule_form_groups: vec![],
+                rule_form_group_inputs: Default::default(),
+                rule_form_group_error: None,
+                import_mode: ImportMode::Replace,
+                import_errors: Vec::new(),
+                model_header: Vec::new(),
 
                 grid_width_input: DEFAULT_GRID_WIDTH.to_string(),
                 grid_height_input: DEFAULT_GRID_HEIGHT.to_string(),
+                preserve_grid_contents: false,
+                autosave_enabled: false,
+                autosave_interval_input: DEFAULT_AUTOSAVE_INTERVAL_SECS.to_string(),
+                pan_step_input: DEFAULT_PAN_STEP_PX.to_string(),
+                recovery_available: autosave_path().exists(),
+                model_issues: None,
+                seed,
+                seed_input: seed.to_string(),
+                force_deterministic: false,
+                grid_seed: Some(seed),
+                pattern_placement: PatternPlacement::Center,
+                neighbor_counts: NeighborCounts::default(),
+                step_buffers: StepBuffers::default(),
+                step_in_flight: false,
+                model_revision: 0,
+                sweep_param: ExampleParam::ForestLightning,
+                sweep_inputs: [
+                    "0.1".to_string(),
+                    "0.9".to_string(),
+                    "9".to_string(),
+                    DEFAULT_SWEEP_STEPS.to_string(),
+                ],
+                sweep_error: None,
+                sweep: None,
+                sweep_running: false,
+                active_modal: None,
+                run_until_kind: StopConditionKind::Population,
+                run_until_state: None,
+                run_until_operator: Some(RelationalOperator::Equals),
+                run_until_value: "0".to_string(),
+                run_until_report: None,
+                selected_example: None,
+                forest_burn_out_input: ForestFireParams::default().burn_out.to_string(),
+                forest_spread_input: ForestFireParams::default().spread.to_string(),
+                forest_growth_input: ForestFireParams::default().growth.to_string(),
+                forest_lightning_input: ForestFireParams::default().lightning.to_string(),
+                life_birth_input: "3".to_string(),
+                life_survival_input: "2,3".to_string(),
+                example_params_error: None,
+                life_like_input: "B3/S23".to_string(),
+                life_like_error: None,
+                ring_inner_input: DEFAULT_RING_INNER.to_string(),
+                ring_outer_input: DEFAULT_RING_OUTER.to_string(),
+                moore_radius_input: DEFAULT_MOORE_RADIUS.to_string(),
+                generation: 0,
+                export_caption: false,
+                gif_recording: false,
+                gif_frames: Vec::new(),
+                export_in_progress: false,
+                export_status: None,
+                comparison_grid: None,
+                show_comparison_diff: false,
+                seed_search_report: None,
+
+                canvas_tool: CanvasTool::Paint,
+                poke_report: None,
                 selected_paint_state_id: DEFAULT_STATE_ID,
                 mouse_pressed: Cell::new(false),
+                brush_size: 1,
+                pause_while_painting: true,
                 last_painted_cell: RefCell::new(None),
+                hovered_cell: Cell::new(None),
+                line_anchor: None,
+                rect_start: Cell::new(None),
+                rect_filled: true,
+
+                annotations: HashMap::new(),
+                selected_annotation_cell: None,
+                annotation_input: String::new(),
+
+                bulk_from_state: None,
+                bulk_to_state: None,
             },
             Command::none(),
         )
@@ -405,156 +1225,299 @@ impl Application for CASimulator {
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::TabSelected(tab) => {
+                if tab != TabId::Definition && self.rule_form_in_progress() {
+                    self.form_warning = Some(
+                        "A partially entered rule has not been added yet; \
+                         it is kept in Define Model."
+                            .into(),
+                    );
+                } else if tab == TabId::Definition {
+                    self.form_warning = None;
+                }
                 self.active_tab = tab;
             }
+            Message::ModalOk => {
+                if let Some(Modal::Confirm { on_confirm, .. }) = self.active_modal.take() {
+                    return self.update(*on_confirm);
+                }
+            }
+            Message::ModalCancel => self.active_modal = None,
+            Message::ShowModal(modal) => self.active_modal = Some(modal),
+            Message::ClearRuleForm => self.clear_rule_form(),
+            Message::DismissFormWarning => self.form_warning = None,
             Message::Tick(()) => {
-                if self.is_simulating {
-                    self.step_simulation_logic();
+                // Hold the current generation while the user is drawing on the canvas
+                let painting = self.pause_while_painting && self.mouse_pressed.get();
+                // Ticks that arrive while a step is still running are skipped
+                if self.is_simulating && !painting && !self.step_in_flight {
+                    return self.start_background_step();
+                }
+            }
+            Message::StepComplete(result) => {
+                self.step_in_flight = false;
+                let result = match result {
+                    Ok(result) => result,
+                    Err(e) => {
+                        self.is_simulating = false;
+                        self.simulation_timer = None;
+                        self.show_error("Simulation stopped", e);
+                        return Command::none();
+                    }
+                };
+                // Paused, or the grid was painted, reset or loaded, or the model or step
+                // settings edited while the step ran: the result is stale, and only its
+                // buffers are kept
+                let stale = !self.is_simulating
+                    || result.model_revision != self.model_revision
+                    || result.width != self.grid.width
+                    || !self
+                        .grid
+                        .cells
+                        .iter()
+                        .flatten()
+                        .eq(result.buffers.current.iter());
+                if stale {
+                    self.step_buffers = result.buffers;
+                    self.neighbor_counts = result.neighbor_counts;
+                    return Command::none();
+                }
+
+                self.push_step_history();
+                self.finish_step(result.buffers, result.neighbor_counts);
+                self.end_generation();
+                // Equality, so stepping past the target by hand doesn't stop it again
+                if self.stop_at_generation == Some(self.generation) {
+                    self.is_simulating = false;
+                    self.simulation_timer = None;
                 }
             }
 
             // --- State Definition Messages ---
-            Message::StateNameChanged(name) => self.new_state_name = name,
+            Message::StateNameChanged(name) => {
+                self.new_state_name = name;
+                self.state_form_error = None;
+            }
             Message::StateColorRChanged(r) => self.new_state_color_r = r,
             Message::StateColorGChanged(g) => self.new_state_color_g = g,
             Message::StateColorBChanged(b) => self.new_state_color_b = b,
+            Message::StateColorPicked(color) => {
+                let [r, g, b, _] = color.into_rgba8();
+                self.new_state_color_r = r.to_string();
+                self.new_state_color_g = g.to_string();
+                self.new_state_color_b = b.to_string();
+                self.state_form_error = None;
+            }
+            Message::RgbFieldsToggled(enabled) => self.show_rgb_fields = enabled,
             Message::RuleProbabilityChanged(val) => {
                 self.rule_form_probability = val;
+                self.rule_form_probability_error = self.probability_input_error();
             }
-            Message::AddState => {
-                if !self.new_state_name.trim().is_empty() {
-                    let r = self.new_state_color_r.parse::<u8>().unwrap_or(0);
-                    let g = self.new_state_color_g.parse::<u8>().unwrap_or(0);
-                    let b = self.new_state_color_b.parse::<u8>().unwrap_or(0);
-
-                    let mut new_id = 0u8;
-                    let mut used_ids: Vec<u8> = self.states.iter().map(|s| s.id).collect();
-                    used_ids.sort_unstable();
-                    for id in used_ids {
-                        if id == new_id {
-                            new_id += 1;
-                        } else if id > new_id {
-                            break;
-                        }
+            Message::RuleRegionToggled(enabled) => self.rule_form_region_enabled = enabled,
+            Message::RuleGlobalToggled(enabled) => self.rule_form_global_enabled = enabled,
+            Message::RuleGlobalStateSelected(state) => {
+                self.rule_form_global_state = Some(state);
+                self.rule_form_global_error = None;
+            }
+            Message::RuleGlobalOperatorSelected(op) => {
+                self.rule_form_global_operator = Some(op);
+                self.rule_form_global_error = None;
+            }
+            Message::RuleGlobalThresholdChanged(val) => {
+                self.rule_form_global_threshold = val;
+                self.rule_form_global_error = None;
+            }
+            Message::RulePrevToggled(enabled) => self.rule_form_prev_enabled = enabled,
+            Message::RulePrevStateSelected(state) => {
+                self.rule_form_prev_state = Some(state);
+                self.rule_form_prev_error = None;
+            }
+            Message::RuleRegionChanged(idx, val) => {
+                if let Some(field) = self.rule_form_region.get_mut(idx) {
+                    *field = val;
+                }
+                self.rule_form_region_error = None;
+            }
+            Message::ProbabilityPercentToggled(enabled) => {
+                // Keep whatever the user typed, just re-express it in the new unit
+                let current = self.parse_probability_input();
+                let outcomes: Vec<Option<f32>> = self
+                    .rule_form_outcomes
+                    .iter()
+                    .map(|o| self.parse_probability(&o.probability))
+                    .collect();
+                self.probability_as_percent = enabled;
+                if let Some(p) = current {
+                    self.rule_form_probability = self.format_probability(p);
+                }
+                self.rule_form_probability_error = self.probability_input_error();
+                for (idx, p) in outcomes.into_iter().enumerate() {
+                    if let Some(p) = p {
+                        self.rule_form_outcomes[idx].probability = self.format_probability(p);
                     }
+                }
+            }
+            Message::AddOutcome => {
+                self.rule_form_outcomes.push(OutcomeForm {
+                    state: None,
+                    probability: String::new(),
+                    error: None,
+                });
+                self.rule_form_outcomes_error = None;
+            }
+            Message::RemoveOutcome(idx) => {
+                if idx < self.rule_form_outcomes.len() {
+                    self.rule_form_outcomes.remove(idx);
+                }
+                self.rule_form_outcomes_error = None;
+            }
+            Message::OutcomeStateSelected(idx, state) => {
+                if let Some(outcome) = self.rule_form_outcomes.get_mut(idx) {
+                    outcome.state = Some(state);
+                    outcome.error = None;
+                }
+            }
+            Message::OutcomeProbabilityChanged(idx, val) => {
+                if let Some(outcome) = self.rule_form_outcomes.get_mut(idx) {
+                    outcome.probability = val;
+                    outcome.error = None;
+                }
+                self.rule_form_outcomes_error = None;
+            }
+            Message::AddState => {
+                let color = self.new_state_color();
+                self.state_form_error = self
+                    .state_name_error(&self.new_state_name, None)
+                    .or_else(|| color.as_ref().err().cloned());
 
+                if let (None, Ok(color)) = (&self.state_form_error, color) {
+                    let Some(new_id) = self.next_free_state_id() else {
+                        self.show_error(
+                            "Too many states",
+                            "Every state id is already in use.".into(),
+                        );
+                        return Command::none();
+                    };
+
+                    self.record_model_edit(false);
                     self.states.push(CAState {
                         id: new_id,
-                        name: self.new_state_name.clone(),
-                        color: Color::from_rgb8(r, g, b),
+                        name: self.new_state_name.trim().to_string(),
+                        color,
                         weight: 1,
                     });
+                    // Cells left over with this id stop rendering as missing
+                    self.grid_cache.clear();
 
                     self.new_state_name.clear();
                 }
             }
             Message::RemoveState(index) => {
+                let Some(state) = self.states.get(index) else {
+                    return Command::none();
+                };
+                let rule_count = self
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.mentions_state(state.id))
+                    .count();
+                // The ones `forget_state` can't rewrite without the state
+                let dropped_count = self
+                    .rules
+                    .iter()
+                    .filter(|&rule| !rule.clone().forget_state(state.id))
+                    .count();
+                let cell_count = self.grid.population()[state.id as usize];
+                if rule_count == 0 && cell_count == 0 {
+                    return self.update(Message::ConfirmRemoveState(index));
+                }
+                // The dialog blocks the state list, so `index` still points at this state
+                self.active_modal = Some(Modal::Confirm {
+                    title: format!("Remove '{}'", state.name),
+                    message: format!(
+                        "{} rule(s) refer to this state and {} cell(s) hold it. {} rule(s) \
+                         depend on it and will be deleted; its cells will be set to the \
+                         background state.",
+                        rule_count, cell_count, dropped_count
+                    ),
+                    on_confirm: Box::new(Message::ConfirmRemoveState(index)),
+                });
+            }
+            Message::ConfirmRemoveState(index) => {
                 if index < self.states.len() {
+                    self.record_model_edit(true);
                     let removed_state_id = self.states[index].id;
                     self.states.remove(index);
-                    self.rules.retain(|rule| {
-                        rule.current_state_id != removed_state_id
-                            && !rule.neighbor_state_id_to_count.contains(&removed_state_id)
-                            && rule.next_state_id != removed_state_id
-                    });
-                    for r in 0..self.grid.height {
-                        for c in 0..self.grid.width {
-                            if self.grid.cells[r][c] == removed_state_id {
-                                self.grid.cells[r][c] = DEFAULT_STATE_ID;
-                            }
-                        }
+                    // Rules only mentioning the state in a condition are kept when they
+                    // still behave the same without it; see `TransitionRule::forget_state`
+                    let before = self.rules.len();
+                    self.rules
+                        .retain_mut(|rule| rule.forget_state(removed_state_id));
+                    if self.rules.len() < before {
+                        // Indices shifted; the form's rule can no longer be tracked
+                        self.editing_rule_index = None;
                     }
+                    self.forget_state_in_rule_form(removed_state_id);
+                    if self
+                        .run_until_state
+                        .as_ref()
+                        .is_some_and(|s| s.id == removed_state_id)
+                    {
+                        self.run_until_state = None;
+                    }
+                    self.ensure_background_state();
+                    self.grid
+                        .replace_state(removed_state_id, self.background_state_id);
                     self.grid_cache.clear();
                 }
             }
+            Message::BackgroundStateSelected(state) => {
+                self.background_state_id = state.id;
+                self.model_revision += 1;
+            }
 
             // --- Rule Definition Messages ---
-            Message::RuleCurrentStateSelected(state) => self.rule_form_current_state = Some(state),
-            Message::RuleNextStateSelected(state) => self.rule_form_next_state = Some(state),
+            Message::RuleCurrentStateSelected(state) => {
+                self.rule_form_current_state = Some(state);
+                self.rule_form_current_error = None;
+            }
+            Message::RuleNextStateSelected(state) => {
+                self.rule_form_next_state = Some(state);
+                self.rule_form_next_error = None;
+            }
 
             Message::ExampleModelSelected(model) => {
+                // Parameterized presets only load once their parameters are valid
+                let forest_fire = self.forest_fire_params();
+                let life_birth = parse_count_list(&self.life_birth_input);
+                let life_survival = parse_count_list(&self.life_survival_input);
+                let params_error = match model {
+                    ExampleModel::ForestFire => forest_fire.as_ref().err(),
+                    ExampleModel::GameOfLife => {
+                        life_birth.as_ref().err().or(life_survival.as_ref().err())
+                    }
+                    _ => None,
+                };
+                self.example_params_error = params_error.cloned();
+                if self.example_params_error.is_some() {
+                    return Command::none();
+                }
+                self.record_model_edit(true);
+                self.selected_example = Some(model);
+                self.discard_rule_form();
+
                 self.states.clear();
                 self.rules.clear();
+                self.model_header = vec![format!("Example model: {}", model)];
 
                 match model {
                     ExampleModel::GameOfLife => {
-                        self.states = vec![
-                            CAState {
-                                id: 0,
-                                name: "Dead".into(),
-                                color: Color::BLACK,
-                                weight: 5,
-                            },
-                            CAState {
-                                id: 1,
-                                name: "Alive".into(),
-                                color: Color::from_rgb8(0, 255, 0),
-                                weight: 5,
-                            },
-                        ];
-
-                        self.rules = vec![
-                            TransitionRule {
-                                current_state_id: 1,
-                                neighbor_state_id_to_count: vec![1],
-                                operator: vec![RelationalOperator::Equals],
-                                neighbor_count_threshold: vec![2],
-                                combiner: vec![],
-                                next_state_id: 1,
-                                current_state_name: "Alive".into(),
-                                neighbor_state_names: vec!["Alive".into()],
-                                next_state_name: "Alive".into(),
-                                probability: 1.0,
-                            },
-                            TransitionRule {
-                                current_state_id: 1,
-                                neighbor_state_id_to_count: vec![1],
-                                operator: vec![RelationalOperator::Equals],
-                                neighbor_count_threshold: vec![3],
-                                combiner: vec![],
-                                next_state_id: 1,
-                                current_state_name: "Alive".into(),
-                                neighbor_state_names: vec!["Alive".into()],
-                                next_state_name: "Alive".into(),
-                                probability: 1.0,
-                            },
-                            TransitionRule {
-                                current_state_id: 0,
-                                neighbor_state_id_to_count: vec![1],
-                                operator: vec![RelationalOperator::Equals],
-                                neighbor_count_threshold: vec![3],
-                                combiner: vec![],
-                                next_state_id: 1,
-                                current_state_name: "Dead".into(),
-                                neighbor_state_names: vec!["Alive".into()],
-                                next_state_name: "Alive".into(),
-                                probability: 1.0,
-                            },
-                            TransitionRule {
-                                current_state_id: 1,
-                                neighbor_state_id_to_count: vec![1],
-                                operator: vec![RelationalOperator::LessThan],
-                                neighbor_count_threshold: vec![2],
-                                combiner: vec![],
-                                next_state_id: 0,
-                                current_state_name: "Alive".into(),
-                                neighbor_state_names: vec!["Alive".into()],
-                                next_state_name: "Dead".into(),
-                                probability: 1.0,
-                            },
-                            TransitionRule {
-                                current_state_id: 1,
-                                neighbor_state_id_to_count: vec![1],
-                                operator: vec![RelationalOperator::GreaterThan],
-                                neighbor_count_threshold: vec![3],
-                                combiner: vec![],
-                                next_state_id: 0,
-                                current_state_name: "Alive".into(),
-                                neighbor_state_names: vec!["Alive".into()],
-                                next_state_name: "Dead".into(),
-                                probability: 1.0,
-                            },
-                        ];
+                        self.states = life_like_states();
+                        self.rules = life_like_rules(
+                            &self.states[0],
+                            &self.states[1],
+                            &life_birth.unwrap_or_default(),
+                            &life_survival.unwrap_or_default(),
+                        );
                     }
 
                     ExampleModel::Wireworld => {
@@ -596,6 +1559,14 @@ impl Application for CASimulator {
                                 neighbor_state_names: vec![],
                                 next_state_name: "ElectronTail".into(),
                                 probability: 1.0,
+                                region: None,
+                                next_distribution: Vec::new(),
+                                next_distribution_names: Vec::new(),
+                                groups: Vec::new(),
+                                global_condition: None,
+                                directions: Vec::new(),
+                                prev_state_id: None,
+                                prev_state_name: String::new(),
                             },
                             TransitionRule {
                                 current_state_id: 2, // Tail -> Conductor
@@ -608,6 +1579,14 @@ impl Application for CASimulator {
                                 neighbor_state_names: vec![],
                                 next_state_name: "Conductor".into(),
                                 probability: 1.0,
+                                region: None,
+                                next_distribution: Vec::new(),
+                                next_distribution_names: Vec::new(),
+                                groups: Vec::new(),
+                                global_condition: None,
+                                directions: Vec::new(),
+                                prev_state_id: None,
+                                prev_state_name: String::new(),
                             },
                             TransitionRule {
                                 current_state_id: 3, // Conductor -> Head if 1 or 2 neighbors are Head
@@ -626,6 +1605,14 @@ impl Application for CASimulator {
                                 ],
                                 next_state_name: "ElectronHead".into(),
                                 probability: 1.0,
+                                region: None,
+                                next_distribution: Vec::new(),
+                                next_distribution_names: Vec::new(),
+                                groups: Vec::new(),
+                                global_condition: None,
+                                directions: Vec::new(),
+                                prev_state_id: None,
+                                prev_state_name: String::new(),
                             },
                         ];
                     }
@@ -664,6 +1651,14 @@ impl Application for CASimulator {
                                 neighbor_state_names: vec!["On".into()],
                                 next_state_name: "On".into(),
                                 probability: 1.0,
+                                region: None,
+                                next_distribution: Vec::new(),
+                                next_distribution_names: Vec::new(),
+                                groups: Vec::new(),
+                                global_condition: None,
+                                directions: Vec::new(),
+                                prev_state_id: None,
+                                prev_state_name: String::new(),
                             },
                             TransitionRule {
                                 current_state_id: 1, // On -> Dying
@@ -676,6 +1671,14 @@ impl Application for CASimulator {
                                 neighbor_state_names: vec![],
                                 next_state_name: "Dying".into(),
                                 probability: 1.0,
+                                region: None,
+                                next_distribution: Vec::new(),
+                                next_distribution_names: Vec::new(),
+                                groups: Vec::new(),
+                                global_condition: None,
+                                directions: Vec::new(),
+                                prev_state_id: None,
+                                prev_state_name: String::new(),
                             },
                             TransitionRule {
                                 current_state_id: 2, // Dying -> Off
@@ -688,6 +1691,14 @@ impl Application for CASimulator {
                                 neighbor_state_names: vec![],
                                 next_state_name: "Off".into(),
                                 probability: 1.0,
+                                region: None,
+                                next_distribution: Vec::new(),
+                                next_distribution_names: Vec::new(),
+                                groups: Vec::new(),
+                                global_condition: None,
+                                directions: Vec::new(),
+                                prev_state_id: None,
+                                prev_state_name: String::new(),
                             },
                         ];
                     }
@@ -726,6 +1737,14 @@ impl Application for CASimulator {
                                 neighbor_state_names: vec!["Activator".into()],
                                 next_state_name: "Activator".into(),
                                 probability: 1.0,
+                                region: None,
+                                next_distribution: Vec::new(),
+                                next_distribution_names: Vec::new(),
+                                groups: Vec::new(),
+                                global_condition: None,
+                                directions: Vec::new(),
+                                prev_state_id: None,
+                                prev_state_name: String::new(),
                             },
                             TransitionRule {
                                 current_state_id: 1, // Activator -> Inhibitor if >=3 neighbors Activator
@@ -738,6 +1757,14 @@ impl Application for CASimulator {
                                 neighbor_state_names: vec!["Activator".into()],
                                 next_state_name: "Inhibitor".into(),
                                 probability: 1.0,
+                                region: None,
+                                next_distribution: Vec::new(),
+                                next_distribution_names: Vec::new(),
+                                groups: Vec::new(),
+                                global_condition: None,
+                                directions: Vec::new(),
+                                prev_state_id: None,
+                                prev_state_name: String::new(),
                             },
                             TransitionRule {
                                 current_state_id: 2, // Inhibitor -> Empty
@@ -750,73 +1777,75 @@ impl Application for CASimulator {
                                 neighbor_state_names: vec![],
                                 next_state_name: "Empty".into(),
                                 probability: 1.0,
+                                region: None,
+                                next_distribution: Vec::new(),
+                                next_distribution_names: Vec::new(),
+                                groups: Vec::new(),
+                                global_condition: None,
+                                directions: Vec::new(),
+                                prev_state_id: None,
+                                prev_state_name: String::new(),
                             },
                         ];
                     }
 
                     ExampleModel::ForestFire => {
-                        self.states = vec![
-                            CAState {
-                                id: 0,
-                                name: "Empty".into(),
-                                color: Color::BLACK,
-                                weight: 10,
-                            },
-                            CAState {
-                                id: 1,
-                                name: "Tree".into(),
-                                color: Color::from_rgb8(0, 200, 0),
-                                weight: 7,
-                            },
-                            CAState {
-                                id: 2,
-                                name: "Burning".into(),
-                                color: Color::from_rgb8(255, 0, 0),
-                                weight: 3,
-                            },
-                        ];
+                        self.states = forest_fire_states();
 
-                        self.rules = vec![
-                            TransitionRule {
-                                current_state_id: 2, // Burning -> Empty
-                                neighbor_state_id_to_count: vec![],
-                                operator: vec![],
-                                neighbor_count_threshold: vec![],
-                                combiner: vec![],
-                                next_state_id: 0,
-                                current_state_name: "Burning".into(),
-                                neighbor_state_names: vec![],
-                                next_state_name: "Empty".into(),
-                                probability: 0.8,
-                            },
-                            TransitionRule {
-                                current_state_id: 1, // Tree -> Burning if >=1 neighbor Burning
-                                neighbor_state_id_to_count: vec![2],
-                                operator: vec![RelationalOperator::GreaterOrEqual],
-                                neighbor_count_threshold: vec![1],
-                                combiner: vec![],
-                                next_state_id: 2,
-                                current_state_name: "Tree".into(),
-                                neighbor_state_names: vec!["Burning".into()],
-                                next_state_name: "Burning".into(),
-                                probability: 0.5,
-                            },
-                            TransitionRule {
-                                current_state_id: 0, // Empty -> Tree (budding)
-                                neighbor_state_id_to_count: vec![],
-                                operator: vec![],
-                                neighbor_count_threshold: vec![],
-                                combiner: vec![],
-                                next_state_id: 1,
-                                current_state_name: "Empty".into(),
-                                neighbor_state_names: vec![],
-                                next_state_name: "Tree".into(),
-                                probability: 0.3,
-                            },
-                        ];
+                        self.rules = forest_fire_rules(
+                            &self.states[0],
+                            &self.states[1],
+                            &self.states[2],
+                            &forest_fire.unwrap_or_default(),
+                        );
+                    }
+
+                    ExampleModel::MajorityVote => {
+                        self.states = majority_vote_states();
+                        self.rules = majority_vote_rules(&self.states);
+                        // The 5-vote threshold is a majority of the 8 Moore neighbors
+                        self.grid.neighborhood = Neighborhood::Moore;
+                    }
+
+                    ExampleModel::LangtonsAnt => {
+                        // An ant on White turns right and on Black turns left, then
+                        // steps forward. A square reads only the neighbor opposite each
+                        // heading: an ant there that turns to face the square moves in
+                        // (e.g. AntNOnWhite to the W turns to E and enters)
+                        self.states = langtons_ant_states();
+                        self.rules = langtons_ant_rules(&self.states);
+                        self.grid.neighborhood = Neighborhood::VonNeumann;
+
+                        // A random grid would start with no ant, so begin from a
+                        // single one on an all-White grid
+                        self.grid.fill(0);
+                        let (row, col) = (self.grid.height / 2, self.grid.width / 2);
+                        if let Some(cell) =
+                            self.grid.cells.get_mut(row).and_then(|r| r.get_mut(col))
+                        {
+                            *cell = ant_state_id(0, 0);
+                        }
+                        self.grid_seed = None;
+                        self.reset_history();
+                        self.transition_flash.clear();
+                    }
+
+                    // Each count is its own Equals rule, so B36 is exactly 3 or 6, not >= 3
+                    ExampleModel::Seeds | ExampleModel::HighLife | ExampleModel::DayAndNight => {
+                        let (birth, survival) = model.life_like_counts().unwrap_or_default();
+                        self.states = life_like_states();
+                        self.rules =
+                            life_like_rules(&self.states[0], &self.states[1], birth, survival);
+                    }
+
+                    ExampleModel::BriansBrain => {
+                        self.states = brians_brain_states();
+                        self.rules =
+                            brians_brain_rules(&self.states[0], &self.states[1], &self.states[2]);
                     }
                 }
 
+                self.ensure_background_state();
                 self.grid_cache.clear();
             }
             Message::RuleCombinerSelected(idx, comb) => {
@@ -831,38 +1860,103 @@ impl Application for CASimulator {
                     operator: None,
                     threshold: String::new(),
                     combiner: None,
+                    directions: DirectionMask::ALL,
+                    errors: Vec::new(),
                 });
             }
             Message::RemoveCondition(idx) => {
                 if idx < self.rule_form_conditions.len() {
                     self.rule_form_conditions.remove(idx);
+                    // Keep groups on the same conditions; a group of just this one goes away
+                    self.rule_form_groups = self
+                        .rule_form_groups
+                        .iter()
+                        .filter(|&&group| group != (idx, idx))
+                        .map(|&(start, end)| {
+                            (
+                                if start > idx { start - 1 } else { start },
+                                if end >= idx { end - 1 } else { end },
+                            )
+                        })
+                        .filter(|&(start, end)| start < end)
+                        .collect();
+                    self.rule_form_groups.dedup();
+                }
+            }
+            Message::RuleGroupBoundChanged(idx, val) => {
+                if let Some(field) = self.rule_form_group_inputs.get_mut(idx) {
+                    *field = val;
+                }
+                self.rule_form_group_error = None;
+            }
+            Message::AddConditionGroup => {
+                let count = self.rule_form_conditions.len();
+                let bounds: Vec<Option<usize>> = self
+                    .rule_form_group_inputs
+                    .iter()
+                    .map(|v| v.trim().parse::<usize>().ok())
+                    .collect();
+                match bounds[..] {
+                    [Some(first), Some(last)] if first >= 1 && first < last && last <= count => {
+                        let mut groups = self.rule_form_groups.clone();
+                        groups.push((first - 1, last - 1));
+                        match validate_groups(&groups, count) {
+                            Ok(()) => {
+                                self.rule_form_groups = groups;
+                                self.rule_form_group_inputs = Default::default();
+                            }
+                            Err(e) => self.rule_form_group_error = Some(e),
+                        }
+                    }
+                    _ => {
+                        self.rule_form_group_error = Some(format!(
+                            "Grupo inválido (use duas condições entre 1 e {})",
+                            count
+                        ))
+                    }
+                }
+            }
+            Message::RemoveConditionGroup(idx) => {
+                if idx < self.rule_form_groups.len() {
+                    self.rule_form_groups.remove(idx);
                 }
+                self.rule_form_group_error = None;
             }
             Message::RuleNeighborStateSelected(idx, state) => {
                 if idx < self.rule_form_conditions.len() {
                     self.rule_form_conditions[idx].neighbor_state = Some(state);
+                    self.rule_form_conditions[idx].errors.clear();
                 }
             }
             Message::RuleOperatorSelected(idx, op) => {
                 if idx < self.rule_form_conditions.len() {
                     self.rule_form_conditions[idx].operator = Some(op);
+                    self.rule_form_conditions[idx].errors.clear();
                 }
             }
             Message::RuleThresholdChanged(idx, val) => {
                 if idx < self.rule_form_conditions.len() {
                     self.rule_form_conditions[idx].threshold = val;
+                    self.rule_form_conditions[idx].errors.clear();
+                }
+            }
+            Message::RuleDirectionToggled(idx, direction) => {
+                if let Some(cond) = self.rule_form_conditions.get_mut(idx) {
+                    cond.directions = cond.directions.toggled(direction);
+                    cond.errors.clear();
                 }
             }
 
             Message::AddRule => {
-                self.rule_form_error = None;
-                let mut errors: Vec<String> = Vec::new();
+                self.clear_rule_form_errors();
+                let mut has_errors = false;
 
                 // Current state
                 let cur = if let Some(s) = self.rule_form_current_state.as_ref() {
                     s
                 } else {
-                    errors.push("Current State não selecionado".to_string());
+                    self.rule_form_current_error = Some("Current State não selecionado".into());
+                    has_errors = true;
                     &CAState {
                         id: 0,
                         name: "".into(),
@@ -871,11 +1965,52 @@ impl Application for CASimulator {
                     }
                 };
 
-                // Next state
+                // Weighted outcomes (optional)
+                let outcome_probabilities: Vec<Option<f32>> = self
+                    .rule_form_outcomes
+                    .iter()
+                    .map(|o| self.parse_probability(&o.probability))
+                    .collect();
+                let mut next_distribution: Vec<(u8, f32)> = Vec::new();
+                let mut next_distribution_names: Vec<String> = Vec::new();
+                for (outcome, p) in self
+                    .rule_form_outcomes
+                    .iter_mut()
+                    .zip(outcome_probabilities)
+                {
+                    match (&outcome.state, p) {
+                        (Some(state), Some(p)) => {
+                            next_distribution.push((state.id, p));
+                            next_distribution_names.push(state.name.clone());
+                        }
+                        (None, _) => outcome.error = Some("Estado não selecionado".into()),
+                        (_, None) => outcome.error = Some("Probabilidade inválida".into()),
+                    }
+                    has_errors |= outcome.error.is_some();
+                }
+                let total: f32 = next_distribution.iter().map(|&(_, p)| p).sum();
+                if !self.rule_form_outcomes.is_empty() && !has_errors && (total - 1.0).abs() > 0.001
+                {
+                    self.rule_form_outcomes_error = Some(format!(
+                        "As probabilidades dos resultados somam {}, não {}",
+                        self.format_probability(total),
+                        self.format_probability(1.0)
+                    ));
+                    has_errors = true;
+                }
+
+                // Next state; with weighted outcomes the first one stands in for it
                 let nxt = if let Some(s) = self.rule_form_next_state.as_ref() {
                     s
+                } else if let Some(s) = self
+                    .rule_form_outcomes
+                    .first()
+                    .and_then(|o| o.state.as_ref())
+                {
+                    s
                 } else {
-                    errors.push("Next State não selecionado".to_string());
+                    self.rule_form_next_error = Some("Next State não selecionado".into());
+                    has_errors = true;
                     &CAState {
                         id: 0,
                         name: "".into(),
@@ -886,71 +2021,133 @@ impl Application for CASimulator {
 
                 let mut neighbor_ids: Vec<u8> = Vec::new();
                 let mut operators: Vec<RelationalOperator> = Vec::new();
-                let mut thresholds: Vec<u8> = Vec::new();
+                let mut thresholds: Vec<u16> = Vec::new();
                 let mut combiners: Vec<ConditionCombiner> = Vec::new();
 
-                for (idx, cond) in self.rule_form_conditions.iter().enumerate() {
+                let condition_count = self.rule_form_conditions.len();
+                for (idx, cond) in self.rule_form_conditions.iter_mut().enumerate() {
                     if let Some(state) = &cond.neighbor_state {
                         neighbor_ids.push(state.id);
                     } else {
-                        errors.push(format!(
-                            "Neighbor State não selecionado na condição {}",
-                            idx + 1
-                        ));
+                        cond.errors.push("Neighbor State não selecionado".into());
                         neighbor_ids.push(0);
                     }
 
                     if let Some(op) = cond.operator {
                         operators.push(op);
                     } else {
-                        errors.push(format!("Operador não selecionado na condição {}", idx + 1));
+                        cond.errors.push("Operador não selecionado".into());
                         operators.push(RelationalOperator::Equals);
                     }
 
-                    match cond.threshold.parse::<u8>() {
+                    match cond.threshold.trim().parse::<u16>() {
                         Ok(v) => thresholds.push(v),
                         Err(_) => {
-                            errors.push(format!("Threshold inválido na condição {}", idx + 1));
+                            cond.errors.push("Threshold inválido".into());
                             thresholds.push(0);
                         }
                     }
 
-                    if idx < self.rule_form_conditions.len() - 1 {
+                    if cond.directions.is_empty() {
+                        cond.errors.push("Nenhuma direção selecionada".into());
+                    }
+
+                    if idx < condition_count - 1 {
                         if let Some(comb) = cond.combiner.clone() {
                             combiners.push(comb);
                         } else {
                             combiners.push(ConditionCombiner::And);
                         }
                     }
+
+                    has_errors |= !cond.errors.is_empty();
                 }
 
-                if !errors.is_empty() {
-                    self.rule_form_error = Some(errors.join("; "));
+                let probability: f32 = match self.parse_probability_input() {
+                    Some(p) => p,
+                    None => {
+                        self.rule_form_probability_error = self.probability_input_error();
+                        has_errors = true;
+                        1.0
+                    }
+                };
+
+                // Global population condition (optional)
+                let global_condition = if self.rule_form_global_enabled {
+                    match (
+                        &self.rule_form_global_state,
+                        self.rule_form_global_operator,
+                        self.rule_form_global_threshold.trim().parse::<u32>(),
+                    ) {
+                        (Some(state), Some(operator), Ok(threshold)) => Some(GlobalCondition {
+                            state_id: state.id,
+                            state_name: state.name.clone(),
+                            operator,
+                            threshold,
+                        }),
+                        _ => {
+                            self.rule_form_global_error = Some(
+                                "Condição global incompleta (estado, operador e total inteiro)"
+                                    .into(),
+                            );
+                            has_errors = true;
+                            None
+                        }
+                    }
                 } else {
-                    if neighbor_ids.is_empty() {
-                        String::new()
-                    } else {
-                        let mut names: Vec<String> = Vec::with_capacity(neighbor_ids.len());
-                        for id in &neighbor_ids {
-                            if let Some(s) = self.states.iter().find(|st| st.id == *id) {
-                                names.push(s.name.clone());
-                            } else {
-                                names.push(format!("State {}", id));
+                    None
+                };
+
+                // Previous state (optional)
+                let prev_state = if self.rule_form_prev_enabled {
+                    if self.rule_form_prev_state.is_none() {
+                        self.rule_form_prev_error = Some("Selecione o estado anterior".into());
+                        has_errors = true;
+                    }
+                    self.rule_form_prev_state.clone()
+                } else {
+                    None
+                };
+
+                // Region (optional)
+                let region = if self.rule_form_region_enabled {
+                    let bounds: Vec<Option<usize>> = self
+                        .rule_form_region
+                        .iter()
+                        .map(|v| v.trim().parse::<usize>().ok())
+                        .collect();
+                    match bounds[..] {
+                        [Some(r0), Some(c0), Some(r1), Some(c1)] => {
+                            if r0.max(r1) >= self.grid.height || c0.max(c1) >= self.grid.width {
+                                self.rule_form_region_error = Some(format!(
+                                    "Região fora do grid ({}x{})",
+                                    self.grid.width, self.grid.height
+                                ));
+                                has_errors = true;
                             }
+                            Some(CellRegion::from_corners(r0, c0, r1, c1))
                         }
-                        names.join(",")
-                    };
-
-                    let probability: f32 = match self.rule_form_probability.parse::<f32>() {
-                        Ok(p) if (0.0..=1.0).contains(&p) => p,
                         _ => {
-                            errors
-                                .push("Probabilidade inválida (use valor entre 0.0 e 1.0)".into());
-                            1.0
+                            self.rule_form_region_error =
+                                Some("Região inválida (use linhas/colunas inteiras)".into());
+                            has_errors = true;
+                            None
                         }
-                    };
+                    }
+                } else {
+                    None
+                };
 
-                    self.rules.push(TransitionRule {
+                if !has_errors {
+                    let mut directions: Vec<DirectionMask> = self
+                        .rule_form_conditions
+                        .iter()
+                        .map(|c| c.directions)
+                        .collect();
+                    if directions.iter().all(|&mask| mask == DirectionMask::ALL) {
+                        directions.clear();
+                    }
+                    let rule = TransitionRule {
                         current_state_id: cur.id,
                         neighbor_state_id_to_count: neighbor_ids,
                         operator: operators,
@@ -969,18 +2166,110 @@ impl Application for CASimulator {
                             .collect(),
                         next_state_name: nxt.name.clone(),
                         probability,
-                    });
-
-                    self.rule_form_current_state = None;
-                    self.rule_form_next_state = None;
-                    self.rule_form_conditions.clear();
-                    self.rule_form_error = None;
+                        region,
+                        next_distribution,
+                        next_distribution_names,
+                        groups: self.rule_form_groups.clone(),
+                        global_condition,
+                        directions,
+                        prev_state_id: prev_state.as_ref().map(|s| s.id),
+                        prev_state_name: prev_state.map_or_else(String::new, |s| s.name),
+                    };
+                    if let Err(e) = rule.validate() {
+                        self.rule_form_error = Some(e);
+                        return Command::none();
+                    }
+                    self.record_model_edit(false);
+                    match self.editing_rule_index.filter(|&i| i < self.rules.len()) {
+                        Some(i) => self.rules[i] = rule,
+                        None => self.rules.push(rule),
+                    }
+                    self.clear_rule_form();
+                }
+            }
+            Message::EditRule(idx) => self.load_rule_into_form(idx),
+            Message::Undo => {
+                if let Some(snapshot) = self.undo_stack.pop_back() {
+                    let current = self.model_snapshot(snapshot.grid.is_some());
+                    self.redo_stack.push(current);
+                    self.restore_model(snapshot);
+                }
+            }
+            Message::Redo => {
+                if let Some(snapshot) = self.redo_stack.pop() {
+                    let current = self.model_snapshot(snapshot.grid.is_some());
+                    self.undo_stack.push_back(current);
+                    self.restore_model(snapshot);
                 }
             }
 
             Message::RemoveRule(idx) => {
                 if idx < self.rules.len() {
+                    self.record_model_edit(false);
                     self.rules.remove(idx);
+                    // The form stays filled in, but as a new rule if its own was removed
+                    self.editing_rule_index = match self.editing_rule_index {
+                        Some(i) if i == idx => None,
+                        Some(i) if i > idx => Some(i - 1),
+                        other => other,
+                    };
+                }
+            }
+            // The first matching rule wins, so order decides precedence
+            Message::MoveRuleUp(idx) => {
+                if idx > 0 && idx < self.rules.len() {
+                    self.rules.swap(idx - 1, idx);
+                    self.follow_rule_swap(idx - 1, idx);
+                    self.model_revision += 1;
+                }
+            }
+            Message::MoveRuleDown(idx) => {
+                if idx + 1 < self.rules.len() {
+                    self.rules.swap(idx, idx + 1);
+                    self.follow_rule_swap(idx, idx + 1);
+                    self.model_revision += 1;
+                }
+            }
+            Message::StateNameEdited(idx, name) => {
+                let Some(id) = self.states.get(idx).map(|s| s.id) else {
+                    return Command::none();
+                };
+                self.state_form_error = self.state_name_error(&name, Some(id));
+                let trimmed = name.trim().to_string();
+                // Only a valid name reaches the state and its rules; the field keeps
+                // showing what was typed until then
+                if self.state_form_error.is_some() || trimmed != name {
+                    self.state_name_edit = Some((id, name));
+                } else {
+                    self.state_name_edit = None;
+                }
+                if self.state_form_error.is_none() {
+                    self.states[idx].name = trimmed;
+                    let state = self.states[idx].clone();
+                    for rule in &mut self.rules {
+                        rule.rename_state(state.id, &state.name);
+                    }
+                    self.sync_state_in_forms(&state);
+                }
+            }
+            Message::StateColorEdited(idx, channel, val) => {
+                if let Some(state) = self.states.get_mut(idx) {
+                    let value = if val.trim().is_empty() {
+                        Some(0)
+                    } else {
+                        val.trim().parse::<u8>().ok()
+                    };
+                    if let Some(value) = value {
+                        let mut rgba = state.color.into_rgba8();
+                        if let Some(c) = rgba.get_mut(channel) {
+                            *c = value;
+                        }
+                        state.color =
+                            Color::from_rgba8(rgba[0], rgba[1], rgba[2], rgba[3] as f32 / 255.0);
+                        let state = state.clone();
+                        self.sync_state_in_forms(&state);
+                        self.grid_cache.clear();
+                    }
                 }
             }
             Message::StateWeightChanged(idx, val) => {
@@ -993,50 +2282,16 @@ impl Application for CASimulator {
                 }
             }
             Message::ExportRules => {
-                use std::fs::File;
-                use std::io::Write;
-
                 if let Some(path) = rfd::FileDialog::new()
                     .set_title("Salvar regras")
                     .add_filter("Arquivo de texto", &["txt"])
                     .save_file()
                 {
-                    if let Ok(mut file) = File::create(&path) {
-                        writeln!(
-                            file,
-                            "WIDTH {} HEIGHT {}",
-                            self.grid.width, self.grid.height
-                        )
-                        .ok();
-
-                        writeln!(file, "STATE {{").ok();
-                        for state in &self.states {
-                            let r = (state.color.r * 255.0).round() as u8;
-                            let g = (state.color.g * 255.0).round() as u8;
-                            let b = (state.color.b * 255.0).round() as u8;
-                            let w = state.weight;
-                            writeln!(file, "    {}({}, {}, {}, {})", state.name, r, g, b, w).ok();
-                        }
-                        writeln!(file, "}}\n").ok();
-
-                        writeln!(file, "RULES {{").ok();
-                        for rule in &self.rules {
-                            let conditions = rule.conditions_as_string();
-                            writeln!(
-                                file,
-                                "    IF current is '{}' AND {} THEN next is '{}' WITH PROB {}",
-                                rule.current_state_name,
-                                conditions,
-                                rule.next_state_name,
-                                rule.probability
-                            )
-                            .ok();
+                    match std::fs::write(&path, self.rules_as_text()) {
+                        Ok(()) => {
+                            println!("Rules, states and probabilities exported to {:?}", path)
                         }
-                        writeln!(file, "}}").ok();
-
-                        println!("Rules, states and probabilities exported to {:?}", path);
-                    } else {
-                        println!("Error creating file: {:?}", path);
+                        Err(_) => println!("Error creating file: {:?}", path),
                     }
                 } else {
                     println!("Export canceled by user");
@@ -1046,90 +2301,13 @@ impl Application for CASimulator {
             }
 
             Message::ImportRules => {
-                use std::fs::File;
-                use std::io::{BufRead, BufReader};
-
                 let path_opt = rfd::FileDialog::new()
                     .add_filter("Text Files", &["txt"])
                     .pick_file();
 
                 if let Some(path) = path_opt {
-                    if let Ok(file) = File::open(&path) {
-                        let reader = BufReader::new(file);
-
-                        self.states.clear();
-                        self.rules.clear();
-
-                        let mut grid_width = 0;
-                        let mut grid_height = 0;
-
-                        let mut in_states = false;
-                        let mut in_rules = false;
-
-                        for line in reader.lines().flatten() {
-                            let line = line.trim();
-
-                            if line.is_empty() {
-                                continue;
-                            }
-
-                            if line.starts_with("WIDTH") {
-                                let parts: Vec<&str> = line.split_whitespace().collect();
-                                if parts.len() >= 4 {
-                                    grid_width = parts[1].parse::<usize>().unwrap_or(50);
-                                    grid_height = parts[3].parse::<usize>().unwrap_or(50);
-                                }
-                            } else if line.starts_with("STATE") && line.contains('{') {
-                                in_states = true;
-                                in_rules = false;
-                            } else if line.starts_with("RULES") && line.contains('{') {
-                                in_rules = true;
-                                in_states = false;
-                            } else if line == "}" {
-                                in_states = false;
-                                in_rules = false;
-                            } else if in_states {
-                                // Parse de estado: nome(r,g,b,weight)
-                                if let Some(start) = line.find('(') {
-                                    if let Some(end) = line.find(')') {
-                                        let name =
-                                            line[..start].trim().trim_end_matches(',').to_string();
-                                        let nums: Vec<u8> = line[start + 1..end]
-                                            .split(',')
-                                            .map(|v| v.trim().parse().unwrap_or(0))
-                                            .collect();
-
-                                        let (r, g, b, weight) = if nums.len() == 4 {
-                                            (nums[0], nums[1], nums[2], nums[3])
-                                        } else if nums.len() == 3 {
-                                            (nums[0], nums[1], nums[2], 1)
-                                        } else {
-                                            (0, 0, 0, 1)
-                                        };
-
-                                        let color = Color::from_rgb8(r, g, b);
-                                        let id = self.states.len() as u8;
-
-                                        self.states.push(CAState {
-                                            id,
-                                            name,
-                                            color,
-                                            weight,
-                                        });
-                                    }
-                                }
-                            } else if in_rules {
-                                if let Ok(rule) = parse_rule(line, &self.states) {
-                                    self.rules.push(rule);
-                                }
-                            }
-                        }
-
-                        self.grid.width = grid_width;
-                        self.grid.height = grid_height;
-
-                        self.grid_cache.clear();
-
+                    if let Ok(content) = std::fs::read_to_string(&path) {
+                        self.import_rules_text(&content);
                         println!("Imported rules, states and grid size from {:?}", path);
                     } else {
                         println!("Error opening file: {:?}", path);
@@ -1140,40 +2318,466 @@ impl Application for CASimulator {
 
                 return Command::none();
             }
+            Message::CopyRulesToClipboard => {
+                return iced::clipboard::write(self.rules_as_text());
+            }
+            Message::PasteRulesFromClipboard => {
+                return iced::clipboard::read(Message::RulesPasted);
+            }
+            Message::RulesPasted(contents) => match contents {
+                Some(text) if !text.trim().is_empty() => self.import_rules_text(&text),
+                _ => self.import_errors = vec!["Clipboard is empty or not text".into()],
+            },
+            Message::ImportModeSelected(mode) => self.import_mode = mode,
+            Message::AutosaveToggled(enabled) => self.autosave_enabled = enabled,
+            Message::AutosaveIntervalChanged(secs) => self.autosave_interval_input = secs,
+            Message::PanStepChanged(step) => self.pan_step_input = step,
+            Message::KeyboardPan(dx, dy) => {
+                // Arrow keys move the view, so the grid shifts the opposite way
+                let step = self
+                    .pan_step_input
+                    .trim()
+                    .parse::<f32>()
+                    .unwrap_or(DEFAULT_PAN_STEP_PX);
+                let offset = self.offset.get();
+                self.offset
+                    .set(Point::new(offset.x - dx * step, offset.y - dy * step));
+                self.grid_cache.clear();
+            }
+            Message::KeyboardZoom(direction) => {
+                // Zooms around the canvas' top-left corner, which stays fixed
+                let old_zoom = self.zoom.get();
+                let new_zoom = (old_zoom + direction * KEYBOARD_ZOOM_STEP).clamp(0.1, 10.0);
+                let offset = self.offset.get();
+                self.zoom.set(new_zoom);
+                self.offset.set(Point::new(
+                    offset.x * new_zoom / old_zoom,
+                    offset.y * new_zoom / old_zoom,
+                ));
+                self.grid_cache.clear();
+            }
+            Message::Autosave => match serde_json::to_string(&self.session()) {
+                Ok(json) => {
+                    if let Err(e) = std::fs::write(autosave_path(), json) {
+                        eprintln!("Failed to autosave session: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Failed to serialize session: {}", e),
+            },
+            Message::RestoreAutosave => {
+                match std::fs::read_to_string(autosave_path()) {
+                    Ok(data) => match serde_json::from_str::<Session>(&data) {
+                        Ok(session) => match session.grid.validate_dimensions() {
+                            Ok(()) => self.apply_session(session),
+                            Err(e) => eprintln!("Rejected malformed autosaved grid: {}", e),
+                        },
+                        Err(e) => eprintln!("Failed to parse autosaved session: {}", e),
+                    },
+                    Err(e) => eprintln!("Failed to read autosaved session: {}", e),
+                }
+                self.recovery_available = false;
+            }
+            Message::SaveProject => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_file_name("project.json")
+                    .add_filter("JSON", &["json"])
+                    .save_file()
+                {
+                    match serde_json::to_string_pretty(&self.session()) {
+                        Ok(json) => {
+                            if let Err(e) = std::fs::write(&path, json) {
+                                self.show_error(
+                                    "Save Project",
+                                    format!("Failed to save project: {}", e),
+                                );
+                            }
+                        }
+                        Err(e) => self.show_error(
+                            "Save Project",
+                            format!("Failed to serialize project: {}", e),
+                        ),
+                    }
+                }
+            }
+            Message::LoadProject => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .pick_file()
+                {
+                    // States, rules and grid are replaced together, or not at all
+                    let loaded = std::fs::read_to_string(&path)
+                        .map_err(|e| format!("Failed to read file: {}", e))
+                        .and_then(|data| {
+                            serde_json::from_str::<Session>(&data)
+                                .map_err(|e| format!("Failed to parse project JSON: {}", e))
+                        })
+                        .and_then(|session| {
+                            session
+                                .grid
+                                .validate_dimensions()
+                                .map(|()| session)
+                                .map_err(|e| format!("Rejected malformed grid: {}", e))
+                        });
+                    match loaded {
+                        Ok(session) => {
+                            self.is_simulating = false;
+                            self.apply_session(session);
+                            self.transition_flash.clear();
+                            self.grid_cache.clear();
+                        }
+                        Err(e) => self.show_error("Load Project", e),
+                    }
+                }
+            }
+            Message::ValidateModel => self.model_issues = Some(self.validate_model()),
+            Message::DismissModelIssues => self.model_issues = None,
+            Message::DiscardAutosave => {
+                if let Err(e) = std::fs::remove_file(autosave_path()) {
+                    eprintln!("Failed to remove autosaved session: {}", e);
+                }
+                self.recovery_available = false;
+            }
+            Message::ResetView => self.fit_view(),
             Message::ToggleFullscreen => {
                 self.fullscreen_mode = !self.fullscreen_mode;
             }
+            Message::BorderWidthChanged(val) => {
+                if let Some(width) = val
+                    .trim()
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|w| (0.0..=MAX_BORDER_WIDTH).contains(w))
+                {
+                    self.border_width = width;
+                    self.grid_cache.clear();
+                }
+                self.border_width_input = val;
+            }
+            Message::BorderModeSelected(mode) => {
+                self.border_mode = mode;
+                self.grid_cache.clear();
+            }
+            Message::AgeShadingToggled(enabled) => {
+                self.show_age_shading = enabled;
+                self.grid_cache.clear();
+            }
+            Message::TransitionFlashesToggled(enabled) => {
+                self.show_transition_flashes = enabled;
+                self.transition_flash.clear();
+                self.grid_cache.clear();
+            }
             Message::SaveGrid => {
                 if let Some(path) = rfd::FileDialog::new()
                     .set_file_name("grid.json")
                     .save_file()
                 {
-                    if let Ok(json) = serde_json::to_string(&self.grid) {
+                    let saved = SavedGrid {
+                        grid: self.grid.clone(),
+                        view: Some(self.view_state()),
+                    };
+                    if let Ok(json) = serde_json::to_string(&saved) {
                         if let Err(e) = std::fs::write(&path, json) {
-                            eprintln!("Failed to save grid: {}", e);
+                            self.show_error("Save Grid", format!("Failed to save grid: {}", e));
                         }
                     } else {
-                        eprintln!("Failed to serialize grid");
+                        self.show_error("Save Grid", "Failed to serialize grid".into());
+                    }
+                }
+            }
+            Message::ExportCsv => {
+                let written = rfd::FileDialog::new()
+                    .set_file_name("grid.csv")
+                    .add_filter("CSV", &["csv"])
+                    .save_file()
+                    .map(|path| std::fs::write(path, self.grid.to_csv()));
+                if let Some(Err(e)) = written {
+                    self.show_error("Export CSV", format!("Failed to save grid: {}", e));
+                }
+            }
+            Message::ImportCsv => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .pick_file()
+                {
+                    match std::fs::read_to_string(&path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|text| CAGrid::cells_from_csv(&text))
+                    {
+                        // Neighborhood and boundary stay as they are
+                        Ok(cells) => {
+                            self.grid.height = cells.len();
+                            self.grid.width = cells[0].len();
+                            self.grid.cells = cells;
+                            self.grid_width_input = self.grid.width.to_string();
+                            self.grid_height_input = self.grid.height.to_string();
+                            self.grid_seed = None;
+                            self.reset_history();
+                            self.fit_view();
+                        }
+                        Err(e) => self.show_error("Import CSV", format!("Invalid grid CSV: {}", e)),
+                    }
+                }
+            }
+            Message::SweepParamSelected(param) => self.sweep_param = param,
+            Message::SweepInputChanged(idx, val) => {
+                if let Some(field) = self.sweep_inputs.get_mut(idx) {
+                    *field = val;
+                }
+                self.sweep_error = None;
+            }
+            Message::StartSweep => self.start_sweep(),
+            Message::SweepTick => self.run_sweep_step(),
+            Message::CancelSweep => self.finish_sweep(),
+            Message::RunUntilKindSelected(kind) => self.run_until_kind = kind,
+            Message::RunUntilStateSelected(state) => self.run_until_state = Some(state),
+            Message::RunUntilOperatorSelected(op) => self.run_until_operator = Some(op),
+            Message::RunUntilValueChanged(val) => self.run_until_value = val,
+            Message::RunUntil(condition) => {
+                self.is_simulating = false;
+                self.run_until_report = Some(self.run_until(&condition));
+                self.grid_cache.clear();
+            }
+            Message::ExportSweepCsv => {
+                let Some(run) = &self.sweep else {
+                    return Command::none();
+                };
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_file_name("sweep.csv")
+                    .add_filter("CSV", &["csv"])
+                    .save_file()
+                {
+                    let csv = results_csv(run.param, &run.state_names, &run.results);
+                    if let Err(e) = std::fs::write(&path, csv) {
+                        self.show_error(
+                            "Export CSV",
+                            format!("Failed to save sweep results: {}", e),
+                        );
+                    }
+                }
+            }
+            Message::ExportConfig => {
+                if self.grid_seed.is_none() {
+                    eprintln!(
+                        "The current grid was not generated from a seed; \
+                         the exported config will rebuild a new grid from seed {}",
+                        self.seed
+                    );
+                }
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_file_name("experiment.json")
+                    .add_filter("JSON", &["json"])
+                    .save_file()
+                {
+                    match serde_json::to_string_pretty(&self.experiment_config()) {
+                        Ok(json) => {
+                            if let Err(e) = std::fs::write(&path, json) {
+                                self.show_error(
+                                    "Export Config",
+                                    format!("Failed to save config: {}", e),
+                                );
+                            }
+                        }
+                        Err(e) => self.show_error(
+                            "Export Config",
+                            format!("Failed to serialize config: {}", e),
+                        ),
                     }
                 }
             }
-            Message::LoadGrid => {
+            Message::ImportConfig => {
                 if let Some(path) = rfd::FileDialog::new()
                     .add_filter("JSON", &["json"])
                     .pick_file()
                 {
                     match std::fs::read_to_string(&path) {
-                        Ok(data) => match serde_json::from_str::<CAGrid>(&data) {
-                            Ok(grid) => {
-                                self.grid = grid;
+                        Ok(data) => match serde_json::from_str::<ExperimentConfig>(&data) {
+                            Ok(config) if config.width == 0 || config.height == 0 => {
+                                self.show_error(
+                                    "Import Config",
+                                    "Rejected config with an empty grid".into(),
+                                );
                             }
-                            Err(e) => eprintln!("Failed to parse grid JSON: {}", e),
+                            Ok(config) => match config.neighborhood.validate() {
+                                Ok(()) => self.apply_experiment_config(config),
+                                Err(e) => self.show_error(
+                                    "Import Config",
+                                    format!("Rejected neighborhood: {}", e),
+                                ),
+                            },
+                            Err(e) => self.show_error(
+                                "Import Config",
+                                format!("Failed to parse config JSON: {}", e),
+                            ),
                         },
-                        Err(e) => eprintln!("Failed to read file: {}", e),
+                        Err(e) => {
+                            self.show_error("Import Config", format!("Failed to read file: {}", e))
+                        }
+                    }
+                }
+            }
+            Message::LoadGrid => match pick_grid_file() {
+                Some(Ok(saved)) => {
+                    self.grid_width_input = saved.grid.width.to_string();
+                    self.grid_height_input = saved.grid.height.to_string();
+                    self.grid = saved.grid;
+                    self.grid_seed = None;
+                    self.reset_history();
+                    match saved.view {
+                        Some(view) => self.apply_view_state(view),
+                        None => self.fit_view(),
+                    }
+                }
+                Some(Err(e)) => self.show_error("Load Grid", e),
+                None => {}
+            },
+            Message::ImportRle => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("RLE", &["rle"])
+                    .pick_file()
+                {
+                    match std::fs::read_to_string(&path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|text| parse_rle(&text))
+                    {
+                        Ok(pattern) => self.stamp_pattern(&pattern),
+                        Err(e) => self.show_error("RLE import failed", e),
+                    }
+                }
+            }
+            Message::ImportCells => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Plaintext", &["cells"])
+                    .pick_file()
+                {
+                    match std::fs::read_to_string(&path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|text| parse_cells(&text))
+                    {
+                        Ok(pattern) => self.stamp_pattern(&pattern),
+                        Err(e) => self.show_error("Plaintext import failed", e),
+                    }
+                }
+            }
+            Message::PatternPlacementSelected(placement) => self.pattern_placement = placement,
+            Message::LoadComparisonGrid => match pick_grid_file() {
+                Some(Ok(saved)) => {
+                    self.comparison_grid = Some(saved.grid);
+                    self.show_comparison_diff = true;
+                    self.grid_cache.clear();
+                }
+                Some(Err(e)) => self.show_error("Load Comparison Grid", e),
+                None => {}
+            },
+            Message::ClearComparisonGrid => {
+                self.comparison_grid = None;
+                self.show_comparison_diff = false;
+                self.grid_cache.clear();
+            }
+            Message::ComparisonDiffToggled(enabled) => {
+                self.show_comparison_diff = enabled;
+                self.grid_cache.clear();
+            }
+            Message::NeighborhoodChanged(nb) => {
+                self.grid.neighborhood = nb;
+                self.model_revision += 1;
+            }
+            Message::LoadBilliardBallRules => {
+                let ball = self
+                    .states
+                    .iter()
+                    .find(|s| s.id != self.background_state_id)
+                    .map(|s| s.id);
+                match ball {
+                    Some(ball) => {
+                        self.block_rules = billiard_ball_rules(self.background_state_id, ball);
+                        self.model_revision += 1;
+                    }
+                    None => self.show_error(
+                        "Billiard-ball rules",
+                        "Add a state besides the background to use as the ball.".into(),
+                    ),
+                }
+            }
+            Message::ClearBlockRules => {
+                self.block_rules.clear();
+                self.model_revision += 1;
+            }
+            Message::LifeLikeNotationChanged(val) => {
+                self.life_like_input = val;
+                self.life_like_error = None;
+            }
+            Message::ApplyLifeLike(notation) => {
+                let neighbor_count = self.grid.neighborhood.offsets().len() as u16;
+                match parse_life_like(&notation) {
+                    Ok((birth, survival))
+                        if birth.iter().chain(&survival).any(|&n| n > neighbor_count) =>
+                    {
+                        self.life_like_error = Some(format!(
+                            "Counts above {} can't occur in the {} neighborhood",
+                            neighbor_count, self.grid.neighborhood
+                        ));
+                    }
+                    Ok((birth, survival)) => {
+                        self.record_model_edit(false);
+                        self.discard_rule_form();
+                        self.states = life_like_states();
+                        self.rules =
+                            life_like_rules(&self.states[0], &self.states[1], &birth, &survival);
+                        self.model_header = vec![format!("Life-like rule {}", notation.trim())];
+                        self.selected_example = None;
+                        self.life_like_error = None;
+                        self.ensure_background_state();
+                        self.grid_cache.clear();
                     }
+                    Err(e) => self.life_like_error = Some(e),
+                }
+            }
+            Message::ExampleParamChanged(param, val) => {
+                let field = match param {
+                    ExampleParam::ForestBurnOut => &mut self.forest_burn_out_input,
+                    ExampleParam::ForestSpread => &mut self.forest_spread_input,
+                    ExampleParam::ForestGrowth => &mut self.forest_growth_input,
+                    ExampleParam::ForestLightning => &mut self.forest_lightning_input,
+                    ExampleParam::LifeBirth => &mut self.life_birth_input,
+                    ExampleParam::LifeSurvival => &mut self.life_survival_input,
+                };
+                *field = val;
+                self.example_params_error = None;
+            }
+            Message::RingInnerChanged(val) => {
+                self.ring_inner_input = val;
+                self.apply_ring_inputs();
+            }
+            Message::RingOuterChanged(val) => {
+                self.ring_outer_input = val;
+                self.apply_ring_inputs();
+            }
+            Message::NeighborhoodRadiusChanged(val) => {
+                self.moore_radius_input = val;
+                // Editing the radius while that neighborhood is active updates it in place
+                if let (Neighborhood::MooreRadius(_), Some(moore)) =
+                    (self.grid.neighborhood, self.moore_radius_from_input())
+                {
+                    self.grid.neighborhood = moore;
+                    self.model_revision += 1;
                 }
             }
-            Message::NeighborhoodChanged(nb) => self.grid.neighborhood = nb,
+            Message::NoMatchPolicySelected(policy) => {
+                self.no_match_policy = policy;
+                self.model_revision += 1;
+            }
+            Message::RuleEvalModeSelected(mode) => {
+                self.rule_eval_mode = mode;
+                self.model_revision += 1;
+            }
+            Message::BoundaryModeChanged(mode) => {
+                self.grid.boundary = mode;
+                self.model_revision += 1;
+            }
+            Message::BoundaryWallStateSelected(state) => {
+                self.grid.boundary = BoundaryMode::Fixed(state.id);
+                self.model_revision += 1;
+            }
             Message::GridWidthChanged(w) => self.grid_width_input = w,
             Message::GridHeightChanged(h) => self.grid_height_input = h,
             Message::ApplyGridSize => {
@@ -1182,19 +2786,63 @@ impl Application for CASimulator {
                     .grid_height_input
                     .parse()
                     .unwrap_or(DEFAULT_GRID_HEIGHT);
-                self.grid = CAGrid::new(width, height, self.states.clone(), self.grid.neighborhood);
+                if self.preserve_grid_contents {
+                    self.grid.resize(width, height, self.background_state_id);
+                    self.grid_seed = None;
+                    self.reset_history();
+                    self.annotations
+                        .retain(|&(r, c), _| r < height && c < width);
+                } else {
+                    self.regenerate_grid(width, height);
+                }
                 self.grid_cache.clear();
             }
+            Message::PreserveGridContentsToggled(enabled) => self.preserve_grid_contents = enabled,
             Message::ResetGrid => {
-                self.grid = CAGrid::new(
-                    self.grid.width,
-                    self.grid.height,
-                    self.states.clone(),
-                    self.grid.neighborhood,
-                );
+                self.regenerate_grid(self.grid.width, self.grid.height);
+                self.fit_view();
+            }
+            Message::FindInterestingSeed => {
+                self.is_simulating = false;
+                self.simulation_timer = None;
+                self.find_interesting_seed();
+                self.fit_view();
+            }
+            Message::SeedChanged(val) => {
+                // Only a valid number replaces the seed in use
+                if let Ok(seed) = val.trim().parse() {
+                    self.seed = seed;
+                    self.model_revision += 1;
+                }
+                self.seed_input = val;
+            }
+            Message::DeterministicToggled(enabled) => {
+                self.force_deterministic = enabled;
+                self.model_revision += 1;
+            }
+            Message::RandomizeSeed => {
+                self.seed = rand::random();
+                self.model_revision += 1;
+                self.seed_input = self.seed.to_string();
+            }
+            Message::ClearGrid => {
+                self.grid.fill(self.selected_paint_state_id);
+                self.grid_seed = None;
+                self.reset_history();
+                self.transition_flash.clear();
+                self.grid_cache.clear();
+            }
+            Message::SeedPatternSelected(pattern) => {
+                // Seeded like regenerate_grid, so a random disc can be redrawn exactly
+                self.grid.apply_seed_pattern(
+                    pattern,
+                    self.background_state_id,
+                    self.selected_paint_state_id,
+                    &mut StdRng::seed_from_u64(self.seed),
+                );
+                self.grid_seed = None;
+                self.reset_history();
                 self.grid_cache.clear();
-                self.zoom.set(1.0);
-                self.offset = Point::new(0.0, 0.0).into();
             }
             Message::ToggleSimulation => {
                 self.is_simulating = !self.is_simulating;
@@ -1204,7 +2852,74 @@ impl Application for CASimulator {
                     None
                 };
             }
-            Message::NextStep => self.step_simulation_logic(),
+            Message::NextStep => self.advance_generation(),
+            Message::StepBack => self.step_back(),
+            Message::StopOnStableToggled(enabled) => self.stop_on_stable = enabled,
+            Message::StopAtChanged(val) => {
+                // Empty (or anything that isn't a number) means never stop
+                self.stop_at_generation = val.trim().parse().ok();
+                self.stop_at_input = val;
+            }
+            Message::GifRecordingToggled(enabled) => {
+                self.gif_recording = enabled;
+                // Recording starts from the generation currently shown
+                if enabled && self.gif_frames.is_empty() {
+                    self.record_gif_frame();
+                }
+            }
+            Message::ClearGifFrames => self.gif_frames.clear(),
+            Message::ExportCaptionToggled(enabled) => self.export_caption = enabled,
+            Message::ExportPng => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_file_name("grid.png")
+                    .add_filter("PNG", &["png"])
+                    .save_file()
+                {
+                    let cells = self.grid.cells.iter().flatten().copied().collect();
+                    let mut job = self.image_export(path, vec![cells]);
+                    if self.export_caption {
+                        job.caption = Some(format!(
+                            "{} | Gen {} | {}x{} | Zoom {:.1}x",
+                            self.model_name(),
+                            self.generation,
+                            self.grid.width,
+                            self.grid.height,
+                            self.zoom.get()
+                        ));
+                    }
+                    self.export_in_progress = true;
+                    self.export_status = Some("Exporting PNG...".into());
+                    return Command::perform(
+                        export::run_in_background(job, export::encode_png),
+                        Message::ExportFinished,
+                    );
+                }
+            }
+            Message::ExportGif => {
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_file_name("simulation.gif")
+                    .add_filter("GIF", &["gif"])
+                    .save_file()
+                {
+                    let job = self.image_export(path, self.gif_frames.clone());
+                    self.export_in_progress = true;
+                    self.export_status = Some(format!(
+                        "Encoding {} GIF frame(s)...",
+                        self.gif_frames.len()
+                    ));
+                    return Command::perform(
+                        export::run_in_background(job, export::encode_gif),
+                        Message::ExportFinished,
+                    );
+                }
+            }
+            Message::ExportFinished(result) => {
+                self.export_in_progress = false;
+                self.export_status = Some(match result {
+                    Ok(path) => format!("Exported to {}", path.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                });
+            }
             Message::SimulationSpeedChanged(value) => {
                 let inv_value = 100.0 - value;
                 self.simulation_speed_ms = (10.0 + inv_value * 9.9) as u64;
@@ -1217,7 +2932,89 @@ impl Application for CASimulator {
                 );
             }
             Message::PaintCell(row, col, state_id) => {
-                self.grid.cells[row][col] = state_id;
+                // Square brush centered on the cell, cut off at the grid edges
+                let reach = self.brush_size / 2;
+                let rows = row.saturating_sub(reach)..(row + reach + 1).min(self.grid.height);
+                let cols = col.saturating_sub(reach)..(col + reach + 1).min(self.grid.width);
+                let square: Vec<(usize, usize)> = rows
+                    .flat_map(|r| cols.clone().map(move |c| (r, c)))
+                    .collect();
+                self.paint_cells(square, state_id);
+            }
+            Message::LineAnchorSet(row, col) => self.line_anchor = Some((row, col)),
+            Message::PaintLine(r0, c0, r1, c1) => {
+                self.line_anchor = None;
+                // The grid may have shrunk since the anchor was set
+                let clamp = |r: usize, c: usize| {
+                    (
+                        r.min(self.grid.height.saturating_sub(1)),
+                        c.min(self.grid.width.saturating_sub(1)),
+                    )
+                };
+                let line = line_cells(clamp(r0, c0), clamp(r1, c1));
+                self.paint_cells(line, self.selected_paint_state_id);
+            }
+            Message::PaintRect(r0, c0, r1, c1, filled) => {
+                let max_row = self.grid.height.saturating_sub(1);
+                let max_col = self.grid.width.saturating_sub(1);
+                let (top, bottom) = (r0.min(r1).min(max_row), r0.max(r1).min(max_row));
+                let (left, right) = (c0.min(c1).min(max_col), c0.max(c1).min(max_col));
+                let rect: Vec<(usize, usize)> = (top..=bottom)
+                    .flat_map(|r| (left..=right).map(move |c| (r, c)))
+                    .filter(|&(r, c)| filled || r == top || r == bottom || c == left || c == right)
+                    .collect();
+                self.paint_cells(rect, self.selected_paint_state_id);
+            }
+            Message::RectFilledToggled(filled) => self.rect_filled = filled,
+            // Kept odd so the brush has a center cell
+            Message::BrushSizeChanged(size) => self.brush_size = (size as usize).max(1) | 1,
+            Message::PauseWhilePaintingToggled(enabled) => self.pause_while_painting = enabled,
+            Message::CanvasToolSelected(tool) => {
+                self.canvas_tool = tool;
+                self.line_anchor = None;
+                self.rect_start.set(None);
+            }
+            // The canvas already stored the cell; the message only rebuilds the readout
+            Message::CellHovered => {}
+            Message::PokeCell(row, col) => {
+                self.poke_cell(row, col);
+                self.grid_cache.clear();
+            }
+            Message::AnnotationCellSelected(row, col) => {
+                self.selected_annotation_cell = Some((row, col));
+                self.annotation_input = self
+                    .annotations
+                    .get(&(row, col))
+                    .cloned()
+                    .unwrap_or_default();
+            }
+            Message::AnnotationTextChanged(text) => self.annotation_input = text,
+            Message::SaveAnnotation => {
+                if let Some(cell) = self.selected_annotation_cell {
+                    let label = self.annotation_input.trim();
+                    if label.is_empty() {
+                        self.annotations.remove(&cell);
+                    } else {
+                        self.annotations.insert(cell, label.to_string());
+                    }
+                    self.grid_cache.clear();
+                }
+            }
+            Message::RemoveAnnotation => {
+                if let Some(cell) = self.selected_annotation_cell.take() {
+                    self.annotations.remove(&cell);
+                    self.annotation_input.clear();
+                    self.grid_cache.clear();
+                }
+            }
+            Message::BulkFromStateSelected(state) => self.bulk_from_state = Some(state),
+            Message::BulkToStateSelected(state) => self.bulk_to_state = Some(state),
+            Message::ReplaceState(from_id, to_id) => {
+                self.grid.replace_state(from_id, to_id);
+                self.grid_cache.clear();
+            }
+            Message::SwapStates(a_id, b_id) => {
+                self.grid.swap_states(a_id, b_id);
                 self.grid_cache.clear();
             }
         }
@@ -1243,6 +3040,7 @@ impl Application for CASimulator {
                 } else {
                     theme::Button::Secondary
                 }),
+            button(text("Check Model")).on_press(Message::ValidateModel),
         ]
         .spacing(10);
 
@@ -1251,10 +3049,73 @@ impl Application for CASimulator {
             TabId::Simulation => self.view_simulation_tab(),
         };
 
-        column![header, tab_buttons, content]
-            .spacing(20)
-            .padding(20)
-            .into()
+        let mut layout = column![header].spacing(20).padding(20);
+
+        if self.recovery_available {
+            layout = layout.push(
+                row![
+                    text("An autosaved session from a previous run was found.")
+                        .style(Color::from_rgb8(255, 200, 0)),
+                    button("Restore").on_press(Message::RestoreAutosave),
+                    button("Discard")
+                        .on_press(Message::ShowModal(Modal::Confirm {
+                            title: "Discard autosave".into(),
+                            message: "The autosaved session will be deleted and cannot be \
+                                      restored afterwards."
+                                .into(),
+                            on_confirm: Box::new(Message::DiscardAutosave),
+                        }))
+                        .style(theme::Button::Destructive),
+                ]
+                .spacing(10)
+                .align_items(iced::Alignment::Center),
+            );
+        }
+
+        if let Some(warning) = &self.form_warning {
+            layout = layout.push(
+                row![
+                    text(warning).style(Color::from_rgb8(255, 200, 0)),
+                    button("Dismiss").on_press(Message::DismissFormWarning),
+                ]
+                .spacing(10)
+                .align_items(iced::Alignment::Center),
+            );
+        }
+
+        if let Some(issues) = &self.model_issues {
+            let summary = if issues.is_empty() {
+                text("No problems found in the model.").style(Color::from_rgb8(0, 200, 0))
+            } else {
+                text(format!("{} problem(s) found in the model:", issues.len()))
+                    .style(Color::from_rgb8(255, 200, 0))
+            };
+            let issue_list = issues.iter().fold(column![].spacing(2), |col, issue| {
+                col.push(
+                    text(format!("- {}", issue))
+                        .size(14)
+                        .style(Color::from_rgb8(255, 200, 0)),
+                )
+            });
+            layout = layout.push(
+                column![
+                    row![
+                        summary,
+                        button("Dismiss").on_press(Message::DismissModelIssues)
+                    ]
+                    .spacing(10)
+                    .align_items(iced::Alignment::Center),
+                    issue_list,
+                ]
+                .spacing(5),
+            );
+        }
+
+        let layout = layout.push(tab_buttons).push(content);
+        match &self.active_modal {
+            Some(modal) => ModalLayer::new(layout, modal::dialog(modal)).into(),
+            None => layout.into(),
+        }
     }
 
     fn theme(&self) -> Theme {
@@ -1262,155 +3123,1715 @@ impl Application for CASimulator {
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        if self.is_simulating {
+        let simulation = if self.is_simulating {
             iced::time::every(Duration::from_millis(self.simulation_speed_ms))
                 .map(|_| Message::Tick(()))
         } else {
             Subscription::none()
-        }
+        };
+
+        let autosave = if self.autosave_enabled {
+            iced::time::every(Duration::from_secs(self.autosave_interval_secs()))
+                .map(|_| Message::Autosave)
+        } else {
+            Subscription::none()
+        };
+
+        // Keys typed into a text input are captured and never reach this
+        let navigation = if self.active_tab == TabId::Simulation && !self.is_simulating {
+            iced::keyboard::on_key_press(|key, _modifiers| {
+                use iced::keyboard::key::{Key, Named};
+                match key.as_ref() {
+                    Key::Named(Named::ArrowLeft) => Some(Message::KeyboardPan(-1.0, 0.0)),
+                    Key::Named(Named::ArrowRight) => Some(Message::KeyboardPan(1.0, 0.0)),
+                    Key::Named(Named::ArrowUp) => Some(Message::KeyboardPan(0.0, -1.0)),
+                    Key::Named(Named::ArrowDown) => Some(Message::KeyboardPan(0.0, 1.0)),
+                    Key::Character("+") | Key::Character("=") => Some(Message::KeyboardZoom(1.0)),
+                    Key::Character("-") => Some(Message::KeyboardZoom(-1.0)),
+                    _ => None,
+                }
+            })
+        } else {
+            Subscription::none()
+        };
+
+        // Off while a dialog is open: a confirm may refer to a state or rule by its
+        // position, which an undo would shift under it
+        let undo = if self.active_tab == TabId::Definition && self.active_modal.is_none() {
+            iced::keyboard::on_key_press(|key, modifiers| {
+                use iced::keyboard::key::Key;
+                if !modifiers.command() {
+                    return None;
+                }
+                // Shift may report the letter in upper case
+                match key.as_ref() {
+                    Key::Character("z" | "Z") if modifiers.shift() => Some(Message::Redo),
+                    Key::Character("z") => Some(Message::Undo),
+                    Key::Character("y" | "Y") => Some(Message::Redo),
+                    _ => None,
+                }
+            })
+        } else {
+            Subscription::none()
+        };
+
+        // One sweep run per tick keeps the window responsive during long sweeps
+        let sweep = if self.sweep_running {
+            iced::time::every(Duration::from_millis(1)).map(|_| Message::SweepTick)
+        } else {
+            Subscription::none()
+        };
+
+        Subscription::batch([simulation, autosave, navigation, undo, sweep])
     }
 }
 
 impl CASimulator {
-    fn step_simulation_logic(&mut self) {
-        if self.states.is_empty() {
-            return;
+    /// Resets pan and zoom so the whole grid is visible on the canvas.
+    pub fn fit_view(&self) {
+        self.zoom.set(1.0);
+        self.offset.set(Point::new(0.0, 0.0));
+        self.grid_cache.clear();
+    }
+
+    /// Snapshot of the model and grid for saving.
+    pub fn session(&self) -> Session {
+        Session {
+            states: self.states.clone(),
+            rules: self.rules.clone(),
+            grid: self.grid.clone(),
+            block_rules: self.block_rules.clone(),
+            background_state_id: self.background_state_id,
+            no_match_policy: self.no_match_policy,
+            rule_eval_mode: self.rule_eval_mode,
+            annotations: self
+                .annotations
+                .iter()
+                .map(|(&cell, label)| (cell, label.clone()))
+                .collect(),
+            view: Some(self.view_state()),
+            active_tab: Some(self.active_tab),
         }
+    }
 
-        let width = self.grid.width;
-        let height = self.grid.height;
-        let grid_size = width * height;
+    /// Loaded rules can't be trusted to be well-formed; bad ones are dropped and logged.
+    fn drop_invalid_rules(&mut self) {
+        self.rules.retain(|rule| match rule.validate() {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!(
+                    "Dropped invalid rule for '{}': {}",
+                    rule.current_state_name, e
+                );
+                false
+            }
+        });
+    }
 
-        let current_grid_flat: Vec<u8> = self
-            .grid
-            .cells
-            .iter()
-            .flat_map(|row| row.iter())
-            .copied()
-            .collect();
-        let mut next_grid_flat = vec![0u8; grid_size];
+    /// The setup needed to rerun from the current grid's seed, without the cells.
+    pub fn experiment_config(&self) -> ExperimentConfig {
+        ExperimentConfig {
+            states: self.states.clone(),
+            rules: self.rules.clone(),
+            block_rules: self.block_rules.clone(),
+            width: self.grid.width,
+            height: self.grid.height,
+            neighborhood: self.grid.neighborhood,
+            boundary: self.grid.boundary,
+            background_state_id: self.background_state_id,
+            no_match_policy: self.no_match_policy,
+            rule_eval_mode: self.rule_eval_mode,
+            seed: self.grid_seed.unwrap_or(self.seed),
+            simulation_speed_ms: self.simulation_speed_ms,
+        }
+    }
 
-        let mut neighbor_counts: Vec<Vec<u8>> = vec![vec![0; grid_size]; self.states.len()];
-        for state in &self.states {
-            let id = state.id as usize;
-            for r in 0..height {
-                for c in 0..width {
-                    neighbor_counts[id][r * width + c] = self.grid.count_neighbors(r, c, state.id);
+    /// Loads a config's model and settings, regenerating the initial grid from its seed.
+    fn apply_experiment_config(&mut self, config: ExperimentConfig) {
+        self.discard_rule_form();
+        self.state_name_edit = None;
+        self.model_revision += 1;
+        self.states = config.states;
+        self.rules = config.rules;
+        self.block_rules = config.block_rules;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.drop_invalid_rules();
+        self.background_state_id = config.background_state_id;
+        self.ensure_background_state();
+        self.no_match_policy = config.no_match_policy;
+        self.rule_eval_mode = config.rule_eval_mode;
+        self.simulation_speed_ms = config.simulation_speed_ms;
+
+        self.grid = CAGrid::new_with_rng(
+            config.width,
+            config.height,
+            self.states.clone(),
+            config.neighborhood,
+            self.background_state_id,
+            &mut StdRng::seed_from_u64(config.seed),
+        );
+        self.grid.boundary = config.boundary;
+        self.grid_seed = Some(config.seed);
+        // Steps draw from the same seed, so the run itself replays too
+        self.seed = config.seed;
+        self.seed_input = config.seed.to_string();
+        self.reset_history();
+        self.annotations.clear();
+        self.selected_annotation_cell = None;
+        self.grid_width_input = config.width.to_string();
+        self.grid_height_input = config.height.to_string();
+        self.is_simulating = false;
+        self.simulation_timer = None;
+        self.fit_view();
+    }
+
+    /// Replaces the model and grid with a previously saved session.
+    fn apply_session(&mut self, session: Session) {
+        self.discard_rule_form();
+        self.state_name_edit = None;
+        self.model_revision += 1;
+        self.states = session.states;
+        self.rules = session.rules;
+        self.block_rules = session.block_rules;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.drop_invalid_rules();
+        self.grid = session.grid;
+        self.grid_seed = None;
+        self.reset_history();
+        self.background_state_id = session.background_state_id;
+        self.no_match_policy = session.no_match_policy;
+        self.rule_eval_mode = session.rule_eval_mode;
+        self.annotations = session.annotations.into_iter().collect();
+        self.selected_annotation_cell = None;
+        self.ensure_background_state();
+        self.grid_width_input = self.grid.width.to_string();
+        self.grid_height_input = self.grid.height.to_string();
+        match session.view {
+            Some(view) => self.apply_view_state(view),
+            None => self.fit_view(),
+        }
+        if let Some(tab) = session.active_tab {
+            self.active_tab = tab;
+        }
+    }
+
+    /// Current zoom and pan, for saving alongside the grid.
+    pub fn view_state(&self) -> ViewState {
+        let offset = self.offset.get();
+        ViewState {
+            zoom: self.zoom.get(),
+            offset: (offset.x, offset.y),
+        }
+    }
+
+    pub fn apply_view_state(&self, view: ViewState) {
+        self.zoom.set(view.zoom.clamp(0.1, 10.0));
+        self.offset.set(Point::new(view.offset.0, view.offset.1));
+        self.grid_cache.clear();
+    }
+
+    fn autosave_interval_secs(&self) -> u64 {
+        self.autosave_interval_input
+            .trim()
+            .parse::<u64>()
+            .unwrap_or(DEFAULT_AUTOSAVE_INTERVAL_SECS)
+            .max(MIN_AUTOSAVE_INTERVAL_SECS)
+    }
+
+    /// States and rules in the text format used by Export/Import Rules.
+    fn rules_as_text(&self) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        if !self.model_header.is_empty() {
+            for comment in &self.model_header {
+                if comment.is_empty() {
+                    writeln!(out, "#").ok();
+                } else {
+                    writeln!(out, "# {}", comment).ok();
                 }
             }
+            writeln!(out).ok();
         }
+        writeln!(out, "WIDTH {} HEIGHT {}", self.grid.width, self.grid.height).ok();
 
-        let threshold = 10_000;
+        writeln!(out, "STATE {{").ok();
+        for state in &self.states {
+            let r = (state.color.r * 255.0).round() as u8;
+            let g = (state.color.g * 255.0).round() as u8;
+            let b = (state.color.b * 255.0).round() as u8;
+            let w = state.weight;
+            writeln!(
+                out,
+                "    {}({}, {}, {}, {}, {})",
+                state.name, state.id, r, g, b, w
+            )
+            .ok();
+        }
+        writeln!(out, "}}\n").ok();
+
+        writeln!(out, "RULES {{").ok();
+        for rule in &self.rules {
+            let conditions = rule.conditions_as_string();
+            let next = if rule.next_distribution.is_empty() {
+                format!("'{}'", rule.next_state_name)
+            } else {
+                rule.next_distribution
+                    .iter()
+                    .zip(&rule.next_distribution_names)
+                    .map(|(&(_, p), name)| format!("'{}' ({})", name, format_export_probability(p)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            writeln!(
+                out,
+                "    IF current is '{}'{} AND {} THEN next is {} WITH PROB {}{}{}",
+                rule.current_state_name,
+                rule.prev_as_string(),
+                conditions,
+                next,
+                format_export_probability(rule.probability),
+                rule.region_as_string(),
+                rule.global_as_string()
+            )
+            .ok();
+        }
+        writeln!(out, "}}").ok();
+        out
+    }
 
-        if grid_size >= threshold {
-            next_grid_flat
-                .par_iter_mut()
-                .enumerate()
-                .for_each(|(idx, cell)| {
-                    let current_cell_state_id = current_grid_flat[idx];
-                    let mut new_state_id = current_cell_state_id;
+    /// Loads states and rules from the text format, honoring the import mode.
+    /// Rule lines that fail to parse are skipped and listed in `import_errors`.
+    fn import_rules_text(&mut self, content: &str) {
+        let merge = self.import_mode == ImportMode::Merge;
+        self.import_errors.clear();
 
-                    let mut rng = rand::rng();
+        // Parsed aside, so text that turns out not to be a model leaves the current one
+        let mut states = if merge {
+            self.states.clone()
+        } else {
+            Vec::new()
+        };
+        let mut rules = Vec::new();
+        let mut parsed_states = 0; // Imported, or matched to an existing one by a merge
+
+        let mut grid_width = self.grid.width;
+        let mut grid_height = self.grid.height;
+
+        let mut in_states = false;
+        let mut in_rules = false;
+        let mut in_header = true;
+        let mut header = Vec::new();
 
-                    for rule in &self.rules {
-                        if rule.current_state_id != current_cell_state_id {
+        for (line_idx, line) in content.lines().enumerate() {
+            // Comments before anything else form the header kept for export
+            if in_header {
+                match line.trim().strip_prefix('#') {
+                    Some(comment) => {
+                        header.push(comment.trim().to_string());
+                        continue;
+                    }
+                    None if line.trim().is_empty() => continue,
+                    None => in_header = false,
+                }
+            }
+
+            let line = strip_comment(line);
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with("WIDTH") {
+                // A merge keeps the current grid, so its size is left alone
+                if merge {
+                    continue;
+                }
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if parts.len() >= 4 {
+                    grid_width = parts[1].parse::<usize>().unwrap_or(50);
+                    grid_height = parts[3].parse::<usize>().unwrap_or(50);
+                }
+            } else if line.starts_with("STATE") && line.contains('{') {
+                in_states = true;
+                in_rules = false;
+            } else if line.starts_with("RULES") && line.contains('{') {
+                in_rules = true;
+                in_states = false;
+            } else if line == "}" {
+                in_states = false;
+                in_rules = false;
+            } else if in_states {
+                // Parse de estado: nome([id,] r, g, b[, weight])
+                if let Some(start) = line.find('(') {
+                    if let Some(end) = line.find(')') {
+                        let name = line[..start].trim().trim_end_matches(',').to_string();
+                        let nums: Vec<u8> = line[start + 1..end]
+                            .split(',')
+                            .map(|v| v.trim().parse().unwrap_or(0))
+                            .collect();
+
+                        let (explicit_id, r, g, b, weight) = match nums[..] {
+                            [id, r, g, b, w] => (Some(id), r, g, b, w),
+                            [r, g, b, w] => (None, r, g, b, w),
+                            [r, g, b] => (None, r, g, b, 1),
+                            _ => (None, 0, 0, 0, 1),
+                        };
+
+                        if name == NOT_SELF_NAME {
+                            self.import_errors.push(format!(
+                                "Line {}: skipping state '{}': the name is reserved",
+                                line_idx + 1,
+                                name
+                            ));
                             continue;
                         }
-
-                        if rng.random::<f32>() > rule.probability {
+                        // Merged states are deduplicated by name; rules
+                        // resolve names, so they pick up the existing id
+                        if merge && states.iter().any(|s| s.name == name) {
+                            parsed_states += 1;
                             continue;
                         }
 
-                        let final_result = if rule.neighbor_state_id_to_count.is_empty() {
-                            true
-                        } else {
-                            let mut res = true;
-                            for i in 0..rule.neighbor_state_id_to_count.len() {
-                                let neighbor_state = rule.neighbor_state_id_to_count[i] as usize;
-                                let op = rule.operator[i];
-                                let thr = rule.neighbor_count_threshold[i];
-
-                                let neighbor_count = neighbor_counts[neighbor_state][idx];
-                                let condition = op.evaluate(neighbor_count, thr);
-
-                                if i == 0 {
-                                    res = condition;
-                                } else {
-                                    match rule.combiner[i - 1] {
-                                        ConditionCombiner::And => res &= condition,
-                                        ConditionCombiner::Or => res |= condition,
-                                        ConditionCombiner::Xor => res ^= condition,
-                                    }
-                                }
-                            }
-                            res
+                        let color = Color::from_rgb8(r, g, b);
+                        // Keep the file's id so saved grids line up; fall
+                        // back to a free id if it's missing or already taken
+                        let id = explicit_id
+                            .filter(|id| *id != NOT_SELF_ID && !states.iter().any(|s| s.id == *id))
+                            .or_else(|| free_state_id(&states));
+                        let Some(id) = id else {
+                            self.import_errors.push(format!(
+                                "Line {}: skipping state '{}': no free state id left",
+                                line_idx + 1,
+                                name
+                            ));
+                            continue;
                         };
 
-                        if final_result {
-                            new_state_id = rule.next_state_id;
-                            break;
-                        }
+                        states.push(CAState {
+                            id,
+                            name,
+                            color,
+                            weight,
+                        });
+                        parsed_states += 1;
                     }
+                }
+            } else if in_rules {
+                match parse_rule(line, &states) {
+                    Ok(rule) => rules.push(rule),
+                    Err(e) => self
+                        .import_errors
+                        .push(format!("Line {}: {}", line_idx + 1, e)),
+                }
+            }
+        }
 
-                    *cell = new_state_id;
-                });
+        if parsed_states == 0 {
+            self.import_errors
+                .push("No usable states found: expected a STATE { ... } block".into());
+            return;
+        }
+
+        // Replacing also rebuilds the grid, so Undo has to bring that back too
+        self.record_model_edit(!merge);
+        if merge {
+            self.rules.extend(rules);
         } else {
-            for idx in 0..grid_size {
-                let current_cell_state_id = current_grid_flat[idx];
-                let mut new_state_id = current_cell_state_id;
+            self.discard_rule_form();
+            self.rules = rules;
+        }
+        self.states = states;
+        self.ensure_background_state();
+        if !merge {
+            self.model_header = header;
+            // Rebuild the cells rather than just the size, or they would no longer
+            // match the new dimensions (nor, possibly, the new states)
+            self.regenerate_grid(grid_width, grid_height);
+            self.grid_width_input = grid_width.to_string();
+            self.grid_height_input = grid_height.to_string();
+            self.transition_flash.clear();
+        }
 
-                let mut rng = rand::rng();
+        self.grid_cache.clear();
+    }
 
-                for rule in &self.rules {
-                    if rule.current_state_id != current_cell_state_id {
-                        continue;
-                    }
+    /// Form conditions as numbers with their combiners and groups, e.g. "1 OR (2 AND 3)".
+    pub fn condition_preview(&self) -> String {
+        let count = self.rule_form_conditions.len();
+        let mut parts: Vec<String> = Vec::new();
+        for (i, cond) in self.rule_form_conditions.iter().enumerate() {
+            let opens = self.rule_form_groups.iter().filter(|g| g.0 == i).count();
+            let closes = self.rule_form_groups.iter().filter(|g| g.1 == i).count();
+            parts.push(format!(
+                "{}{}{}",
+                "(".repeat(opens),
+                i + 1,
+                ")".repeat(closes)
+            ));
+            if i + 1 < count {
+                parts.push(
+                    cond.combiner
+                        .clone()
+                        .unwrap_or(ConditionCombiner::And)
+                        .to_string(),
+                );
+            }
+        }
+        parts.join(" ")
+    }
 
-                    if rng.random::<f32>() > rule.probability {
-                        continue;
-                    }
+    /// Unselects a removed state wherever the rule form still points at it.
+    /// Replaces the copies of `state` held by pickers so they show its current name and color.
+    fn sync_state_in_forms(&mut self, state: &CAState) {
+        let sync = |slot: &mut Option<CAState>| {
+            if let Some(held) = slot.as_mut().filter(|s| s.id == state.id) {
+                *held = state.clone();
+            }
+        };
+        sync(&mut self.rule_form_current_state);
+        sync(&mut self.rule_form_next_state);
+        sync(&mut self.rule_form_global_state);
+        sync(&mut self.rule_form_prev_state);
+        sync(&mut self.run_until_state);
+        for cond in &mut self.rule_form_conditions {
+            sync(&mut cond.neighbor_state);
+        }
+        for outcome in &mut self.rule_form_outcomes {
+            sync(&mut outcome.state);
+        }
+    }
 
-                    let final_result = if rule.neighbor_state_id_to_count.is_empty() {
-                        true
-                    } else {
-                        let mut res = true;
-                        for i in 0..rule.neighbor_state_id_to_count.len() {
-                            let neighbor_state = rule.neighbor_state_id_to_count[i] as usize;
-                            let op = rule.operator[i];
-                            let thr = rule.neighbor_count_threshold[i];
-
-                            let neighbor_count = neighbor_counts[neighbor_state][idx];
-                            let condition = op.evaluate(neighbor_count, thr);
-
-                            if i == 0 {
-                                res = condition;
-                            } else {
-                                match rule.combiner[i - 1] {
-                                    ConditionCombiner::And => res &= condition,
-                                    ConditionCombiner::Or => res |= condition,
-                                    ConditionCombiner::Xor => res ^= condition,
-                                }
-                            }
-                        }
-                        res
-                    };
+    fn forget_state_in_rule_form(&mut self, state_id: u8) {
+        let is_removed = |state: &Option<CAState>| state.as_ref().is_some_and(|s| s.id == state_id);
+        if is_removed(&self.rule_form_current_state) {
+            self.rule_form_current_state = None;
+        }
+        if is_removed(&self.rule_form_next_state) {
+            self.rule_form_next_state = None;
+        }
+        if is_removed(&self.rule_form_global_state) {
+            self.rule_form_global_state = None;
+        }
+        if is_removed(&self.rule_form_prev_state) {
+            self.rule_form_prev_state = None;
+        }
+        for cond in &mut self.rule_form_conditions {
+            if is_removed(&cond.neighbor_state) {
+                cond.neighbor_state = None;
+            }
+        }
+        for outcome in &mut self.rule_form_outcomes {
+            if is_removed(&outcome.state) {
+                outcome.state = None;
+            }
+        }
+    }
+
+    /// Whether the rule form holds anything the user would lose by clearing it.
+    fn rule_form_in_progress(&self) -> bool {
+        self.rule_form_current_state.is_some()
+            || self.rule_form_next_state.is_some()
+            || !self.rule_form_conditions.is_empty()
+            || !self.rule_form_outcomes.is_empty()
+            || self.rule_form_region_enabled
+            || self.rule_form_global_enabled
+            || self.rule_form_prev_enabled
+    }
+
+    /// Resets every rule form field to how a fresh form starts.
+    fn clear_rule_form(&mut self) {
+        self.rule_form_current_state = None;
+        self.rule_form_next_state = None;
+        self.rule_form_conditions.clear();
+        self.rule_form_groups.clear();
+        self.rule_form_group_inputs = Default::default();
+        self.rule_form_region_enabled = false;
+        self.rule_form_global_enabled = false;
+        self.rule_form_global_state = None;
+        self.rule_form_global_operator = None;
+        self.rule_form_global_threshold.clear();
+        self.rule_form_prev_enabled = false;
+        self.rule_form_prev_state = None;
+        self.rule_form_region = Default::default();
+        self.rule_form_outcomes.clear();
+        self.rule_form_probability = self.format_probability(1.0);
+        self.editing_rule_index = None;
+        self.clear_rule_form_errors();
+    }
+
+    /// Fills the rule form from an existing rule, which Add Rule will then replace.
+    fn load_rule_into_form(&mut self, idx: usize) {
+        let Some(rule) = self.rules.get(idx).cloned() else {
+            return;
+        };
+        self.clear_rule_form();
+        let state = |id: u8| match id {
+            NOT_SELF_ID => Some(CAState::not_self()),
+            _ => self.states.iter().find(|s| s.id == id).cloned(),
+        };
+
+        self.rule_form_current_state = state(rule.current_state_id);
+        if rule.next_distribution.is_empty() {
+            self.rule_form_next_state = state(rule.next_state_id);
+        }
+        self.rule_form_outcomes = rule
+            .next_distribution
+            .iter()
+            .map(|&(id, p)| OutcomeForm {
+                state: state(id),
+                probability: self.format_probability(p),
+                error: None,
+            })
+            .collect();
+        // Combiner i joins condition i to the next one, as in the form
+        self.rule_form_conditions = (0..rule.neighbor_state_id_to_count.len())
+            .map(|i| ConditionForm {
+                neighbor_state: state(rule.neighbor_state_id_to_count[i]),
+                operator: rule.operator.get(i).copied(),
+                threshold: rule
+                    .neighbor_count_threshold
+                    .get(i)
+                    .map_or_else(String::new, |t| t.to_string()),
+                combiner: rule.combiner.get(i).cloned(),
+                directions: rule.direction_mask(i),
+                errors: Vec::new(),
+            })
+            .collect();
+        self.rule_form_groups = rule.groups.clone();
+        self.rule_form_probability = self.format_probability(rule.probability);
+
+        if let Some(region) = rule.region {
+            self.rule_form_region_enabled = true;
+            self.rule_form_region = [
+                region.row_min.to_string(),
+                region.col_min.to_string(),
+                region.row_max.to_string(),
+                region.col_max.to_string(),
+            ];
+        }
+        if let Some(global) = &rule.global_condition {
+            self.rule_form_global_enabled = true;
+            self.rule_form_global_state = state(global.state_id);
+            self.rule_form_global_operator = Some(global.operator);
+            self.rule_form_global_threshold = global.threshold.to_string();
+        }
+        if let Some(prev_id) = rule.prev_state_id {
+            self.rule_form_prev_enabled = true;
+            self.rule_form_prev_state = state(prev_id);
+        }
+        self.editing_rule_index = Some(idx);
+    }
+
+    /// Keeps the rule being edited tracked when two rules trade places.
+    fn follow_rule_swap(&mut self, a: usize, b: usize) {
+        self.editing_rule_index = match self.editing_rule_index {
+            Some(i) if i == a => Some(b),
+            Some(i) if i == b => Some(a),
+            other => other,
+        };
+    }
+
+    /// Clears the rule form when the model it refers to is replaced, telling the user.
+    fn discard_rule_form(&mut self) {
+        if self.rule_form_in_progress() {
+            self.form_warning = Some(
+                "The partially entered rule was cleared because the model was replaced.".into(),
+            );
+        }
+        self.clear_rule_form();
+    }
+
+    fn clear_rule_form_errors(&mut self) {
+        self.rule_form_error = None;
+        self.rule_form_current_error = None;
+        self.rule_form_next_error = None;
+        self.rule_form_probability_error = None;
+        self.rule_form_region_error = None;
+        self.rule_form_outcomes_error = None;
+        self.rule_form_group_error = None;
+        self.rule_form_global_error = None;
+        self.rule_form_prev_error = None;
+        for cond in &mut self.rule_form_conditions {
+            cond.errors.clear();
+        }
+        for outcome in &mut self.rule_form_outcomes {
+            outcome.error = None;
+        }
+    }
+
+    /// Problems that make the model misbehave silently: dangling state ids,
+    /// an empty ruleset, or a missing background state.
+    pub fn validate_model(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        let state_exists = |id: u8| self.states.iter().any(|s| s.id == id);
+
+        if self.states.is_empty() {
+            issues.push("No states are defined.".to_string());
+        }
+        if self.rules.is_empty() {
+            issues.push("No rules are defined; the grid will never change.".to_string());
+        }
+        if !self.states.is_empty() && !state_exists(self.background_state_id) {
+            issues.push(format!(
+                "Background state id {} does not exist.",
+                self.background_state_id
+            ));
+        }
 
-                    if final_result {
-                        new_state_id = rule.next_state_id;
-                        break;
+        for (idx, rule) in self.rules.iter().enumerate() {
+            let rule_no = idx + 1;
+            if !state_exists(rule.current_state_id) {
+                issues.push(format!(
+                    "Rule {}: current state '{}' (id {}) does not exist.",
+                    rule_no, rule.current_state_name, rule.current_state_id
+                ));
+            }
+            for &(id, _) in &rule.next_distribution {
+                if !state_exists(id) {
+                    issues.push(format!(
+                        "Rule {}: outcome state id {} does not exist.",
+                        rule_no, id
+                    ));
+                }
+            }
+            if !state_exists(rule.next_state_id) {
+                issues.push(format!(
+                    "Rule {}: next state '{}' (id {}) does not exist.",
+                    rule_no, rule.next_state_name, rule.next_state_id
+                ));
+            }
+            for (cond_idx, &id) in rule.neighbor_state_id_to_count.iter().enumerate() {
+                if id != NOT_SELF_ID && !state_exists(id) {
+                    issues.push(format!(
+                        "Rule {}: condition {} counts missing state id {}.",
+                        rule_no,
+                        cond_idx + 1,
+                        id
+                    ));
+                }
+            }
+            if let Err(e) = rule.validate() {
+                issues.push(format!("Rule {}: {}.", rule_no, e));
+            }
+        }
+
+        // Cells in a state that was removed render with the red fallback
+        let mut dangling_cells: HashMap<u8, usize> = HashMap::new();
+        for &id in self.grid.cells.iter().flatten() {
+            if !state_exists(id) {
+                *dangling_cells.entry(id).or_insert(0) += 1;
+            }
+        }
+        let mut dangling_cells: Vec<_> = dangling_cells.into_iter().collect();
+        dangling_cells.sort_unstable();
+        for (id, count) in dangling_cells {
+            issues.push(format!(
+                "{} grid cell(s) hold missing state id {}.",
+                count, id
+            ));
+        }
+
+        issues
+    }
+
+    /// Lowest state id not used by any defined state, or `None` once every id below
+    /// `NOT_SELF_ID` is taken.
+    fn next_free_state_id(&self) -> Option<u8> {
+        free_state_id(&self.states)
+    }
+
+    /// Why `name` can't name a state, if it can't. The state being renamed, if any, is
+    /// left out of the duplicate check.
+    fn state_name_error(&self, name: &str, renamed_id: Option<u8>) -> Option<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            Some("The state needs a name.".into())
+        } else if name == NOT_SELF_NAME {
+            Some(format!(
+                "'{}' is reserved for count(!self) conditions.",
+                NOT_SELF_NAME
+            ))
+        } else if self
+            .states
+            .iter()
+            .filter(|s| Some(s.id) != renamed_id)
+            .any(|s| s.name.trim().eq_ignore_ascii_case(name))
+        {
+            // Rules refer to states by name, so two states can't share one
+            Some(format!("A state named '{}' already exists.", name))
+        } else {
+            None
+        }
+    }
+
+    /// Text shown in the state list's name field for `state`.
+    pub fn state_name_input<'a>(&'a self, state: &'a CAState) -> &'a str {
+        match &self.state_name_edit {
+            Some((id, typed)) if *id == state.id => typed,
+            _ => &state.name,
+        }
+    }
+
+    /// Builds a fresh weighted-random grid, keeping the current neighborhood and boundary.
+    fn regenerate_grid(&mut self, width: usize, height: usize) {
+        let boundary = self.grid.boundary;
+        // The same seed gives the same grid, so a run can be restarted exactly
+        let seed = self.seed;
+        self.grid = CAGrid::new_with_rng(
+            width,
+            height,
+            self.states.clone(),
+            self.grid.neighborhood,
+            self.background_state_id,
+            &mut StdRng::seed_from_u64(seed),
+        );
+        self.grid_seed = Some(seed);
+        self.grid.boundary = boundary;
+        self.reset_history();
+        self.annotations
+            .retain(|&(r, c), _| r < height && c < width);
+    }
+
+    /// Cells that differ from the comparison grid, or `None` if there is no
+    /// comparison grid or its size doesn't match.
+    pub fn comparison_diff_count(&self) -> Option<usize> {
+        let other = self.comparison_grid.as_ref()?;
+        if other.width != self.grid.width || other.height != self.grid.height {
+            return None;
+        }
+        Some(
+            self.grid
+                .cells
+                .iter()
+                .flatten()
+                .zip(other.cells.iter().flatten())
+                .filter(|(a, b)| a != b)
+                .count(),
+        )
+    }
+
+    /// Forest fire parameters from the example form; probabilities are plain 0.0-1.0.
+    fn forest_fire_params(&self) -> Result<ForestFireParams, String> {
+        let parse = |label: &str, raw: &str| {
+            raw.trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|p| (0.0..=1.0).contains(p))
+                .ok_or_else(|| format!("{} must be between 0.0 and 1.0", label))
+        };
+        Ok(ForestFireParams {
+            burn_out: parse("Burn-out probability", &self.forest_burn_out_input)?,
+            spread: parse("Spread probability", &self.forest_spread_input)?,
+            growth: parse("Growth probability", &self.forest_growth_input)?,
+            lightning: parse("Lightning probability", &self.forest_lightning_input)?,
+        })
+    }
+
+    /// Ring neighborhood described by the inner/outer inputs, if they are valid.
+    pub fn ring_from_inputs(&self) -> Option<Neighborhood> {
+        let inner = self.ring_inner_input.trim().parse::<u8>().ok()?;
+        let outer = self.ring_outer_input.trim().parse::<u8>().ok()?;
+        let ring = Neighborhood::Ring { inner, outer };
+        ring.validate().is_ok().then_some(ring)
+    }
+
+    /// Moore neighborhood with the radius typed in, if it is valid.
+    pub fn moore_radius_from_input(&self) -> Option<Neighborhood> {
+        let radius = self.moore_radius_input.trim().parse::<u8>().ok()?;
+        let moore = Neighborhood::MooreRadius(radius);
+        moore.validate().is_ok().then_some(moore)
+    }
+
+    // Editing the bounds while a ring is active updates it in place
+    fn apply_ring_inputs(&mut self) {
+        if let (Neighborhood::Ring { .. }, Some(ring)) =
+            (self.grid.neighborhood, self.ring_from_inputs())
+        {
+            self.grid.neighborhood = ring;
+            self.model_revision += 1;
+        }
+    }
+
+    /// Checks the sweep inputs and starts running the Forest Fire preset once per value.
+    fn start_sweep(&mut self) {
+        let base = match self.forest_fire_params() {
+            Ok(params) => params,
+            Err(e) => {
+                self.sweep_error = Some(e);
+                return;
+            }
+        };
+        let [from, to, points, steps] = &self.sweep_inputs;
+        let probability = |raw: &str| {
+            raw.trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|p| (0.0..=1.0).contains(p))
+        };
+        let (Some(from), Some(to)) = (probability(from), probability(to)) else {
+            self.sweep_error = Some("Sweep range must be between 0.0 and 1.0".into());
+            return;
+        };
+        let Some(points) = points
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=MAX_SWEEP_VALUES).contains(n))
+        else {
+            self.sweep_error = Some(format!(
+                "Number of values must be between 1 and {}",
+                MAX_SWEEP_VALUES
+            ));
+            return;
+        };
+        let Some(steps) = steps.trim().parse::<usize>().ok().filter(|&n| n > 0) else {
+            self.sweep_error = Some("Steps per run must be a positive integer".into());
+            return;
+        };
+
+        self.is_simulating = false;
+        self.simulation_timer = None;
+        self.sweep_error = None;
+        self.sweep = Some(SweepRun {
+            param: self.sweep_param,
+            base,
+            values: sweep_values(from, to, points),
+            steps,
+            seed: self.seed,
+            state_names: forest_fire_states().into_iter().map(|s| s.name).collect(),
+            results: Vec::new(),
+            saved_states: self.states.clone(),
+            saved_rules: self.rules.clone(),
+            saved_grid: self.grid.clone(),
+            saved_background_state_id: self.background_state_id,
+        });
+        self.sweep_running = true;
+    }
+
+    /// Runs the sweep's next value: the preset with that value, from the seeded grid,
+    /// for the configured number of steps, recording the final population.
+    fn run_sweep_step(&mut self) {
+        let Some(run) = self.sweep.as_ref().filter(|_| self.sweep_running) else {
+            return;
+        };
+        let Some((value, params)) = run.next_params() else {
+            self.finish_sweep();
+            return;
+        };
+        let (seed, steps) = (run.seed, run.steps);
+
+        self.states = forest_fire_states();
+        self.rules = forest_fire_rules(&self.states[0], &self.states[1], &self.states[2], &params);
+        self.background_state_id = self.states[0].id;
+        let boundary = self.grid.boundary;
+        self.grid = CAGrid::new_with_rng(
+            self.grid.width,
+            self.grid.height,
+            self.states.clone(),
+            self.grid.neighborhood,
+            self.background_state_id,
+            &mut StdRng::seed_from_u64(seed),
+        );
+        self.grid.boundary = boundary;
+
+        let show_flashes = self.show_transition_flashes;
+        self.show_transition_flashes = false;
+        // Each run replays the same random draws, so only the parameter differs
+        for generation in 0..steps as u64 {
+            self.generation = generation;
+            self.step_simulation_logic();
+        }
+        self.show_transition_flashes = show_flashes;
+
+        let histogram = self.grid.population();
+        let population: Vec<u32> = self
+            .states
+            .iter()
+            .map(|s| histogram[s.id as usize])
+            .collect();
+        if let Some(run) = self.sweep.as_mut() {
+            run.results.push(SweepResult {
+                value,
+                entropy: shannon_entropy(&population),
+                population,
+            });
+            if run.is_finished() {
+                self.finish_sweep();
+            }
+        }
+    }
+
+    /// Stops the sweep (finished or cancelled) and puts the user's model and grid back.
+    fn finish_sweep(&mut self) {
+        if !self.sweep_running {
+            return;
+        }
+        if let Some(run) = self.sweep.as_mut() {
+            self.states = std::mem::take(&mut run.saved_states);
+            self.rules = std::mem::take(&mut run.saved_rules);
+            self.grid = run.saved_grid.clone();
+            self.background_state_id = run.saved_background_state_id;
+        }
+        self.sweep_running = false;
+        self.reset_history();
+        self.transition_flash.clear();
+        self.grid_cache.clear();
+    }
+
+    /// Forgets everything tied to the previous grid's past: generation count, cell ages and
+    /// Step Back history.
+    fn reset_history(&mut self) {
+        self.generation = 0;
+        self.stable_report = None;
+        self.population_history.clear();
+        self.cell_ages.clear();
+        self.step_history.clear();
+    }
+
+    /// Steps the simulation once as the user sees it (timer or Next Step).
+    fn advance_generation(&mut self) {
+        self.push_step_history();
+        self.step_simulation_logic();
+        self.end_generation();
+    }
+
+    /// Keeps the grid about to be stepped, for Step Back.
+    fn push_step_history(&mut self) {
+        if self.step_history.len() >= MAX_STEP_HISTORY {
+            self.step_history.pop_front();
+        }
+        self.step_history.push_back(self.grid.cells.clone());
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    fn model_snapshot(&self, with_grid: bool) -> ModelSnapshot {
+        ModelSnapshot {
+            states: self.states.clone(),
+            rules: self.rules.clone(),
+            background_state_id: self.background_state_id,
+            grid: with_grid.then(|| self.grid.clone()),
+        }
+    }
+
+    /// Saves the model for Undo right before an edit, which makes any redo obsolete.
+    fn record_model_edit(&mut self, with_grid: bool) {
+        if self.undo_stack.len() >= MAX_UNDO_DEPTH {
+            self.undo_stack.pop_front();
+        }
+        let snapshot = self.model_snapshot(with_grid);
+        self.undo_stack.push_back(snapshot);
+        self.redo_stack.clear();
+        self.model_revision += 1;
+        self.state_name_edit = None;
+    }
+
+    /// Puts back an undone or redone model, unselecting states it doesn't have.
+    fn restore_model(&mut self, snapshot: ModelSnapshot) {
+        let removed: Vec<u8> = self
+            .states
+            .iter()
+            .map(|s| s.id)
+            .filter(|&id| !snapshot.states.iter().any(|s| s.id == id))
+            .collect();
+        self.states = snapshot.states;
+        self.rules = snapshot.rules;
+        self.background_state_id = snapshot.background_state_id;
+        self.model_revision += 1;
+        self.state_name_edit = None;
+        if let Some(grid) = snapshot.grid {
+            self.grid = grid;
+            self.grid_width_input = self.grid.width.to_string();
+            self.grid_height_input = self.grid.height.to_string();
+            self.reset_history();
+        }
+
+        // Rule indices may have shifted under the form
+        self.editing_rule_index = None;
+        for id in removed {
+            self.forget_state_in_rule_form(id);
+        }
+        for state in self.states.clone() {
+            self.sync_state_in_forms(&state);
+        }
+        self.ensure_background_state();
+        self.grid_cache.clear();
+    }
+
+    /// Counts a generation the user has seen stepped, after `push_step_history`.
+    fn end_generation(&mut self) {
+        self.generation += 1;
+        if self.gif_recording {
+            self.record_gif_frame();
+        }
+        if self.population_history.len() >= MAX_POPULATION_HISTORY {
+            self.population_history.pop_front();
+        }
+        let num_ids = self
+            .states
+            .iter()
+            .map(|s| s.id)
+            .max()
+            .map_or(0, |id| id.saturating_add(1));
+        self.population_history
+            .push_back(self.grid.state_counts(num_ids));
+
+        // 1 when the step changed nothing, 2 when it went back to the grid before
+        let period = self
+            .step_history
+            .iter()
+            .rev()
+            .take(2)
+            .position(|past| *past == self.grid.cells)
+            .map(|i| i + 1);
+        match period {
+            // Already reported, with the generation it first happened at
+            Some(_) if self.stable_report.is_some() => {}
+            Some(1) => {
+                self.stable_report =
+                    Some(format!("Stabilized at generation {}", self.generation - 1))
+            }
+            Some(period) => {
+                self.stable_report = Some(format!(
+                    "Oscillating with period {} since generation {}",
+                    period,
+                    self.generation - period as u64
+                ))
+            }
+            None => self.stable_report = None,
+        }
+        if period.is_some() && self.stop_on_stable {
+            self.is_simulating = false;
+            self.simulation_timer = None;
+        }
+    }
+
+    /// Sets the given cells to `state_id`, skipping any outside the grid.
+    fn paint_cells(&mut self, cells: impl IntoIterator<Item = (usize, usize)>, state_id: u8) {
+        for (r, c) in cells {
+            let Some(cell) = self.grid.cells.get_mut(r).and_then(|row| row.get_mut(c)) else {
+                continue;
+            };
+            let changed = *cell != state_id;
+            *cell = state_id;
+            if let Some(age) = self
+                .cell_ages
+                .get_mut(r * self.grid.width + c)
+                .filter(|_| changed)
+            {
+                *age = 0;
+            }
+        }
+        self.grid_cache.clear();
+    }
+
+    /// Stamps an imported pattern where `pattern_placement` says, live cells in the
+    /// paint state over the background state, like the seed patterns.
+    fn stamp_pattern(&mut self, pattern: &Pattern) {
+        let origin = self
+            .pattern_placement
+            .origin(pattern, self.grid.width, self.grid.height);
+        let stamped = self.grid.stamp_pattern(
+            pattern,
+            origin,
+            self.selected_paint_state_id,
+            self.background_state_id,
+        );
+        match stamped {
+            Ok(()) => {
+                self.grid_seed = None;
+                self.reset_history();
+                self.grid_cache.clear();
+            }
+            Err(e) => self.show_error("Pattern too large", e),
+        }
+    }
+
+    /// Logs `message` and shows it in a dialog, for failures of something the user just asked for.
+    fn show_error(&mut self, title: &str, message: String) {
+        eprintln!("{}", message);
+        self.active_modal = Some(Modal::Info {
+            title: title.into(),
+            message,
+        });
+    }
+
+    /// The condition described by the Run Until form, or `None` while it is incomplete.
+    pub fn run_until_condition(&self) -> Option<StopCondition> {
+        match self.run_until_kind {
+            StopConditionKind::Population => {
+                let state = self.run_until_state.as_ref()?;
+                Some(StopCondition::Population(GlobalCondition {
+                    state_id: state.id,
+                    state_name: state.name.clone(),
+                    operator: self.run_until_operator?,
+                    threshold: self.run_until_value.trim().parse().ok()?,
+                }))
+            }
+            StopConditionKind::Stable => Some(StopCondition::Stable),
+            StopConditionKind::Cycle => Some(StopCondition::Cycle),
+            StopConditionKind::Generation => self
+                .run_until_value
+                .trim()
+                .parse()
+                .ok()
+                .map(StopCondition::Generation),
+        }
+    }
+
+    /// Steps until `condition` holds or the safety bound is hit, and says which happened.
+    fn run_until(&mut self, condition: &StopCondition) -> String {
+        // Generation at which each grid checksum was first seen, for cycle detection
+        let mut seen: HashMap<u64, u64> = HashMap::new();
+        seen.insert(self.grid.checksum(), self.generation);
+        let mut steps = 0;
+
+        let reason = loop {
+            match condition {
+                StopCondition::Population(c) if c.holds(&self.grid.population()) => {
+                    break format!("{} holds", c);
+                }
+                StopCondition::Generation(target) if self.generation >= *target => {
+                    break format!("reached generation {}", target);
+                }
+                _ => {}
+            }
+            if steps >= MAX_RUN_UNTIL_STEPS {
+                break format!(
+                    "safety limit of {} steps reached before the condition held",
+                    MAX_RUN_UNTIL_STEPS
+                );
+            }
+
+            let before = self.grid.checksum();
+            self.advance_generation();
+            steps += 1;
+            let after = self.grid.checksum();
+
+            match condition {
+                StopCondition::Stable if after == before => {
+                    break "the grid did not change in the last step".to_string();
+                }
+                StopCondition::Cycle => {
+                    if let Some(first) = seen.insert(after, self.generation) {
+                        break format!(
+                            "the grid repeats generation {} (period {})",
+                            first,
+                            self.generation - first
+                        );
                     }
                 }
+                _ => {}
+            }
+        };
+
+        format!(
+            "Stopped after {} step(s), at generation {}: {}",
+            steps, self.generation, reason
+        )
+    }
+
+    /// Restores the cells from before the last step, if any are still kept. Cell ages are
+    /// not recorded, so they start over.
+    fn step_back(&mut self) {
+        let Some(cells) = self.step_history.pop_back() else {
+            return;
+        };
+        self.grid.cells = cells;
+        self.generation = self.generation.saturating_sub(1);
+        self.stable_report = None;
+        self.population_history.pop_back();
+        self.cell_ages.clear();
+        self.transition_flash.clear();
+        self.grid_cache.clear();
+    }
+
+    fn record_gif_frame(&mut self) {
+        if self.gif_frames.len() >= MAX_GIF_FRAMES {
+            self.gif_frames.remove(0);
+        }
+        self.gif_frames
+            .push(self.grid.cells.iter().flatten().copied().collect());
+    }
+
+    /// Copies what an image export needs so it can be encoded off the UI thread.
+    fn image_export(&self, path: std::path::PathBuf, frames: Vec<Vec<u8>>) -> ImageExport {
+        ImageExport {
+            path,
+            width: self.grid.width,
+            height: self.grid.height,
+            palette: self
+                .states
+                .iter()
+                .map(|s| {
+                    let [r, g, b, _] = s.color.into_rgba8();
+                    (s.id, [r, g, b])
+                })
+                .collect(),
+            frames,
+            frame_delay_ms: self.simulation_speed_ms,
+            caption: None,
+        }
+    }
+
+    /// Name for the loaded model, taken from the first header line (set by examples and imports).
+    pub fn model_name(&self) -> String {
+        self.model_header
+            .first()
+            .filter(|line| !line.is_empty())
+            .cloned()
+            .unwrap_or_else(|| "Custom model".into())
+    }
+
+    /// Random grid with the current size and states, reproducible from `sub_seed`.
+    fn seeded_grid(&self, sub_seed: u64) -> CAGrid {
+        let mut grid = CAGrid::new_with_rng(
+            self.grid.width,
+            self.grid.height,
+            self.states.clone(),
+            self.grid.neighborhood,
+            self.background_state_id,
+            &mut StdRng::seed_from_u64(sub_seed),
+        );
+        grid.boundary = self.grid.boundary;
+        grid
+    }
+
+    /// Tries several random grids derived from `seed`, runs each for a few steps
+    /// and keeps the one scoring highest on activity times state entropy.
+    fn find_interesting_seed(&mut self) {
+        let Some(settings) = self.step_settings() else {
+            return;
+        };
+
+        let cell_count = (self.grid.width * self.grid.height).max(1) as f32;
+        let max_entropy = (self.states.len() as f32).log2().max(1.0);
+        let mut sub_seeds = StdRng::seed_from_u64(self.seed);
+        let mut best: Option<(u64, f32)> = None;
+
+        for _ in 0..SEED_SEARCH_CANDIDATES {
+            let sub_seed = sub_seeds.random::<u64>();
+            // Candidates are stepped on their own, leaving the simulator's grid alone,
+            // but with the same settings and block rules as the run they would seed
+            let mut grid = self.seeded_grid(sub_seed);
+            let mut buffers = StepBuffers::default();
+            let mut neighbor_counts = NeighborCounts::default();
+
+            // Activity: average fraction of cells changing per step
+            let mut changed = 0usize;
+            for generation in 0..SEED_SEARCH_STEPS as u64 {
+                compute_step(
+                    &grid,
+                    &self.rules,
+                    &self.block_rules,
+                    &self.states,
+                    StepSettings {
+                        generation,
+                        ..settings
+                    },
+                    &mut neighbor_counts,
+                    &mut buffers,
+                );
+                let next = &buffers.next;
+                changed += buffers
+                    .current
+                    .iter()
+                    .zip(next)
+                    .filter(|(a, b)| a != b)
+                    .count();
+                for (row, next_row) in grid.cells.iter_mut().zip(next.chunks(grid.width.max(1))) {
+                    row.copy_from_slice(next_row);
+                }
+            }
+            let activity = changed as f32 / (cell_count * SEED_SEARCH_STEPS as f32);
+
+            // Entropy of the final state distribution, normalized to 0..1
+            let mut counts: HashMap<u8, usize> = HashMap::new();
+            for &id in grid.cells.iter().flatten() {
+                *counts.entry(id).or_insert(0) += 1;
+            }
+            let entropy = counts
+                .values()
+                .map(|&n| {
+                    let p = n as f32 / cell_count;
+                    -p * p.log2()
+                })
+                .sum::<f32>()
+                / max_entropy;
 
-                next_grid_flat[idx] = new_state_id;
+            let score = activity * entropy;
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((sub_seed, score));
             }
         }
 
-        for r in 0..height {
-            for c in 0..width {
-                self.grid.cells[r][c] = next_grid_flat[r * width + c];
+        if let Some((sub_seed, score)) = best {
+            self.grid = self.seeded_grid(sub_seed);
+            self.grid_seed = Some(sub_seed);
+            self.reset_history();
+            self.seed_search_report = Some(format!(
+                "Best of {} from seed {}: sub-seed {} (score {:.3})",
+                SEED_SEARCH_CANDIDATES, self.seed, sub_seed, score
+            ));
+        }
+        self.grid_cache.clear();
+    }
+
+    /// Keeps `background_state_id` pointing at a defined state after the state list changes.
+    fn ensure_background_state(&mut self) {
+        if !self.states.iter().any(|s| s.id == self.background_state_id) {
+            self.background_state_id = self
+                .states
+                .first()
+                .map_or(DEFAULT_BACKGROUND_STATE_ID, |s| s.id);
+        }
+    }
+
+    /// Parses the rule form probability, honoring the percentage mode.
+    /// Returns the internal 0.0-1.0 value, or `None` if out of range.
+    pub fn parse_probability_input(&self) -> Option<f32> {
+        self.parse_probability(&self.rule_form_probability)
+    }
+
+    /// Color of the state being created, from the RGB fields the picker also fills.
+    pub fn new_state_color(&self) -> Result<Color, String> {
+        let channel = |label: &str, raw: &str| {
+            raw.trim()
+                .parse::<u8>()
+                .map_err(|_| format!("{} must be a whole number from 0 to 255.", label))
+        };
+        Ok(Color::from_rgb8(
+            channel("R", &self.new_state_color_r)?,
+            channel("G", &self.new_state_color_g)?,
+            channel("B", &self.new_state_color_b)?,
+        ))
+    }
+
+    /// Why the rule form probability can't be used, shown while it is typed.
+    fn probability_input_error(&self) -> Option<String> {
+        let raw = self.rule_form_probability.trim();
+        if self.parse_probability_input().is_some() {
+            return None;
+        }
+        let range = if self.probability_as_percent {
+            "entre 0 e 100%"
+        } else {
+            "entre 0.0 e 1.0"
+        };
+        Some(if raw.is_empty() {
+            format!("Informe a probabilidade ({})", range)
+        } else {
+            format!("Probabilidade '{}' inválida (use valor {})", raw, range)
+        })
+    }
+
+    /// Parses any probability field of the rule form, honoring the percentage mode.
+    pub fn parse_probability(&self, raw: &str) -> Option<f32> {
+        let raw = raw.trim();
+        let p = if self.probability_as_percent {
+            raw.trim_end_matches('%').trim().parse::<f32>().ok()? / 100.0
+        } else {
+            raw.parse::<f32>().ok()?
+        };
+        (0.0..=1.0).contains(&p).then_some(p)
+    }
+
+    /// Formats an internal 0.0-1.0 probability for display in the current mode.
+    pub fn format_probability(&self, p: f32) -> String {
+        if self.probability_as_percent {
+            format!("{}%", (p * 1000.0).round() / 10.0)
+        } else {
+            p.to_string()
+        }
+    }
+
+    /// Applies one rule evaluation to a single cell, leaving all others unchanged.
+    fn poke_cell(&mut self, row: usize, col: usize) {
+        let current_state_id = self.grid.cells[row][col];
+        let grid = &self.grid;
+        let offsets = grid.neighborhood.offsets();
+        // The draws the next step would make for this cell
+        let mut rng = cell_rng(self.seed, self.generation, row * grid.width + col);
+        let prepared = prepare_rules(
+            &self.rules,
+            &grid.population(),
+            self.force_deterministic,
+            self.rule_eval_mode,
+        );
+        let fired = evaluate_cell(
+            &self.rules,
+            &prepared,
+            (row, col),
+            current_state_id,
+            self.step_history.back().map(|cells| cells[row][col]),
+            |state_id, mask| match state_id {
+                NOT_SELF_ID => {
+                    grid.count_differing_neighbors(&offsets, row, col, current_state_id, mask)
+                }
+                _ => grid.count_neighbors_masked(&offsets, row, col, state_id, mask),
+            },
+            &mut rng,
+        );
+
+        let state_name = |id: u8| {
+            self.states
+                .iter()
+                .find(|s| s.id == id)
+                .map_or_else(|| format!("State {}", id), |s| s.name.clone())
+        };
+
+        self.poke_report = Some(match fired {
+            Some(rule_idx) => {
+                let next_state_id = self.rules[rule_idx].sample_next_state(&mut rng);
+                let report = format!(
+                    "({}, {}): '{}' -> '{}' by rule #{}",
+                    row,
+                    col,
+                    state_name(current_state_id),
+                    state_name(next_state_id),
+                    rule_idx + 1
+                );
+                self.grid.cells[row][col] = next_state_id;
+                report
+            }
+            None => {
+                let next_state_id = self
+                    .no_match_policy
+                    .fallback_state(current_state_id, self.background_state_id);
+                let report = format!(
+                    "({}, {}): no rule fired, {} -> '{}'",
+                    row,
+                    col,
+                    self.no_match_policy,
+                    state_name(next_state_id)
+                );
+                self.grid.cells[row][col] = next_state_id;
+                report
             }
+        });
+    }
+
+    // What a step needs besides the model and grid, or `None` with no states to step
+    fn step_settings(&self) -> Option<StepSettings> {
+        (!self.states.is_empty()).then_some(StepSettings {
+            no_match_policy: self.no_match_policy,
+            background_state_id: self.background_state_id,
+            seed: self.seed,
+            generation: self.generation,
+            deterministic: self.force_deterministic,
+            eval_mode: self.rule_eval_mode,
+        })
+    }
+
+    fn step_simulation_logic(&mut self) {
+        let Some(settings) = self.step_settings() else {
+            return;
+        };
+        // Taken out for the step so they can be borrowed alongside `self`
+        let mut buffers = std::mem::take(&mut self.step_buffers);
+        let mut neighbor_counts = std::mem::take(&mut self.neighbor_counts);
+        compute_step(
+            &self.grid,
+            &self.rules,
+            &self.block_rules,
+            &self.states,
+            settings,
+            &mut neighbor_counts,
+            &mut buffers,
+        );
+        self.finish_step(buffers, neighbor_counts);
+    }
+
+    /// Copies a computed step into the grid, updating the per-cell flashes and ages,
+    /// and keeps its buffers for the next one.
+    fn finish_step(&mut self, buffers: StepBuffers, neighbor_counts: NeighborCounts) {
+        let width = self.grid.width;
+        let grid_size = width * self.grid.height;
+        let (current_grid_flat, next_grid_flat) = (&buffers.current, &buffers.next);
+
+        if self.show_transition_flashes {
+            if self.transition_flash.len() != grid_size {
+                self.transition_flash = vec![0; grid_size];
+            }
+            for (idx, flash) in self.transition_flash.iter_mut().enumerate() {
+                *flash = if next_grid_flat[idx] != current_grid_flat[idx] {
+                    TRANSITION_FLASH_STEPS
+                } else {
+                    flash.saturating_sub(1)
+                };
+            }
+        }
+
+        if self.cell_ages.len() != grid_size {
+            self.cell_ages = vec![0; grid_size];
+        }
+        for (idx, age) in self.cell_ages.iter_mut().enumerate() {
+            *age = if next_grid_flat[idx] == current_grid_flat[idx] {
+                age.saturating_add(1)
+            } else {
+                0
+            };
         }
 
+        for (r, row) in self.grid.cells.iter_mut().enumerate() {
+            row.copy_from_slice(&next_grid_flat[r * width..(r + 1) * width]);
+        }
+        self.step_buffers = buffers;
+        self.neighbor_counts = neighbor_counts;
         self.grid_cache.clear();
     }
+
+    /// Starts computing the next generation on another thread, for the timer. The
+    /// grid and rules are copied, so the UI keeps responding while it runs.
+    fn start_background_step(&mut self) -> Command<Message> {
+        let Some(settings) = self.step_settings() else {
+            return Command::none();
+        };
+        let job = StepJob {
+            grid: self.grid.clone(),
+            rules: self.rules.clone(),
+            block_rules: self.block_rules.clone(),
+            states: self.states.clone(),
+            settings,
+            model_revision: self.model_revision,
+            neighbor_counts: std::mem::take(&mut self.neighbor_counts),
+            buffers: std::mem::take(&mut self.step_buffers),
+        };
+        self.step_in_flight = true;
+        Command::perform(run_step_in_background(job), Message::StepComplete)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Importing a model declaring a bigger grid than the current one must rebuild the
+    // cells at the new size, so the next step doesn't index past the old rows
+    #[test]
+    fn import_larger_grid_then_step() {
+        let (mut simulator, _) = CASimulator::new(());
+        let (width, height) = (simulator.grid.width + 7, simulator.grid.height + 5);
+        let exported = simulator.rules_as_text();
+        let model = exported
+            .lines()
+            .map(|line| {
+                if line.starts_with("WIDTH") {
+                    format!("WIDTH {} HEIGHT {}", width, height)
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        simulator.import_rules_text(&model);
+        simulator.step_simulation_logic();
+
+        assert_eq!(
+            (simulator.grid.width, simulator.grid.height),
+            (width, height)
+        );
+        assert_eq!(simulator.grid.cells.len(), height);
+        assert!(simulator.grid.cells.iter().all(|row| row.len() == width));
+    }
+
+    // Text without a STATE block, e.g. a stray URL pasted by mistake, leaves the model
+    // alone; a real import replaces it and can be undone
+    #[test]
+    fn import_without_states_keeps_model() {
+        let (mut simulator, _) = CASimulator::new(());
+        let (states, rules) = (simulator.states.clone(), simulator.rules.clone());
+        let exported = simulator.rules_as_text();
+
+        simulator.import_rules_text("https://example.com/model");
+        assert_eq!(simulator.states, states);
+        assert_eq!(simulator.rules.len(), rules.len());
+        assert_eq!(simulator.import_errors.len(), 1);
+
+        simulator.import_rules_text(&exported.replace("Alive", "Living"));
+        assert!(simulator.states.iter().any(|s| s.name == "Living"));
+        let _ = simulator.update(Message::Undo);
+        assert_eq!(simulator.states, states);
+    }
+
+    // Renaming a state to another's name (in any case) or to nothing is refused, and
+    // rules keep the last valid name until the edit becomes valid
+    #[test]
+    fn state_rename_is_validated() {
+        let (mut simulator, _) = CASimulator::new(());
+        let alive = simulator
+            .states
+            .iter()
+            .position(|s| s.name == "Alive")
+            .unwrap();
+
+        let _ = simulator.update(Message::StateNameEdited(alive, "dead".into()));
+        assert!(simulator.state_form_error.is_some());
+        assert_eq!(simulator.states[alive].name, "Alive");
+        assert_eq!(simulator.state_name_input(&simulator.states[alive]), "dead");
+
+        let _ = simulator.update(Message::StateNameEdited(alive, " ".into()));
+        assert!(simulator.state_form_error.is_some());
+        assert_eq!(simulator.states[alive].name, "Alive");
+
+        let _ = simulator.update(Message::StateNameEdited(alive, "Live ".into()));
+        assert!(simulator.state_form_error.is_none());
+        assert_eq!(simulator.states[alive].name, "Live");
+        assert!(simulator
+            .rules
+            .iter()
+            .all(|rule| rule.current_state_name != "Alive"));
+    }
+
+    #[test]
+    fn export_probability_round_trips() {
+        assert_eq!(format_export_probability(0.3), "0.3");
+        assert_eq!(format_export_probability(1.0), "1.0");
+        assert_eq!(format_export_probability(0.125), "0.125");
+        for p in [0.0004, 0.00001, 0.12345, 1.0 / 3.0] {
+            assert_eq!(format_export_probability(p).parse::<f32>(), Ok(p));
+        }
+    }
+
+    // A background step computed before a model edit is dropped when it lands, even
+    // though the grid it started from is unchanged
+    #[test]
+    fn step_started_before_model_edit_is_stale() {
+        let (mut simulator, _) = CASimulator::new(());
+        simulator.is_simulating = true;
+        let mut buffers = StepBuffers::default();
+        let mut neighbor_counts = NeighborCounts::default();
+        compute_step(
+            &simulator.grid,
+            &simulator.rules,
+            &simulator.block_rules,
+            &simulator.states,
+            simulator.step_settings().unwrap(),
+            &mut neighbor_counts,
+            &mut buffers,
+        );
+        let result = StepResult {
+            width: simulator.grid.width,
+            model_revision: simulator.model_revision,
+            neighbor_counts,
+            buffers,
+        };
+        let before = simulator.grid.cells.clone();
+
+        let _ = simulator.update(Message::RuleEvalModeSelected(RuleEvalMode::LastMatch));
+        let _ = simulator.update(Message::StepComplete(Ok(result)));
+
+        assert_eq!(simulator.grid.cells, before);
+        assert_eq!(simulator.generation, 0);
+    }
 }
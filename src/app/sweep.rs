@@ -0,0 +1,98 @@
+use crate::app::simulator::ExampleParam;
+use crate::state::ca_grid::CAGrid;
+use crate::state::exemple::ForestFireParams;
+use crate::state::transition_rule::TransitionRule;
+use crate::state::CAState;
+
+// Example parameters that take a single number, and so can be swept over a range
+pub const SWEEPABLE_PARAMS: [ExampleParam; 4] = [
+    ExampleParam::ForestBurnOut,
+    ExampleParam::ForestSpread,
+    ExampleParam::ForestGrowth,
+    ExampleParam::ForestLightning,
+];
+
+// Final statistics of one run of a sweep
+#[derive(Debug, Clone)]
+pub struct SweepResult {
+    pub value: f32,
+    pub population: Vec<u32>, // Cells per state, in the order of `SweepRun::state_names`
+    pub entropy: f32,         // Shannon entropy of the final state distribution, in bits
+}
+
+// A sweep in progress: one run per value, all from the same seeded initial grid
+pub struct SweepRun {
+    pub param: ExampleParam,
+    pub base: ForestFireParams, // Values of the parameters not being swept
+    pub values: Vec<f32>,
+    pub steps: usize,
+    pub seed: u64,
+    pub state_names: Vec<String>,
+    pub results: Vec<SweepResult>,
+    // Model and grid the user had before the sweep, put back when it ends
+    pub saved_states: Vec<CAState>,
+    pub saved_rules: Vec<TransitionRule>,
+    pub saved_grid: CAGrid,
+    pub saved_background_state_id: u8,
+}
+
+impl SweepRun {
+    pub fn is_finished(&self) -> bool {
+        self.results.len() >= self.values.len()
+    }
+
+    // Parameters for the next run, or `None` once every value has been run
+    pub fn next_params(&self) -> Option<(f32, ForestFireParams)> {
+        let value = *self.values.get(self.results.len())?;
+        let mut params = self.base.clone();
+        match self.param {
+            ExampleParam::ForestBurnOut => params.burn_out = value,
+            ExampleParam::ForestSpread => params.spread = value,
+            ExampleParam::ForestGrowth => params.growth = value,
+            ExampleParam::ForestLightning => params.lightning = value,
+            ExampleParam::LifeBirth | ExampleParam::LifeSurvival => return None,
+        }
+        Some((value, params))
+    }
+}
+
+// `points` evenly spaced values from `from` to `to`, both included
+pub fn sweep_values(from: f32, to: f32, points: usize) -> Vec<f32> {
+    match points {
+        0 => Vec::new(),
+        1 => vec![from],
+        _ => (0..points)
+            .map(|i| from + (to - from) * i as f32 / (points - 1) as f32)
+            .collect(),
+    }
+}
+
+pub fn shannon_entropy(population: &[u32]) -> f32 {
+    let total: u32 = population.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    population
+        .iter()
+        .filter(|&&n| n > 0)
+        .map(|&n| {
+            let p = n as f32 / total as f32;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+// One row per run: the swept value, the population of each state, then the entropy
+pub fn results_csv(param: ExampleParam, state_names: &[String], results: &[SweepResult]) -> String {
+    let mut out = format!("{},{},entropy\n", param, state_names.join(","));
+    for result in results {
+        let counts: Vec<String> = result.population.iter().map(|n| n.to_string()).collect();
+        out.push_str(&format!(
+            "{},{},{:.4}\n",
+            result.value,
+            counts.join(","),
+            result.entropy
+        ));
+    }
+    out
+}
@@ -0,0 +1,208 @@
+use crate::app::simulation::{compute_step, StepBuffers, StepSettings};
+use crate::state::ca_grid::NeighborCounts;
+use crate::state::session::{SavedGrid, Session};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: ca_test run --project <project.json> --steps <n> --out <grid.csv|grid.json> [--seed <n>] [--deterministic]";
+
+// Options of `run`, which steps a saved project without opening a window
+struct RunOptions {
+    project: PathBuf,
+    steps: u64,
+    out: PathBuf,
+    seed: u64,
+    deterministic: bool,
+}
+
+fn parse_run_options(args: &[String]) -> Result<RunOptions, String> {
+    let (mut project, mut steps, mut out) = (None, None, None);
+    let mut seed = 0;
+    let mut deterministic = false;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", flag));
+        match flag.as_str() {
+            "--project" => project = Some(PathBuf::from(value()?)),
+            "--out" => out = Some(PathBuf::from(value()?)),
+            "--steps" => {
+                let raw = value()?;
+                steps = Some(
+                    raw.parse::<u64>()
+                        .map_err(|_| format!("Invalid --steps '{}'", raw))?,
+                );
+            }
+            "--seed" => {
+                let raw = value()?;
+                seed = raw
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid --seed '{}'", raw))?;
+            }
+            "--deterministic" => deterministic = true,
+            _ => return Err(format!("Unknown option '{}'", flag)),
+        }
+    }
+
+    Ok(RunOptions {
+        project: project.ok_or("Missing --project")?,
+        steps: steps.ok_or("Missing --steps")?,
+        out: out.ok_or("Missing --out")?,
+        seed,
+        deterministic,
+    })
+}
+
+// `run`: loads a project saved from the app, steps it and writes the final grid,
+// as CSV or, for a .json path, in the app's Save Grid format
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_run_options(args)?;
+
+    let data = std::fs::read_to_string(&options.project)
+        .map_err(|e| format!("Failed to read {}: {}", options.project.display(), e))?;
+    let mut session: Session =
+        serde_json::from_str(&data).map_err(|e| format!("Failed to parse project JSON: {}", e))?;
+    session
+        .grid
+        .validate_dimensions()
+        .map_err(|e| format!("Rejected malformed grid: {}", e))?;
+    // Same as loading the project in the app: invalid rules are left out
+    session.rules.retain(|rule| match rule.validate() {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!(
+                "Dropped invalid rule for '{}': {}",
+                rule.current_state_name, e
+            );
+            false
+        }
+    });
+
+    let mut grid = session.grid;
+    // Kept across steps, which is also what gives rules their previous generation
+    let mut neighbor_counts = NeighborCounts::default();
+    let mut buffers = StepBuffers::default();
+    for generation in 0..options.steps {
+        let settings = StepSettings {
+            no_match_policy: session.no_match_policy,
+            background_state_id: session.background_state_id,
+            seed: options.seed,
+            generation,
+            deterministic: options.deterministic,
+            eval_mode: session.rule_eval_mode,
+        };
+        compute_step(
+            &grid,
+            &session.rules,
+            &session.block_rules,
+            &session.states,
+            settings,
+            &mut neighbor_counts,
+            &mut buffers,
+        );
+        for (row, next_row) in grid
+            .cells
+            .iter_mut()
+            .zip(buffers.next.chunks(grid.width.max(1)))
+        {
+            row.copy_from_slice(next_row);
+        }
+    }
+
+    let is_json = options
+        .out
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let output = if is_json {
+        serde_json::to_string_pretty(&SavedGrid { grid, view: None })
+            .map_err(|e| format!("Failed to serialize grid: {}", e))?
+    } else {
+        grid.to_csv()
+    };
+    std::fs::write(&options.out, output)
+        .map_err(|e| format!("Failed to write {}: {}", options.out.display(), e))?;
+    println!(
+        "Ran {} step(s), final grid written to {}",
+        options.steps,
+        options.out.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::ca_grid::{BoundaryMode, CAGrid, Neighborhood};
+    use crate::state::exemple::{life_like_rules, life_like_states};
+    use crate::state::transition_rule::{NoMatchPolicy, RuleEvalMode};
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parse_run_options_reads_flags() {
+        let options = parse_run_options(&args(
+            "--project p.json --steps 12 --out out.csv --seed 9 --deterministic",
+        ))
+        .unwrap();
+        assert_eq!(options.project, PathBuf::from("p.json"));
+        assert_eq!(options.steps, 12);
+        assert_eq!(options.out, PathBuf::from("out.csv"));
+        assert_eq!(options.seed, 9);
+        assert!(options.deterministic);
+
+        assert!(parse_run_options(&args("--project p.json --out out.csv")).is_err());
+        assert!(parse_run_options(&args("--project p.json --steps x --out o.csv")).is_err());
+        assert!(parse_run_options(&args("--steps 1 --out o.csv --verbose")).is_err());
+    }
+
+    // A vertical blinker saved with last-match evaluation: every live cell falls through
+    // to Life's unconditional "die" rule, while the two births still happen
+    #[test]
+    fn run_steps_project_with_its_eval_mode() {
+        let dir = std::env::temp_dir().join(format!("ca_cli_run_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (project, out) = (dir.join("project.json"), dir.join("grid.csv"));
+
+        let states = life_like_states();
+        let rules = life_like_rules(&states[0], &states[1], &[3], &[2, 3]);
+        let mut cells = vec![vec![0; 5]; 5];
+        for row in &mut cells[1..4] {
+            row[2] = 1;
+        }
+        let session = Session {
+            states,
+            rules,
+            grid: CAGrid {
+                width: 5,
+                height: 5,
+                cells,
+                neighborhood: Neighborhood::Moore,
+                boundary: BoundaryMode::Bounded,
+            },
+            block_rules: Vec::new(),
+            background_state_id: 0,
+            no_match_policy: NoMatchPolicy::default(),
+            rule_eval_mode: RuleEvalMode::LastMatch,
+            annotations: Vec::new(),
+            view: None,
+            active_tab: None,
+        };
+        std::fs::write(&project, serde_json::to_string(&session).unwrap()).unwrap();
+
+        // Paths pushed whole, since the temp dir may contain spaces
+        let mut run_args = args("--steps 1 --project");
+        run_args.push(project.display().to_string());
+        run_args.push("--out".into());
+        run_args.push(out.display().to_string());
+        let result = run(&run_args);
+        let written = std::fs::read_to_string(&out);
+        std::fs::remove_dir_all(&dir).ok();
+
+        result.unwrap();
+        assert_eq!(
+            written.unwrap(),
+            "0,0,0,0,0\n0,0,0,0,0\n0,1,0,1,0\n0,0,0,0,0\n0,0,0,0,0\n"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 mod app;
+mod cli;
 mod messages;
 mod state;
 mod view;
@@ -7,6 +8,16 @@ use crate::app::CASimulator;
 use iced::{Application, Settings};
 
 pub fn main() -> iced::Result {
+    // `run` steps a saved project headlessly; anything else opens the app
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|command| command == "run") {
+        if let Err(e) = cli::run(&args[1..]) {
+            eprintln!("{}\n{}", e, cli::USAGE);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     CASimulator::run(Settings {
         window: iced::window::Settings {
             size: iced::Size {
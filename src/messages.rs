@@ -1,51 +1,183 @@
-use crate::app::simulator::TabId;
-use crate::state::ca_grid::Neighborhood;
+use crate::app::simulator::{
+    BorderMode, CanvasTool, ExampleParam, ImportMode, Modal, StepResult, StopCondition,
+    StopConditionKind, TabId,
+};
+use crate::state::ca_grid::{BoundaryMode, Direction, Neighborhood, SeedPattern};
 use crate::state::exemple::ExampleModel;
+use crate::state::pattern::PatternPlacement;
 use crate::state::transition_rule::ConditionCombiner;
+use crate::state::transition_rule::NoMatchPolicy;
 use crate::state::transition_rule::RelationalOperator;
+use crate::state::transition_rule::RuleEvalMode;
 use crate::state::CAState;
+use iced::Color;
 #[derive(Debug, Clone)]
 pub enum Message {
     TabSelected(TabId),
+    ModalOk,
+    ModalCancel,
+    ShowModal(Modal),
     Tick(()),
+    StepComplete(Result<StepResult, String>),
 
     // State definition
     RuleProbabilityChanged(String),
+    ProbabilityPercentToggled(bool),
+    RuleRegionToggled(bool),
+    RuleGlobalToggled(bool),
+    RuleGlobalStateSelected(CAState),
+    RuleGlobalOperatorSelected(RelationalOperator),
+    RuleGlobalThresholdChanged(String),
+    RulePrevToggled(bool),
+    RulePrevStateSelected(CAState),
+    AddOutcome,
+    RemoveOutcome(usize),
+    OutcomeStateSelected(usize, CAState),
+    OutcomeProbabilityChanged(usize, String),
+    RuleRegionChanged(usize, String),
+    RuleGroupBoundChanged(usize, String),
+    AddConditionGroup,
+    RemoveConditionGroup(usize),
     StateNameChanged(String),
     StateColorRChanged(String),
     StateColorGChanged(String),
     StateColorBChanged(String),
+    StateColorPicked(Color),
+    RgbFieldsToggled(bool),
     AddState,
-    RemoveState(usize), // by index
+    RemoveState(usize), // by index; asks first if rules or cells use the state
+    ConfirmRemoveState(usize), // by index
 
     // Rule definition
     ExampleModelSelected(ExampleModel),
+    ExampleParamChanged(ExampleParam, String),
+    LifeLikeNotationChanged(String),
+    ApplyLifeLike(String),
     RuleCombinerSelected(usize, ConditionCombiner),
     AddCondition,
     RemoveCondition(usize),
     RuleNeighborStateSelected(usize, CAState),
     RuleOperatorSelected(usize, RelationalOperator),
     RuleThresholdChanged(usize, String),
+    RuleDirectionToggled(usize, Direction),
     RuleCurrentStateSelected(CAState),
     RuleNextStateSelected(CAState),
     AddRule,
+    ClearRuleForm,
+    DismissFormWarning,
     RemoveRule(usize), // by index
+    EditRule(usize),
+    Undo,
+    Redo,
+    MoveRuleUp(usize),
+    MoveRuleDown(usize),
+    StateNameEdited(usize, String),
+    StateColorEdited(usize, usize, String), // State index, channel (0 = R, 1 = G, 2 = B)
     StateWeightChanged(usize, String),
+    BackgroundStateSelected(CAState),
     ExportRules,
     ImportRules,
+    ExportConfig,
+    ImportConfig,
+
+    // Parameter sweep
+    SweepParamSelected(ExampleParam),
+    SweepInputChanged(usize, String),
+    StartSweep,
+    SweepTick,
+    RunUntilKindSelected(StopConditionKind),
+    RunUntilStateSelected(CAState),
+    RunUntilOperatorSelected(RelationalOperator),
+    RunUntilValueChanged(String),
+    RunUntil(StopCondition),
+    CancelSweep,
+    ExportSweepCsv,
+    CopyRulesToClipboard,
+    PasteRulesFromClipboard,
+    RulesPasted(Option<String>),
+    ImportModeSelected(ImportMode),
+
+    SaveProject,
+    LoadProject,
+    // Session autosave
+    AutosaveToggled(bool),
+    AutosaveIntervalChanged(String),
+    Autosave,
+    PanStepChanged(String),
+    KeyboardPan(f32, f32), // Direction in steps along x/y
+    KeyboardZoom(f32),     // +1 zooms in, -1 zooms out
+    RestoreAutosave,
+    ValidateModel,
+    FindInterestingSeed,
+    DismissModelIssues,
+    DiscardAutosave,
 
     // Grid/Simulation
     ToggleFullscreen,
+    ResetView,
+    ClearGrid,
+    SeedChanged(String),
+    RandomizeSeed,
+    DeterministicToggled(bool),
+    TransitionFlashesToggled(bool),
+    AgeShadingToggled(bool),
+    BorderWidthChanged(String),
+    BorderModeSelected(BorderMode),
     SaveGrid,
     LoadGrid,
+    ExportCsv,
+    ImportCsv,
+    ImportRle,
+    ImportCells,
+    PatternPlacementSelected(PatternPlacement),
+    LoadComparisonGrid,
+    ExportPng,
+    ExportGif,
+    ExportFinished(Result<std::path::PathBuf, String>),
+    GifRecordingToggled(bool),
+    ClearGifFrames,
+    ExportCaptionToggled(bool),
+    ClearComparisonGrid,
+    ComparisonDiffToggled(bool),
     NeighborhoodChanged(Neighborhood),
+    LoadBilliardBallRules,
+    ClearBlockRules,
+    RingInnerChanged(String),
+    RingOuterChanged(String),
+    NeighborhoodRadiusChanged(String),
+    BoundaryModeChanged(BoundaryMode),
+    NoMatchPolicySelected(NoMatchPolicy),
+    RuleEvalModeSelected(RuleEvalMode),
+    BoundaryWallStateSelected(CAState),
     GridWidthChanged(String),
     GridHeightChanged(String),
+    PreserveGridContentsToggled(bool),
     ApplyGridSize,
     ResetGrid,
+    SeedPatternSelected(SeedPattern),
     ToggleSimulation,
     NextStep,
+    StepBack,
+    StopAtChanged(String),
+    StopOnStableToggled(bool),
     SimulationSpeedChanged(f32), // From slider (0-100), map to ms
     PaintStateSelected(CAState), // For selecting which state to paint on click
     PaintCell(usize, usize, u8),
+    LineAnchorSet(usize, usize),           // First click of the Line tool
+    PaintLine(usize, usize, usize, usize), // From (row, col) to (row, col)
+    PaintRect(usize, usize, usize, usize, bool), // Opposite corners, then whether filled
+    RectFilledToggled(bool),
+    CellHovered,
+    BrushSizeChanged(u8),
+    CanvasToolSelected(CanvasTool),
+    AnnotationCellSelected(usize, usize),
+    PokeCell(usize, usize),
+    AnnotationTextChanged(String),
+    SaveAnnotation,
+    RemoveAnnotation,
+    PauseWhilePaintingToggled(bool),
+    BulkFromStateSelected(CAState),
+    BulkToStateSelected(CAState),
+    ReplaceState(u8, u8), // from id, to id
+    SwapStates(u8, u8),
 }
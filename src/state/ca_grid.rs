@@ -1,6 +1,10 @@
+use crate::state::pattern::Pattern;
 use crate::state::CAState;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::fmt;
+use std::sync::OnceLock;
 
 // The 2D grid for simulation
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,13 +13,338 @@ pub struct CAGrid {
     pub height: usize,
     pub cells: Vec<Vec<u8>>, // Stores state IDs
     pub neighborhood: Neighborhood,
+    #[serde(default)]
+    pub boundary: BoundaryMode,
 }
 
+// How neighbors that fall outside the grid are counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BoundaryMode {
+    // Out-of-bounds neighbors are ignored
+    #[default]
+    Bounded,
+    // Out-of-bounds neighbors count as the given state (a wall around the grid)
+    Fixed(u8),
+    // Opposite edges are joined, so neighbors wrap around to the other side
+    Toroidal,
+}
+
+impl fmt::Display for BoundaryMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoundaryMode::Bounded => write!(f, "Bounded"),
+            BoundaryMode::Fixed(_) => write!(f, "Fixed wall state"),
+            BoundaryMode::Toroidal => write!(f, "Toroidal (wrap around)"),
+        }
+    }
+}
+
+// Radius caps of the parameterized neighborhoods. They keep counting affordable and
+// every count well inside u16 (a radius of 10 sees 440 cells)
+pub const MAX_RING_RADIUS: u8 = 10;
+pub const MAX_MOORE_RADIUS: u8 = 10;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Neighborhood {
     VonNeumann,
     Moore,
     ExtendedMoore,
+    // Square shell: cells whose Chebyshev distance lies in [inner, outer]
+    Ring { inner: u8, outer: u8 },
+    // Full square of the given Chebyshev radius, e.g. 1 is Moore and 2 Extended Moore
+    MooreRadius(u8),
+    // 2x2 blocks updated by block rules instead of per-cell rules. Counts made under
+    // it (e.g. by Poke) use the Moore neighborhood
+    Margolus,
+}
+
+// How distance from the center cell is measured when building a neighborhood
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistanceMetric {
+    Chebyshev, // square: max(|dr|, |dc|)
+    Manhattan, // diamond: |dr| + |dc|
+}
+
+// Describes a neighborhood as every offset whose distance under `metric` lies
+// in `min_radius..=radius`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NeighborhoodShape {
+    pub min_radius: usize,
+    pub radius: usize,
+    pub metric: DistanceMetric,
+    pub include_self: bool,
+}
+
+impl NeighborhoodShape {
+    // Offsets in row-major order, from (-radius, -radius) to (radius, radius)
+    pub fn offsets(&self) -> Vec<(isize, isize)> {
+        let radius = self.radius as isize;
+        let mut offsets = Vec::new();
+        for dr in -radius..=radius {
+            for dc in -radius..=radius {
+                if dr == 0 && dc == 0 && !self.include_self {
+                    continue;
+                }
+                let distance = match self.metric {
+                    DistanceMetric::Chebyshev => dr.abs().max(dc.abs()),
+                    DistanceMetric::Manhattan => dr.abs() + dc.abs(),
+                };
+                if distance >= self.min_radius as isize && distance <= radius {
+                    offsets.push((dr, dc));
+                }
+            }
+        }
+        offsets
+    }
+}
+
+impl Neighborhood {
+    // Rejects radii the editor wouldn't allow, e.g. from a hand-edited file
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            Neighborhood::Ring { inner, outer }
+                if inner == 0 || inner > outer || outer > MAX_RING_RADIUS =>
+            {
+                Err(format!(
+                    "ring {}-{} must satisfy 1 <= inner <= outer <= {}",
+                    inner, outer, MAX_RING_RADIUS
+                ))
+            }
+            Neighborhood::MooreRadius(radius) if radius == 0 || radius > MAX_MOORE_RADIUS => {
+                Err(format!(
+                    "Moore radius {} must be between 1 and {}",
+                    radius, MAX_MOORE_RADIUS
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn shape(&self) -> NeighborhoodShape {
+        let (min_radius, radius, metric) = match *self {
+            Neighborhood::VonNeumann => (1, 1, DistanceMetric::Manhattan),
+            Neighborhood::Moore | Neighborhood::Margolus => (1, 1, DistanceMetric::Chebyshev),
+            Neighborhood::ExtendedMoore => (1, 2, DistanceMetric::Chebyshev),
+            Neighborhood::Ring { inner, outer } => (
+                inner.max(1) as usize,
+                outer as usize,
+                DistanceMetric::Chebyshev,
+            ),
+            Neighborhood::MooreRadius(radius) => (1, radius as usize, DistanceMetric::Chebyshev),
+        };
+        NeighborhoodShape {
+            min_radius,
+            radius,
+            metric,
+            include_self: false,
+        }
+    }
+
+    // The fixed variants' offsets are built once and shared. Ring and MooreRadius are
+    // built on every call, so counting code resolves them once per step, not per cell
+    pub fn offsets(&self) -> Cow<'static, [(isize, isize)]> {
+        static VON_NEUMANN: OnceLock<Vec<(isize, isize)>> = OnceLock::new();
+        static MOORE: OnceLock<Vec<(isize, isize)>> = OnceLock::new();
+        static EXTENDED_MOORE: OnceLock<Vec<(isize, isize)>> = OnceLock::new();
+
+        let table = match self {
+            Neighborhood::VonNeumann => &VON_NEUMANN,
+            Neighborhood::Moore | Neighborhood::Margolus => &MOORE,
+            Neighborhood::ExtendedMoore => &EXTENDED_MOORE,
+            Neighborhood::Ring { .. } | Neighborhood::MooreRadius(_) => {
+                return Cow::Owned(self.shape().offsets());
+            }
+        };
+        Cow::Borrowed(table.get_or_init(|| self.shape().offsets()))
+    }
+}
+
+// Compass direction of a neighbor, with north towards row 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    N,
+    NE,
+    E,
+    SE,
+    S,
+    SW,
+    W,
+    NW,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::N,
+        Direction::NE,
+        Direction::E,
+        Direction::SE,
+        Direction::S,
+        Direction::SW,
+        Direction::W,
+        Direction::NW,
+    ];
+
+    // Direction an offset points to, by the signs of its components. In neighborhoods
+    // larger than Moore, N covers the whole column above the cell and NE everything
+    // strictly above and to the right. The cell itself has no direction
+    pub fn of_offset(dr: isize, dc: isize) -> Option<Direction> {
+        Some(match (dr.signum(), dc.signum()) {
+            (-1, 0) => Direction::N,
+            (-1, 1) => Direction::NE,
+            (0, 1) => Direction::E,
+            (1, 1) => Direction::SE,
+            (1, 0) => Direction::S,
+            (1, -1) => Direction::SW,
+            (0, -1) => Direction::W,
+            (-1, -1) => Direction::NW,
+            _ => return None,
+        })
+    }
+
+    // Rotated clockwise by `eighths` eighths of a turn, so 2 is a right angle
+    pub fn turned(self, eighths: usize) -> Direction {
+        Direction::ALL[(self as usize + eighths) % 8]
+    }
+
+    pub fn opposite(self) -> Direction {
+        self.turned(4)
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::str::FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Direction::ALL
+            .into_iter()
+            .find(|d| d.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("'{}' is not a direction (N, NE, E, SE, S, SW, W, NW)", s))
+    }
+}
+
+// Set of directions a condition counts neighbors in, one bit per `Direction`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectionMask(pub u8);
+
+impl DirectionMask {
+    // Every direction, which is the whole neighborhood
+    pub const ALL: DirectionMask = DirectionMask(0xFF);
+
+    pub fn only(direction: Direction) -> Self {
+        DirectionMask(1 << direction as u8)
+    }
+
+    pub fn contains(self, direction: Direction) -> bool {
+        self.0 & (1 << direction as u8) != 0
+    }
+
+    pub fn contains_offset(self, dr: isize, dc: isize) -> bool {
+        Direction::of_offset(dr, dc).is_some_and(|d| self.contains(d))
+    }
+
+    // Adds `direction` when missing, removes it otherwise
+    pub fn toggled(self, direction: Direction) -> Self {
+        DirectionMask(self.0 ^ DirectionMask::only(direction).0)
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl Default for DirectionMask {
+    fn default() -> Self {
+        DirectionMask::ALL
+    }
+}
+
+impl fmt::Display for DirectionMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = Direction::ALL
+            .iter()
+            .filter(|&&d| self.contains(d))
+            .map(|d| d.to_string())
+            .collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+impl std::str::FromStr for DirectionMask {
+    type Err = String;
+
+    // Comma-separated directions, e.g. "N,NE"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mask = DirectionMask(0);
+        for part in s.split(',') {
+            mask.0 |= DirectionMask::only(part.parse()?).0;
+        }
+        Ok(mask)
+    }
+}
+
+// Cells of the straight line from `from` to `to` (row, col), both ends included,
+// by Bresenham's algorithm
+pub fn line_cells(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+    let (mut r, mut c) = (from.0 as isize, from.1 as isize);
+    let (r1, c1) = (to.0 as isize, to.1 as isize);
+    let (dr, dc) = ((r1 - r).abs(), -(c1 - c).abs());
+    let (step_r, step_c) = ((r1 - r).signum(), (c1 - c).signum());
+    let mut error = dr + dc;
+
+    let mut cells = Vec::new();
+    loop {
+        cells.push((r as usize, c as usize));
+        if r == r1 && c == c1 {
+            return cells;
+        }
+        let doubled = 2 * error;
+        if doubled >= dc {
+            error += dc;
+            r += step_r;
+        }
+        if doubled <= dr {
+            error += dr;
+            c += step_c;
+        }
+    }
+}
+
+// Deterministic starting layouts, stamped centered over a background
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedPattern {
+    SingleCell,
+    Block,
+    Cross,
+    RandomDisc,
+    Checkerboard,
+}
+
+impl SeedPattern {
+    pub const ALL: [SeedPattern; 5] = [
+        SeedPattern::SingleCell,
+        SeedPattern::Block,
+        SeedPattern::Cross,
+        SeedPattern::RandomDisc,
+        SeedPattern::Checkerboard,
+    ];
+}
+
+impl fmt::Display for SeedPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeedPattern::SingleCell => write!(f, "Single centered cell"),
+            SeedPattern::Block => write!(f, "Centered block"),
+            SeedPattern::Cross => write!(f, "Centered cross"),
+            SeedPattern::RandomDisc => write!(f, "Random disc"),
+            SeedPattern::Checkerboard => write!(f, "Checkerboard"),
+        }
+    }
 }
 
 impl fmt::Display for Neighborhood {
@@ -23,48 +352,67 @@ impl fmt::Display for Neighborhood {
         match self {
             Neighborhood::VonNeumann => write!(f, "Von Neumann (4)"),
             Neighborhood::Moore => write!(f, "Moore (8)"),
-            Neighborhood::ExtendedMoore => write!(f, "Extended Moore (16)"),
+            Neighborhood::ExtendedMoore => write!(f, "Extended Moore (24)"),
+            Neighborhood::Ring { inner, outer } => {
+                write!(f, "Ring {}-{} ({})", inner, outer, self.offsets().len())
+            }
+            Neighborhood::MooreRadius(radius) => {
+                write!(f, "Moore radius {} ({})", radius, self.offsets().len())
+            }
+            Neighborhood::Margolus => write!(f, "Margolus (2x2 blocks)"),
+        }
+    }
+}
+
+// Maps a roll in `0..total_weight` to the state whose cumulative weight window contains it
+fn pick_weighted_state(states: &[CAState], mut roll: u32) -> u8 {
+    for state in states {
+        if roll < state.weight as u32 {
+            return state.id;
         }
+        roll -= state.weight as u32;
     }
+    states[0].id
 }
 
 impl CAGrid {
-    pub fn new(
+    // Weighted random fill driven by the given RNG, so a seeded RNG gives a reproducible grid.
+    //
+    // Each cell draws `roll` uniformly in `0..total_weight` and takes the state whose
+    // cumulative window `[sum of previous weights, + own weight)` contains it. Every state
+    // therefore gets exactly `weight / total_weight` of the probability mass; there are no
+    // ties to break. States are ordered by id first so the same seed maps to the same grid
+    // no matter how the state list is ordered in the UI.
+    pub fn new_with_rng<R: Rng + ?Sized>(
         width: usize,
         height: usize,
         states: Vec<CAState>,
         neighborhood: Neighborhood,
+        background_state_id: u8,
+        rng: &mut R,
     ) -> Self {
-        use rand::Rng;
-
         let mut available_states: Vec<CAState> =
             states.into_iter().filter(|s| s.weight > 0).collect();
+        available_states.sort_by_key(|s| s.id);
 
+        // Nothing to sample from: start from a uniform background instead
         if available_states.is_empty() {
-            available_states.push(CAState {
-                id: 0,
-                name: "Default".to_string(),
-                color: iced::Color::BLACK,
-                weight: 1,
-            });
+            return CAGrid {
+                width,
+                height,
+                cells: vec![vec![background_state_id; width]; height],
+                neighborhood,
+                boundary: BoundaryMode::default(),
+            };
         }
 
         let total_weight: u32 = available_states.iter().map(|s| s.weight as u32).sum();
 
-        let mut rng = rand::rng();
-
         let cells = (0..height)
             .map(|_| {
                 (0..width)
                     .map(|_| {
-                        let mut roll = rng.random_range(0..total_weight);
-                        for state in &available_states {
-                            if roll < state.weight as u32 {
-                                return state.id;
-                            }
-                            roll -= state.weight as u32;
-                        }
-                        available_states[0].id
+                        pick_weighted_state(&available_states, rng.random_range(0..total_weight))
                     })
                     .collect::<Vec<u8>>()
             })
@@ -75,66 +423,604 @@ impl CAGrid {
             height,
             cells,
             neighborhood,
+            boundary: BoundaryMode::default(),
+        }
+    }
+
+    // Clears to `background_state_id` and stamps `pattern` in `fill_state_id`, centered.
+    // RandomDisc draws its cells from `rng`
+    pub fn apply_seed_pattern<R: Rng + ?Sized>(
+        &mut self,
+        pattern: SeedPattern,
+        background_state_id: u8,
+        fill_state_id: u8,
+        rng: &mut R,
+    ) {
+        let (height, width) = (self.height, self.width);
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let (center_r, center_c) = (height / 2, width / 2);
+        // Block side, cross arm length and disc radius scale with the smaller side
+        let extent = (width.min(height) / 5).max(1);
+
+        for r in 0..height {
+            for c in 0..width {
+                let dr = r.abs_diff(center_r);
+                let dc = c.abs_diff(center_c);
+                let filled = match pattern {
+                    SeedPattern::SingleCell => dr == 0 && dc == 0,
+                    SeedPattern::Block => dr <= extent / 2 && dc <= extent / 2,
+                    SeedPattern::Cross => (dr == 0 && dc <= extent) || (dc == 0 && dr <= extent),
+                    SeedPattern::RandomDisc => {
+                        dr * dr + dc * dc <= extent * extent && rng.random_bool(0.5)
+                    }
+                    SeedPattern::Checkerboard => (r + c) % 2 == 0,
+                };
+                self.cells[r][c] = if filled {
+                    fill_state_id
+                } else {
+                    background_state_id
+                };
+            }
         }
     }
 
-    pub fn count_neighbors(&self, r: usize, c: usize, target_state_id: u8) -> u8 {
-        let directions: &[(isize, isize)] = match self.neighborhood {
-            Neighborhood::VonNeumann => &[(-1, 0), (1, 0), (0, -1), (0, 1)],
-            Neighborhood::Moore => &[
-                (-1, -1),
-                (-1, 0),
-                (-1, 1),
-                (0, -1),
-                (0, 1),
-                (1, -1),
-                (1, 0),
-                (1, 1),
-            ],
-            Neighborhood::ExtendedMoore => &[
-                // normal Moore
-                (-1, -1),
-                (-1, 0),
-                (-1, 1),
-                (0, -1),
-                (0, 1),
-                (1, -1),
-                (1, 0),
-                (1, 1),
-                // Second layer
-                (-2, -2),
-                (-2, -1),
-                (-2, 0),
-                (-2, 1),
-                (-2, 2),
-                (-1, -2),
-                (-1, 2),
-                (0, -2),
-                (0, 2),
-                (1, -2),
-                (1, 2),
-                (2, -2),
-                (2, -1),
-                (2, 0),
-                (2, 1),
-                (2, 2),
-            ],
+    // Writes `pattern` with its top-left cell at `origin`, live cells as `alive_state_id`
+    // and dead ones as `dead_state_id`. Cells around the pattern keep their state
+    pub fn stamp_pattern(
+        &mut self,
+        pattern: &Pattern,
+        origin: (usize, usize),
+        alive_state_id: u8,
+        dead_state_id: u8,
+    ) -> Result<(), String> {
+        let (top, left) = origin;
+        if top + pattern.height > self.height || left + pattern.width > self.width {
+            return Err(format!(
+                "The {}x{} pattern doesn't fit on the {}x{} grid",
+                pattern.width, pattern.height, self.width, self.height
+            ));
+        }
+        for (r, row) in pattern.cells.iter().enumerate() {
+            for (c, &alive) in row.iter().enumerate() {
+                self.cells[top + r][left + c] = if alive { alive_state_id } else { dead_state_id };
+            }
+        }
+        Ok(())
+    }
+
+    // Number of cells in each state, indexed by state id
+    pub fn population(&self) -> [u32; 256] {
+        let mut counts = [0u32; 256];
+        for &id in self.cells.iter().flatten() {
+            counts[id as usize] += 1;
+        }
+        counts
+    }
+
+    // Cells in each state id below `num_ids`, in a single pass over the grid
+    pub fn state_counts(&self, num_ids: u8) -> Vec<usize> {
+        let mut counts = vec![0; num_ids as usize];
+        for &id in self.cells.iter().flatten() {
+            if let Some(count) = counts.get_mut(id as usize) {
+                *count += 1;
+            }
+        }
+        counts
+    }
+
+    // FNV-1a over the dimensions and cells: stable across runs and platforms, so two
+    // users can compare grids by a single number
+    pub fn checksum(&self) -> u64 {
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        let mut hash = FNV_OFFSET;
+        let dims = (self.width as u64)
+            .to_le_bytes()
+            .into_iter()
+            .chain((self.height as u64).to_le_bytes());
+        for byte in dims.chain(self.cells.iter().flatten().copied()) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+        hash
+    }
+
+    // One line per row of state ids, separated by commas
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        for row in &self.cells {
+            let ids: Vec<String> = row.iter().map(|id| id.to_string()).collect();
+            out.push_str(&ids.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    // Reads cells written by `to_csv`; every row must have the same number of ids
+    pub fn cells_from_csv(text: &str) -> Result<Vec<Vec<u8>>, String> {
+        let cells = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .enumerate()
+            .map(|(r, line)| {
+                line.split(',')
+                    .map(|id| {
+                        id.trim()
+                            .parse::<u8>()
+                            .map_err(|_| format!("row {}: '{}' is not a state id", r + 1, id))
+                    })
+                    .collect::<Result<Vec<u8>, String>>()
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let width = cells.first().map_or(0, |row| row.len());
+        if width == 0 {
+            return Err("the file has no cells".into());
+        }
+        if let Some(r) = cells.iter().position(|row| row.len() != width) {
+            return Err(format!(
+                "row {} has {} cells, but the first row has {}",
+                r + 1,
+                cells[r].len(),
+                width
+            ));
+        }
+        Ok(cells)
+    }
+
+    // Checks that `cells` really is `height` rows of `width` cells and that the
+    // neighborhood is one the editor allows (e.g. after loading JSON)
+    pub fn validate_dimensions(&self) -> Result<(), String> {
+        if self.cells.len() != self.height {
+            return Err(format!(
+                "grid declares height {} but has {} rows",
+                self.height,
+                self.cells.len()
+            ));
+        }
+        if let Some((r, row)) = self
+            .cells
+            .iter()
+            .enumerate()
+            .find(|(_, row)| row.len() != self.width)
+        {
+            return Err(format!(
+                "grid declares width {} but row {} has {} cells",
+                self.width,
+                r,
+                row.len()
+            ));
+        }
+        self.neighborhood.validate()
+    }
+
+    // Changes the size in place: cells inside both the old and new sizes keep their
+    // state, and any new cells get `fill_state_id`
+    pub fn resize(&mut self, width: usize, height: usize, fill_state_id: u8) {
+        self.cells.truncate(height);
+        for row in &mut self.cells {
+            row.resize(width, fill_state_id);
+        }
+        self.cells.resize(height, vec![fill_state_id; width]);
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn fill(&mut self, state_id: u8) {
+        for row in &mut self.cells {
+            row.fill(state_id);
+        }
+    }
+
+    pub fn replace_state(&mut self, from_state_id: u8, to_state_id: u8) {
+        for cell in self.cells.iter_mut().flat_map(|row| row.iter_mut()) {
+            if *cell == from_state_id {
+                *cell = to_state_id;
+            }
+        }
+    }
+
+    pub fn swap_states(&mut self, a_state_id: u8, b_state_id: u8) {
+        for cell in self.cells.iter_mut().flat_map(|row| row.iter_mut()) {
+            if *cell == a_state_id {
+                *cell = b_state_id;
+            } else if *cell == b_state_id {
+                *cell = a_state_id;
+            }
+        }
+    }
+
+    // Neighbors in `target_state_id` among those lying in `mask`'s directions. u16 so
+    // large neighborhoods (more than 255 cells) can't wrap the count. `offsets` is
+    // this grid's `neighborhood.offsets()`, resolved once by the caller
+    pub fn count_neighbors_masked(
+        &self,
+        offsets: &[(isize, isize)],
+        r: usize,
+        c: usize,
+        target_state_id: u8,
+        mask: DirectionMask,
+    ) -> u16 {
+        self.neighbor_states(offsets, r, c, mask)
+            .filter(|&id| id == target_state_id)
+            .count() as u16
+    }
+
+    // Neighbors in any state other than `state_id`, for `count(!self)` conditions
+    pub fn count_differing_neighbors(
+        &self,
+        offsets: &[(isize, isize)],
+        r: usize,
+        c: usize,
+        state_id: u8,
+        mask: DirectionMask,
+    ) -> u16 {
+        self.neighbor_states(offsets, r, c, mask)
+            .filter(|&id| id != state_id)
+            .count() as u16
+    }
+
+    // State of every neighbor of (r, c) in `mask` that the boundary mode lets the cell see
+    fn neighbor_states<'a>(
+        &'a self,
+        offsets: &'a [(isize, isize)],
+        r: usize,
+        c: usize,
+        mask: DirectionMask,
+    ) -> impl Iterator<Item = u8> + 'a {
+        offsets
+            .iter()
+            .filter(move |&&(dr, dc)| mask == DirectionMask::ALL || mask.contains_offset(dr, dc))
+            .filter_map(move |&(dr, dc)| {
+                let nr = r as isize + dr;
+                let nc = c as isize + dc;
+
+                let in_bounds =
+                    nr >= 0 && nr < self.height as isize && nc >= 0 && nc < self.width as isize;
+
+                if in_bounds {
+                    return Some(self.cells[nr as usize][nc as usize]);
+                }
+                match self.boundary {
+                    BoundaryMode::Bounded => None,
+                    BoundaryMode::Fixed(wall_state_id) => Some(wall_state_id),
+                    // rem_euclid also covers offsets larger than the grid itself
+                    BoundaryMode::Toroidal => {
+                        let wr = nr.rem_euclid(self.height as isize) as usize;
+                        let wc = nc.rem_euclid(self.width as isize) as usize;
+                        Some(self.cells[wr][wc])
+                    }
+                }
+            })
+    }
+}
+
+// Changed cells above this fraction of the grid are cheaper to recount from scratch
+const INCREMENTAL_RECOUNT_DIVISOR: usize = 4;
+
+// How many neighbors of each state every cell has, kept between steps. Only the
+// cells around those that changed since the last refresh are recounted
+#[derive(Debug, Clone, Default)]
+pub struct NeighborCounts {
+    pub counts: Vec<Vec<u16>>, // [state id][row * width + col]
+    // Flat cells and layout the counts were taken from
+    cells: Vec<u8>,
+    layout: Option<(usize, usize, Neighborhood, BoundaryMode)>,
+    changed: Vec<usize>, // Scratch list of changed cells, reused between refreshes
+}
+
+impl NeighborCounts {
+    // Brings the counts of ids below `id_slots` up to date with `grid`
+    pub fn refresh(&mut self, grid: &CAGrid, id_slots: usize) {
+        let layout = Some((grid.width, grid.height, grid.neighborhood, grid.boundary));
+        let offsets = grid.neighborhood.offsets();
+
+        if self.layout != layout || self.counts.len() != id_slots {
+            self.recount(grid, &offsets, id_slots);
+        } else {
+            let mut changed = std::mem::take(&mut self.changed);
+            changed.clear();
+            changed.extend(
+                grid.cells
+                    .iter()
+                    .flatten()
+                    .zip(&self.cells)
+                    .enumerate()
+                    .filter(|(_, (now, before))| now != before)
+                    .map(|(idx, _)| idx),
+            );
+            if changed.len() > self.cells.len() / INCREMENTAL_RECOUNT_DIVISOR {
+                self.recount(grid, &offsets, id_slots);
+            } else {
+                for &idx in &changed {
+                    let now = grid.cells[idx / grid.width][idx % grid.width];
+                    self.move_cell(grid, &offsets, idx, self.cells[idx], now);
+                }
+            }
+            self.changed = changed;
+        }
+        self.cells.clear();
+        self.cells.extend(grid.cells.iter().flatten());
+        self.layout = layout;
+    }
+
+    fn recount(&mut self, grid: &CAGrid, offsets: &[(isize, isize)], id_slots: usize) {
+        let size = grid.width * grid.height;
+        // Reuses the count buffers when they already have the right shape
+        self.counts.resize_with(id_slots, Vec::new);
+        for counts in &mut self.counts {
+            counts.clear();
+            counts.resize(size, 0);
+        }
+        for r in 0..grid.height {
+            for c in 0..grid.width {
+                for id in grid.neighbor_states(offsets, r, c, DirectionMask::ALL) {
+                    if let Some(counts) = self.counts.get_mut(id as usize) {
+                        counts[r * grid.width + c] += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    // Moves the count of the cell at `idx` from state `from` to `to` in every cell that
+    // sees it. Walls of a fixed boundary never change, so only in-grid cells are visited
+    fn move_cell(
+        &mut self,
+        grid: &CAGrid,
+        offsets: &[(isize, isize)],
+        idx: usize,
+        from: u8,
+        to: u8,
+    ) {
+        let (r, c) = ((idx / grid.width) as isize, (idx % grid.width) as isize);
+        let (height, width) = (grid.height as isize, grid.width as isize);
+        for &(dr, dc) in offsets {
+            // The cell that sees (r, c) through this offset
+            let (mut sr, mut sc) = (r - dr, c - dc);
+            let in_bounds = sr >= 0 && sr < height && sc >= 0 && sc < width;
+            if !in_bounds {
+                if grid.boundary != BoundaryMode::Toroidal {
+                    continue;
+                }
+                sr = sr.rem_euclid(height);
+                sc = sc.rem_euclid(width);
+            }
+            let seer = sr as usize * grid.width + sc as usize;
+            if let Some(counts) = self.counts.get_mut(from as usize) {
+                counts[seer] -= 1;
+            }
+            if let Some(counts) = self.counts.get_mut(to as usize) {
+                counts[seer] += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::simulation::step;
+    use crate::state::exemple::{life_like_rules, life_like_states};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // Runs Game of Life from a glider and a blinker, checking after every generation
+    // that the incrementally refreshed counts match a count taken from scratch
+    fn assert_refresh_matches_recount(boundary: BoundaryMode) {
+        let states = life_like_states();
+        let rules = life_like_rules(&states[0], &states[1], &[3], &[2, 3]);
+        let (width, height) = (16, 16);
+        let mut cells = vec![vec![0u8; width]; height];
+        for (r, c) in [
+            (0, 1),
+            (1, 2),
+            (2, 0),
+            (2, 1),
+            (2, 2),
+            (8, 7),
+            (8, 8),
+            (8, 9),
+        ] {
+            cells[r][c] = 1;
+        }
+        let mut grid = CAGrid {
+            width,
+            height,
+            cells,
+            neighborhood: Neighborhood::Moore,
+            boundary,
         };
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut counts = NeighborCounts::default();
 
-        let mut count = 0;
-        for (dr, dc) in directions {
-            let nr = r as isize + dr;
-            let nc = c as isize + dc;
+        for generation in 0..40 {
+            counts.refresh(&grid, states.len());
+            let mut fresh = NeighborCounts::default();
+            fresh.recount(&grid, &grid.neighborhood.offsets(), states.len());
+            assert_eq!(
+                counts.counts, fresh.counts,
+                "{boundary:?} counts diverged at generation {generation}"
+            );
 
-            if nr >= 0
-                && nr < self.height as isize
-                && nc >= 0
-                && nc < self.width as isize
-                && self.cells[nr as usize][nc as usize] == target_state_id
-            {
-                count += 1;
+            let next = step(&grid, &rules, &states, &mut rng);
+            for (row, next_row) in grid.cells.iter_mut().zip(next.chunks(width)) {
+                row.copy_from_slice(next_row);
             }
         }
-        count
+    }
+
+    #[test]
+    fn refresh_matches_recount_bounded() {
+        assert_refresh_matches_recount(BoundaryMode::Bounded);
+    }
+
+    #[test]
+    fn refresh_matches_recount_fixed() {
+        assert_refresh_matches_recount(BoundaryMode::Fixed(1));
+    }
+
+    #[test]
+    fn refresh_matches_recount_toroidal() {
+        assert_refresh_matches_recount(BoundaryMode::Toroidal);
+    }
+
+    // Offsets must leave out the cell itself and list no cell twice
+    fn assert_offsets(neighborhood: Neighborhood, expected: &[(isize, isize)]) {
+        let offsets = neighborhood.offsets();
+        let mut sorted = offsets.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(
+            sorted.len(),
+            offsets.len(),
+            "{neighborhood:?} repeats an offset"
+        );
+        assert!(
+            !offsets.contains(&(0, 0)),
+            "{neighborhood:?} includes the cell"
+        );
+        let mut expected = expected.to_vec();
+        expected.sort_unstable();
+        assert_eq!(sorted, expected, "{neighborhood:?}");
+    }
+
+    // Every offset within `radius` whose Chebyshev distance is at least `inner`
+    fn square_shell(inner: isize, radius: isize) -> Vec<(isize, isize)> {
+        (-radius..=radius)
+            .flat_map(|dr| (-radius..=radius).map(move |dc| (dr, dc)))
+            .filter(|&(dr, dc)| dr.abs().max(dc.abs()) >= inner)
+            .collect()
+    }
+
+    #[test]
+    fn fixed_neighborhood_offsets() {
+        assert_offsets(
+            Neighborhood::VonNeumann,
+            &[(-1, 0), (0, -1), (0, 1), (1, 0)],
+        );
+        assert_offsets(Neighborhood::Moore, &square_shell(1, 1));
+        assert_offsets(Neighborhood::Margolus, &square_shell(1, 1));
+        assert_offsets(Neighborhood::ExtendedMoore, &square_shell(1, 2));
+        assert_eq!(Neighborhood::VonNeumann.offsets().len(), 4);
+        assert_eq!(Neighborhood::Moore.offsets().len(), 8);
+        assert_eq!(Neighborhood::ExtendedMoore.offsets().len(), 24);
+    }
+
+    #[test]
+    fn parameterized_neighborhood_offsets() {
+        // Shell of distance 2 to 3: 7 * 7 - 3 * 3 cells
+        let ring = square_shell(2, 3);
+        assert_eq!(ring.len(), 40);
+        assert_offsets(Neighborhood::Ring { inner: 2, outer: 3 }, &ring);
+        assert_offsets(
+            Neighborhood::Ring { inner: 1, outer: 1 },
+            &square_shell(1, 1),
+        );
+        assert_offsets(Neighborhood::MooreRadius(3), &square_shell(1, 3));
+        assert_eq!(Neighborhood::MooreRadius(3).offsets().len(), 48);
+    }
+
+    // Each state's share of a large random grid is close to its share of the weights,
+    // and a state without weight never appears
+    #[test]
+    fn new_with_rng_follows_weights() {
+        let states: Vec<CAState> = [(0, 1), (1, 3), (2, 6), (3, 0)]
+            .into_iter()
+            .map(|(id, weight)| CAState {
+                id,
+                name: format!("S{}", id),
+                color: iced::Color::BLACK,
+                weight,
+            })
+            .collect();
+        let (width, height) = (300, 300);
+        let grid = CAGrid::new_with_rng(
+            width,
+            height,
+            states,
+            Neighborhood::Moore,
+            0,
+            &mut StdRng::seed_from_u64(7),
+        );
+
+        let population = grid.population();
+        let total = (width * height) as f64;
+        for (id, weight) in [(0, 1.0), (1, 3.0), (2, 6.0)] {
+            let share = population[id] as f64 / total;
+            let expected = weight / 10.0;
+            assert!(
+                (share - expected).abs() < 0.01,
+                "state {id}: share {share:.4}, expected {expected}"
+            );
+        }
+        assert_eq!(population[3], 0);
+    }
+
+    #[test]
+    fn random_disc_follows_seed() {
+        let disc = |seed| {
+            let mut grid = CAGrid {
+                width: 30,
+                height: 30,
+                cells: vec![vec![0; 30]; 30],
+                neighborhood: Neighborhood::Moore,
+                boundary: BoundaryMode::Bounded,
+            };
+            grid.apply_seed_pattern(
+                SeedPattern::RandomDisc,
+                0,
+                1,
+                &mut StdRng::seed_from_u64(seed),
+            );
+            grid.cells
+        };
+        assert_eq!(disc(3), disc(3));
+        assert_ne!(disc(3), disc(4));
+    }
+
+    // Radius 8 sees 17 * 17 - 1 = 288 cells, more than a u8 count could hold
+    #[test]
+    fn large_radius_counts_past_255() {
+        let grid = CAGrid {
+            width: 17,
+            height: 17,
+            cells: vec![vec![1; 17]; 17],
+            neighborhood: Neighborhood::MooreRadius(8),
+            boundary: BoundaryMode::Bounded,
+        };
+        let offsets = grid.neighborhood.offsets();
+        assert_eq!(offsets.len(), 288);
+        assert_eq!(
+            grid.count_neighbors_masked(&offsets, 8, 8, 1, DirectionMask::ALL),
+            288
+        );
+        let mut counts = NeighborCounts::default();
+        counts.refresh(&grid, 2);
+        assert_eq!(counts.counts[1][8 * 17 + 8], 288);
+    }
+
+    #[test]
+    fn validate_rejects_radius_beyond_cap() {
+        let mut grid = CAGrid {
+            width: 1,
+            height: 1,
+            cells: vec![vec![0]],
+            neighborhood: Neighborhood::MooreRadius(MAX_MOORE_RADIUS),
+            boundary: BoundaryMode::Bounded,
+        };
+        assert!(grid.validate_dimensions().is_ok());
+        grid.neighborhood = Neighborhood::MooreRadius(200);
+        assert!(grid.validate_dimensions().is_err());
+        grid.neighborhood = Neighborhood::Ring {
+            inner: 1,
+            outer: MAX_RING_RADIUS + 1,
+        };
+        assert!(grid.validate_dimensions().is_err());
     }
 }
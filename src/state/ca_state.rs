@@ -1,13 +1,45 @@
-#[derive(Debug, Clone, PartialEq)]
+use crate::state::transition_rule::{NOT_SELF_ID, NOT_SELF_NAME};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CAState {
     pub id: u8,
     pub name: String,
+    #[serde(with = "rgba8")]
     pub color: iced::Color,
     pub weight: u8,
 }
 
+impl CAState {
+    // Stand-in offered next to the real states when picking what a condition counts
+    pub fn not_self() -> Self {
+        CAState {
+            id: NOT_SELF_ID,
+            name: NOT_SELF_NAME.to_string(),
+            color: iced::Color::TRANSPARENT,
+            weight: 0,
+        }
+    }
+}
+
 impl std::fmt::Display for CAState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} (ID: {})", self.name, self.id)
     }
 }
+
+// iced::Color isn't serializable, so it is stored as [r, g, b, a] bytes
+mod rgba8 {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &iced::Color, serializer: S) -> Result<S::Ok, S::Error> {
+        color.into_rgba8().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<iced::Color, D::Error> {
+        let [r, g, b, a] = <[u8; 4]>::deserialize(deserializer)?;
+        Ok(iced::Color::from_rgba8(r, g, b, a as f32 / 255.0))
+    }
+}
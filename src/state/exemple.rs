@@ -1,3 +1,8 @@
+use crate::state::ca_grid::{Direction, DirectionMask};
+use crate::state::transition_rule::{RelationalOperator, TransitionRule};
+use crate::state::CAState;
+use iced::Color;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExampleModel {
     GameOfLife,
@@ -5,16 +10,38 @@ pub enum ExampleModel {
     Greenberg,
     TuringPatterns,
     ForestFire,
+    MajorityVote,
+    LangtonsAnt,
+    BriansBrain,
+    Seeds,
+    HighLife,
+    DayAndNight,
 }
 
 impl ExampleModel {
-    pub const ALL: [ExampleModel; 5] = [
+    pub const ALL: [ExampleModel; 11] = [
         ExampleModel::GameOfLife,
         ExampleModel::Wireworld,
         ExampleModel::Greenberg,
         ExampleModel::TuringPatterns,
         ExampleModel::ForestFire,
+        ExampleModel::MajorityVote,
+        ExampleModel::LangtonsAnt,
+        ExampleModel::BriansBrain,
+        ExampleModel::Seeds,
+        ExampleModel::HighLife,
+        ExampleModel::DayAndNight,
     ];
+
+    // Fixed (birth, survival) neighbor counts of the Life-like variants
+    pub fn life_like_counts(&self) -> Option<(&'static [u16], &'static [u16])> {
+        match self {
+            ExampleModel::Seeds => Some((&[2], &[])),
+            ExampleModel::HighLife => Some((&[3, 6], &[2, 3])),
+            ExampleModel::DayAndNight => Some((&[3, 6, 7, 8], &[3, 4, 6, 7, 8])),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for ExampleModel {
@@ -25,6 +52,369 @@ impl std::fmt::Display for ExampleModel {
             ExampleModel::Greenberg => write!(f, "Greenberg"),
             ExampleModel::TuringPatterns => write!(f, "Turing Patterns"),
             ExampleModel::ForestFire => write!(f, "Forest Fire"),
+            ExampleModel::MajorityVote => write!(f, "Majority Vote"),
+            ExampleModel::LangtonsAnt => write!(f, "Langton's Ant"),
+            ExampleModel::BriansBrain => write!(f, "Brian's Brain"),
+            ExampleModel::Seeds => write!(f, "Seeds (B2/S)"),
+            ExampleModel::HighLife => write!(f, "HighLife (B36/S23)"),
+            ExampleModel::DayAndNight => write!(f, "Day & Night (B3678/S34678)"),
+        }
+    }
+}
+
+// Tunable knobs of the parameterized presets
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForestFireParams {
+    pub burn_out: f32,  // Burning -> Empty
+    pub spread: f32,    // Tree -> Burning next to fire
+    pub growth: f32,    // Empty -> Tree
+    pub lightning: f32, // Tree -> Burning with no fire around (0 disables it)
+}
+
+impl Default for ForestFireParams {
+    fn default() -> Self {
+        ForestFireParams {
+            burn_out: 0.8,
+            spread: 0.5,
+            growth: 0.3,
+            lightning: 0.0,
+        }
+    }
+}
+
+// Single-condition rule `count(neighbor) op threshold`, or unconditional when `condition` is None
+fn simple_rule(
+    current: &CAState,
+    condition: Option<(&CAState, RelationalOperator, u16)>,
+    next: &CAState,
+    probability: f32,
+) -> TransitionRule {
+    let (ids, operators, thresholds, names) = match condition {
+        Some((neighbor, op, threshold)) => (
+            vec![neighbor.id],
+            vec![op],
+            vec![threshold],
+            vec![neighbor.name.clone()],
+        ),
+        None => (vec![], vec![], vec![], vec![]),
+    };
+    TransitionRule {
+        current_state_id: current.id,
+        neighbor_state_id_to_count: ids,
+        operator: operators,
+        neighbor_count_threshold: thresholds,
+        combiner: vec![],
+        next_state_id: next.id,
+        current_state_name: current.name.clone(),
+        neighbor_state_names: names,
+        next_state_name: next.name.clone(),
+        probability,
+        region: None,
+        next_distribution: Vec::new(),
+        next_distribution_names: Vec::new(),
+        groups: Vec::new(),
+        global_condition: None,
+        directions: Vec::new(),
+        prev_state_id: None,
+        prev_state_name: String::new(),
+    }
+}
+
+// Empty (id 0), Tree (id 1) and Burning (id 2), as used by the Forest Fire preset
+pub fn forest_fire_states() -> Vec<CAState> {
+    vec![
+        CAState {
+            id: 0,
+            name: "Empty".into(),
+            color: Color::BLACK,
+            weight: 10,
+        },
+        CAState {
+            id: 1,
+            name: "Tree".into(),
+            color: Color::from_rgb8(0, 200, 0),
+            weight: 7,
+        },
+        CAState {
+            id: 2,
+            name: "Burning".into(),
+            color: Color::from_rgb8(255, 0, 0),
+            weight: 3,
+        },
+    ]
+}
+
+// Forest fire over Empty/Tree/Burning states
+pub fn forest_fire_rules(
+    empty: &CAState,
+    tree: &CAState,
+    burning: &CAState,
+    params: &ForestFireParams,
+) -> Vec<TransitionRule> {
+    let mut rules = vec![
+        simple_rule(burning, None, empty, params.burn_out),
+        simple_rule(
+            tree,
+            Some((burning, RelationalOperator::GreaterOrEqual, 1)),
+            burning,
+            params.spread,
+        ),
+    ];
+    if params.lightning > 0.0 {
+        rules.push(simple_rule(tree, None, burning, params.lightning));
+    }
+    rules.push(simple_rule(empty, None, tree, params.growth));
+    rules
+}
+
+// Red (id 0) and Blue (id 1), as used by the Majority Vote preset
+pub fn majority_vote_states() -> Vec<CAState> {
+    vec![
+        CAState {
+            id: 0,
+            name: "Red".into(),
+            color: Color::from_rgb8(220, 40, 40),
+            weight: 5,
+        },
+        CAState {
+            id: 1,
+            name: "Blue".into(),
+            color: Color::from_rgb8(40, 80, 220),
+            weight: 5,
+        },
+    ]
+}
+
+// Voting automaton for the Moore neighborhood: a cell takes the other state when at
+// least 5 of its 8 neighbors hold it, and keeps its own on a tie or a win. With two
+// states every differing neighbor is a vote for the other one, hence count(!self)
+pub fn majority_vote_rules(states: &[CAState]) -> Vec<TransitionRule> {
+    let not_self = CAState::not_self();
+    let mut rules = Vec::new();
+    for (current, other) in states.iter().zip(states.iter().rev()) {
+        rules.push(simple_rule(
+            current,
+            Some((&not_self, RelationalOperator::GreaterOrEqual, 5)),
+            other,
+            1.0,
+        ));
+        // Explicit, so the outcome doesn't depend on the no-match policy
+        rules.push(simple_rule(current, None, current, 1.0));
+    }
+    rules
+}
+
+// Off (id 0), On (id 1) and Dying (id 2), as used by Brian's Brain
+pub fn brians_brain_states() -> Vec<CAState> {
+    vec![
+        CAState {
+            id: 0,
+            name: "Off".into(),
+            color: Color::BLACK,
+            weight: 10,
+        },
+        CAState {
+            id: 1,
+            name: "On".into(),
+            color: Color::WHITE,
+            weight: 3,
+        },
+        CAState {
+            id: 2,
+            name: "Dying".into(),
+            color: Color::from_rgb8(0, 90, 255),
+            weight: 1,
+        },
+    ]
+}
+
+// Brian's Brain: Off fires with exactly 2 On neighbors, On always starts dying and
+// Dying always turns off
+pub fn brians_brain_rules(off: &CAState, on: &CAState, dying: &CAState) -> Vec<TransitionRule> {
+    vec![
+        simple_rule(off, Some((on, RelationalOperator::Equals, 2)), on, 1.0),
+        simple_rule(on, None, dying, 1.0),
+        simple_rule(dying, None, off, 1.0),
+    ]
+}
+
+// Headings of the ant, in the order of the ant state ids
+pub const ANT_HEADINGS: [Direction; 4] = [Direction::N, Direction::E, Direction::S, Direction::W];
+
+// Id of the ant state heading `ANT_HEADINGS[heading]` over a White (0) or Black (1) square
+pub fn ant_state_id(heading: usize, color_id: u8) -> u8 {
+    2 + heading as u8 * 2 + color_id
+}
+
+// White (id 0), Black (id 1), then an ant state for each heading and square color
+pub fn langtons_ant_states() -> Vec<CAState> {
+    let mut states = vec![
+        CAState {
+            id: 0,
+            name: "White".into(),
+            color: Color::WHITE,
+            weight: 1,
+        },
+        CAState {
+            id: 1,
+            name: "Black".into(),
+            color: Color::BLACK,
+            weight: 0,
+        },
+    ];
+    for (heading, direction) in ANT_HEADINGS.iter().enumerate() {
+        for (color_id, color_name, color) in [
+            (0, "White", Color::from_rgb8(255, 60, 60)),
+            (1, "Black", Color::from_rgb8(150, 0, 0)),
+        ] {
+            states.push(CAState {
+                id: ant_state_id(heading, color_id),
+                name: format!("Ant{}On{}", direction, color_name),
+                color,
+                weight: 0,
+            });
+        }
+    }
+    states
+}
+
+// Langton's Ant as a plain automaton. The ant's square flips color as it leaves, and
+// the square it moves into picks it up by reading the one neighbor the ant can come
+// from: an ant turns right on White and left on Black, then steps forward, so a
+// square becomes an ant heading D when the neighbor behind it, opposite D, holds an
+// ant that turns to face D. Needs a neighborhood holding the 4 orthogonal neighbors
+pub fn langtons_ant_rules(states: &[CAState]) -> Vec<TransitionRule> {
+    let state = |id: u8| &states[id as usize];
+    let mut rules = Vec::new();
+
+    for heading in 0..ANT_HEADINGS.len() {
+        for color_id in 0..2 {
+            let ant = state(ant_state_id(heading, color_id));
+            rules.push(simple_rule(ant, None, state(1 - color_id), 1.0));
         }
     }
+
+    for square_id in 0..2 {
+        let square = state(square_id);
+        for (heading, direction) in ANT_HEADINGS.iter().enumerate() {
+            for color_id in 0..2 {
+                let turn = if color_id == 0 { 2 } else { 6 };
+                let faced = direction.turned(turn);
+                let arriving = ANT_HEADINGS.iter().position(|&d| d == faced).unwrap_or(0);
+
+                let ant = state(ant_state_id(heading, color_id));
+                let mut rule = simple_rule(
+                    square,
+                    Some((ant, RelationalOperator::GreaterOrEqual, 1)),
+                    state(ant_state_id(arriving, square_id)),
+                    1.0,
+                );
+                rule.directions = vec![DirectionMask::only(faced.opposite())];
+                rules.push(rule);
+            }
+        }
+        // Explicit, so the outcome doesn't depend on the no-match policy
+        rules.push(simple_rule(square, None, square, 1.0));
+    }
+    rules
+}
+
+// Dead (id 0) and Alive (id 1), as used by Game of Life and other Life-like rules
+pub fn life_like_states() -> Vec<CAState> {
+    vec![
+        CAState {
+            id: 0,
+            name: "Dead".into(),
+            color: Color::BLACK,
+            weight: 5,
+        },
+        CAState {
+            id: 1,
+            name: "Alive".into(),
+            color: Color::from_rgb8(0, 255, 0),
+            weight: 5,
+        },
+    ]
+}
+
+// Life-like automaton: a dead cell is born with exactly one of `birth` live
+// neighbors, a live cell survives with one of `survival`, and dies otherwise
+pub fn life_like_rules(
+    dead: &CAState,
+    alive: &CAState,
+    birth: &[u16],
+    survival: &[u16],
+) -> Vec<TransitionRule> {
+    let mut rules: Vec<TransitionRule> = survival
+        .iter()
+        .map(|&n| {
+            simple_rule(
+                alive,
+                Some((alive, RelationalOperator::Equals, n)),
+                alive,
+                1.0,
+            )
+        })
+        .collect();
+    rules.push(simple_rule(alive, None, dead, 1.0));
+    rules.extend(birth.iter().map(|&n| {
+        simple_rule(
+            dead,
+            Some((alive, RelationalOperator::Equals, n)),
+            alive,
+            1.0,
+        )
+    }));
+    rules
+}
+
+// Parses B/S notation such as "B3/S23", "b36/s23" or "B2/S" into (birth, survival)
+// counts. Each digit is one count; use commas for counts above 9 ("B3,10/S2,3")
+pub fn parse_life_like(notation: &str) -> Result<(Vec<u16>, Vec<u16>), String> {
+    let notation = notation.trim().to_ascii_uppercase();
+    let (birth, survival) = notation
+        .split_once('/')
+        .ok_or_else(|| "Expected B.../S... separated by '/'".to_string())?;
+    let birth = birth
+        .trim()
+        .strip_prefix('B')
+        .ok_or_else(|| "Birth part must start with 'B'".to_string())?;
+    let survival = survival
+        .trim()
+        .strip_prefix('S')
+        .ok_or_else(|| "Survival part must start with 'S'".to_string())?;
+
+    let counts = |part: &str| {
+        if part.contains(',') {
+            parse_count_list(part)
+        } else {
+            let mut counts = part
+                .chars()
+                .map(|c| {
+                    c.to_digit(10)
+                        .map(|d| d as u16)
+                        .ok_or_else(|| format!("'{}' is not a neighbor count", c))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            counts.sort_unstable();
+            counts.dedup();
+            Ok(counts)
+        }
+    };
+    Ok((counts(birth)?, counts(survival)?))
+}
+
+// Parses a neighbor count list such as "2,3" or "2 3"; an empty list is allowed
+pub fn parse_count_list(input: &str) -> Result<Vec<u16>, String> {
+    let mut counts: Vec<u16> = input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            part.parse::<u16>()
+                .map_err(|_| format!("'{}' is not a neighbor count", part))
+        })
+        .collect::<Result<_, _>>()?;
+    counts.sort_unstable();
+    counts.dedup();
+    Ok(counts)
 }
@@ -1,6 +1,8 @@
 pub mod ca_grid;
 pub mod ca_state;
 pub mod exemple;
+pub mod pattern;
+pub mod session;
 pub mod transition_rule;
 
 pub use ca_state::CAState;
@@ -0,0 +1,227 @@
+use std::fmt;
+
+// Largest width or height a pattern file may declare. Patterns are checked against
+// the grid only once parsed, so this keeps a bogus header from allocating first
+pub const MAX_PATTERN_SIDE: usize = 4096;
+
+// Two-state pattern read from an external file, such as a Life glider or gun
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<Vec<bool>>, // `height` rows of `width` cells, true when alive
+}
+
+impl Pattern {
+    fn dead(width: usize, height: usize) -> Result<Self, String> {
+        if width > MAX_PATTERN_SIDE || height > MAX_PATTERN_SIDE {
+            return Err(format!(
+                "A {}x{} pattern is larger than the {} cell limit per side",
+                width, height, MAX_PATTERN_SIDE
+            ));
+        }
+        Ok(Pattern {
+            width,
+            height,
+            cells: vec![vec![false; width]; height],
+        })
+    }
+}
+
+// Where an imported pattern is stamped on the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternPlacement {
+    Center,
+    TopLeft,
+}
+
+impl PatternPlacement {
+    pub const ALL: [PatternPlacement; 2] = [PatternPlacement::Center, PatternPlacement::TopLeft];
+
+    // (row, col) of the pattern's top-left cell on a grid of the given size
+    pub fn origin(
+        self,
+        pattern: &Pattern,
+        grid_width: usize,
+        grid_height: usize,
+    ) -> (usize, usize) {
+        match self {
+            PatternPlacement::Center => (
+                grid_height.saturating_sub(pattern.height) / 2,
+                grid_width.saturating_sub(pattern.width) / 2,
+            ),
+            PatternPlacement::TopLeft => (0, 0),
+        }
+    }
+}
+
+impl fmt::Display for PatternPlacement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternPlacement::Center => write!(f, "Centered"),
+            PatternPlacement::TopLeft => write!(f, "Top-left corner"),
+        }
+    }
+}
+
+// Parses a run-length encoded pattern: `#` comment lines, an `x = .., y = ..` header,
+// then runs of `b` (dead), `o` (alive) and `$` (end of row), closed by `!`. Letters
+// of multi-state RLE other than `b` count as alive
+pub fn parse_rle(text: &str) -> Result<Pattern, String> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    let header = lines.next().ok_or("The file has no RLE header")?;
+
+    let (mut width, mut height) = (None, None);
+    for field in header.split(',') {
+        let Some((key, value)) = field.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "x" => width = value.parse::<usize>().ok(),
+            "y" => height = value.parse::<usize>().ok(),
+            _ => {} // e.g. rule = B3/S23
+        }
+    }
+    let (Some(width), Some(height)) = (width, height) else {
+        return Err(format!(
+            "Expected an 'x = .., y = ..' header, found '{}'",
+            header
+        ));
+    };
+
+    let mut pattern = Pattern::dead(width, height)?;
+    let (mut row, mut col) = (0usize, 0usize);
+    let mut run = String::new();
+    'body: for line in lines {
+        for c in line.chars() {
+            if c.is_ascii_digit() {
+                run.push(c);
+                continue;
+            }
+            if c.is_whitespace() {
+                continue;
+            }
+            let count = if run.is_empty() {
+                1
+            } else {
+                run.parse::<usize>()
+                    .map_err(|_| format!("Run length '{}' is too large", run))?
+            };
+            run.clear();
+            let outside = || format!("The pattern runs outside its {}x{} header", width, height);
+
+            match c {
+                '!' => break 'body,
+                '$' => {
+                    row = row.checked_add(count).ok_or_else(outside)?;
+                    col = 0;
+                }
+                'b' | '.' => col = col.checked_add(count).ok_or_else(outside)?,
+                c if c.is_ascii_alphabetic() => {
+                    let end = col.checked_add(count).filter(|&end| end <= width);
+                    let Some(end) = end.filter(|_| row < height) else {
+                        return Err(outside());
+                    };
+                    pattern.cells[row][col..end].fill(true);
+                    col = end;
+                }
+                c => return Err(format!("Unexpected '{}' in the RLE body", c)),
+            }
+        }
+    }
+    Ok(pattern)
+}
+
+// Parses a plaintext `.cells` pattern: `!` comment lines, then one line per row with
+// `O` for alive. Any other character is dead, and short rows are padded with dead cells
+pub fn parse_cells(text: &str) -> Result<Pattern, String> {
+    let rows: Vec<&str> = text
+        .lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.starts_with('!'))
+        .collect();
+    let width = rows
+        .iter()
+        .map(|row| row.chars().count())
+        .max()
+        .unwrap_or(0);
+    if width == 0 {
+        return Err("The file has no pattern rows".into());
+    }
+
+    let mut pattern = Pattern::dead(width, rows.len())?;
+    for (cells, row) in pattern.cells.iter_mut().zip(&rows) {
+        for (cell, c) in cells.iter_mut().zip(row.chars()) {
+            *cell = c == 'O';
+        }
+    }
+    Ok(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(pattern: &Pattern) -> Vec<String> {
+        pattern
+            .cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&alive| if alive { 'O' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rle_glider() {
+        let pattern =
+            parse_rle("#N Glider\n#C a comment\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n")
+                .unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(rows(&pattern), [".O.", "..O", "OOO"]);
+    }
+
+    #[test]
+    fn rle_runs_rows_and_end() {
+        // A run before `$` skips rows, a body may span lines, and `!` ends it
+        let pattern = parse_rle("x = 4, y = 4\n2o\n2$b3o!oooo").unwrap();
+        assert_eq!(rows(&pattern), ["OO..", "....", ".OOO", "...."]);
+    }
+
+    #[test]
+    fn rle_rejects_malformed_input() {
+        assert!(parse_rle("").is_err());
+        assert!(parse_rle("# only a comment").is_err());
+        assert!(parse_rle("x = 3\nooo!").is_err());
+        assert!(parse_rle("x = 2, y = 1\nooo!").is_err());
+        assert!(parse_rle("x = 2, y = 1\n$o!").is_err());
+        assert!(parse_rle("x = 2, y = 1\no?!").is_err());
+        assert!(parse_rle("x = 2, y = 1\n99999999999999999999999o!").is_err());
+        assert!(parse_rle(&format!("x = 2, y = 1\n{}bo!", usize::MAX)).is_err());
+    }
+
+    #[test]
+    fn rle_rejects_oversized_header() {
+        let err = parse_rle("x = 100000, y = 100000\no!").unwrap_err();
+        assert!(err.contains("limit"), "{err}");
+    }
+
+    #[test]
+    fn cells_pads_short_rows() {
+        let pattern = parse_cells("!Name: Glider\n.O\n..O\nOOO\n").unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(rows(&pattern), [".O.", "..O", "OOO"]);
+    }
+
+    #[test]
+    fn cells_rejects_empty_input() {
+        assert!(parse_cells("").is_err());
+        assert!(parse_cells("!only a comment\n").is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use crate::app::simulator::TabId;
+use crate::state::ca_grid::{BoundaryMode, CAGrid, Neighborhood};
+use crate::state::transition_rule::{BlockRule, NoMatchPolicy, RuleEvalMode, TransitionRule};
+use crate::state::CAState;
+use serde::{Deserialize, Serialize};
+
+// Everything needed to restore a modeling session: the model and the grid it runs on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub states: Vec<CAState>,
+    pub rules: Vec<TransitionRule>,
+    pub grid: CAGrid,
+    #[serde(default)]
+    pub block_rules: Vec<BlockRule>,
+    #[serde(default)]
+    pub background_state_id: u8,
+    #[serde(default)]
+    pub no_match_policy: NoMatchPolicy,
+    #[serde(default)]
+    pub rule_eval_mode: RuleEvalMode,
+    // ((row, col), label); a list because JSON keys can't be tuples
+    #[serde(default)]
+    pub annotations: Vec<((usize, usize), String)>,
+    #[serde(default)]
+    pub view: Option<ViewState>,
+    // Tab that was open when the session was saved
+    #[serde(default)]
+    pub active_tab: Option<TabId>,
+}
+
+// The recipe for a run without its results: the model, the grid's shape and the seed
+// its initial cells are regenerated from, instead of the cells themselves
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentConfig {
+    pub states: Vec<CAState>,
+    pub rules: Vec<TransitionRule>,
+    #[serde(default)]
+    pub block_rules: Vec<BlockRule>,
+    pub width: usize,
+    pub height: usize,
+    pub neighborhood: Neighborhood,
+    #[serde(default)]
+    pub boundary: BoundaryMode,
+    #[serde(default)]
+    pub background_state_id: u8,
+    #[serde(default)]
+    pub no_match_policy: NoMatchPolicy,
+    #[serde(default)]
+    pub rule_eval_mode: RuleEvalMode,
+    pub seed: u64,
+    pub simulation_speed_ms: u64,
+}
+
+// Canvas zoom and pan, stored as plain values since the simulator keeps them in `Cell`s
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ViewState {
+    pub zoom: f32,
+    pub offset: (f32, f32),
+}
+
+// A saved grid file: the grid's own fields at the top level, plus the view it was saved with.
+// Files written before the view was stored still load, with `view` left empty
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedGrid {
+    #[serde(flatten)]
+    pub grid: CAGrid,
+    #[serde(default)]
+    pub view: Option<ViewState>,
+}
@@ -1,6 +1,9 @@
+use crate::state::ca_grid::DirectionMask;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RelationalOperator {
     Equals,
     NotEquals,
@@ -20,7 +23,13 @@ impl RelationalOperator {
         RelationalOperator::GreaterOrEqual,
     ];
 
-    pub fn evaluate(&self, count: u8, threshold: u8) -> bool {
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        RelationalOperator::ALL
+            .into_iter()
+            .find(|op| op.to_string() == symbol)
+    }
+
+    pub fn evaluate<T: PartialOrd>(&self, count: T, threshold: T) -> bool {
         match self {
             RelationalOperator::Equals => count == threshold,
             RelationalOperator::NotEquals => count != threshold,
@@ -30,6 +39,18 @@ impl RelationalOperator {
             RelationalOperator::GreaterOrEqual => count >= threshold,
         }
     }
+
+    // Help text shown when hovering the operator picker
+    pub fn description(&self) -> &'static str {
+        match self {
+            RelationalOperator::Equals => "== exactly that many neighbors (e.g. == 3: only 3)",
+            RelationalOperator::NotEquals => "!= any count except that one (e.g. != 3: 0-2, 4+)",
+            RelationalOperator::LessThan => "< fewer than (e.g. < 2: 0 or 1)",
+            RelationalOperator::LessOrEqual => "<= at most (e.g. <= 2: 0, 1 or 2)",
+            RelationalOperator::GreaterThan => "> more than (e.g. > 3: 4 or more)",
+            RelationalOperator::GreaterOrEqual => ">= at least (e.g. >= 3: 3 or more)",
+        }
+    }
 }
 
 impl std::fmt::Display for RelationalOperator {
@@ -49,7 +70,7 @@ impl std::fmt::Display for RelationalOperator {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ConditionCombiner {
     And,
     Or,
@@ -73,16 +94,256 @@ impl ConditionCombiner {
         ConditionCombiner::Or,
         ConditionCombiner::Xor,
     ];
+
+    // Help text shown when hovering the combiner picker
+    pub fn description(&self) -> &'static str {
+        match self {
+            ConditionCombiner::And => "AND: both sides must hold",
+            ConditionCombiner::Or => "OR: at least one side must hold",
+            ConditionCombiner::Xor => "XOR: exactly one side must hold, not both",
+        }
+    }
+}
+
+// Compares a state's population over the whole grid with a threshold. It is checked
+// once per step, so it switches a rule on or off for every cell at the same time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GlobalCondition {
+    pub state_id: u8,
+    pub state_name: String,
+    pub operator: RelationalOperator,
+    pub threshold: u32,
+}
+
+impl GlobalCondition {
+    // `population` is indexed by state id, as from `CAGrid::population`
+    pub fn holds(&self, population: &[u32]) -> bool {
+        let total = population.get(self.state_id as usize).copied().unwrap_or(0);
+        self.operator.evaluate(total, self.threshold)
+    }
+}
+
+impl fmt::Display for GlobalCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "total({}) {} {}",
+            self.state_name, self.operator, self.threshold
+        )
+    }
+}
+
+// Boolean expression over a rule's conditions, by condition index. The children
+// of a group are folded left to right, each joined to the previous one by the
+// combiner written after the previous child's last condition
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionExpr {
+    Condition(usize),
+    Group(Vec<ConditionExpr>),
+}
+
+impl ConditionExpr {
+    fn last_condition(&self) -> Option<usize> {
+        match self {
+            ConditionExpr::Condition(i) => Some(*i),
+            ConditionExpr::Group(children) => children.last().and_then(|c| c.last_condition()),
+        }
+    }
+
+    // `holds` tells whether condition `i` is true; an empty group holds
+    pub fn evaluate(
+        &self,
+        combiners: &[ConditionCombiner],
+        holds: &impl Fn(usize) -> bool,
+    ) -> bool {
+        match self {
+            ConditionExpr::Condition(i) => holds(*i),
+            ConditionExpr::Group(children) => {
+                let mut res = true;
+                let mut previous: Option<&ConditionExpr> = None;
+                for child in children {
+                    let value = child.evaluate(combiners, holds);
+                    match previous {
+                        None => res = value,
+                        Some(prev) => {
+                            let combiner = prev
+                                .last_condition()
+                                .and_then(|i| combiners.get(i))
+                                .unwrap_or(&ConditionCombiner::And);
+                            match combiner {
+                                ConditionCombiner::And => res &= value,
+                                ConditionCombiner::Or => res |= value,
+                                ConditionCombiner::Xor => res ^= value,
+                            }
+                        }
+                    }
+                    previous = Some(child);
+                }
+                res
+            }
+        }
+    }
+}
+
+// Children of the conditions lo..=hi, given the groups inside that span sorted by
+// start, widest first
+fn build_condition_group(lo: usize, hi: usize, groups: &[(usize, usize)]) -> Vec<ConditionExpr> {
+    let mut children = Vec::new();
+    let mut i = lo;
+    let mut g = 0;
+    while i <= hi {
+        if let Some(&(start, end)) = groups.get(g).filter(|&&(start, _)| start == i) {
+            // Groups nested in this one follow it directly in the sorted list
+            let mut inner_end = g + 1;
+            while groups.get(inner_end).is_some_and(|&(s, _)| s <= end) {
+                inner_end += 1;
+            }
+            children.push(ConditionExpr::Group(build_condition_group(
+                start,
+                end,
+                &groups[g + 1..inner_end],
+            )));
+            i = end + 1;
+            g = inner_end;
+        } else {
+            children.push(ConditionExpr::Condition(i));
+            i += 1;
+        }
+    }
+    children
+}
+
+// Groups are (first, last) condition indexes that must fit the `n` conditions
+pub fn validate_groups(groups: &[(usize, usize)], n: usize) -> Result<(), String> {
+    for (i, &(start, end)) in groups.iter().enumerate() {
+        if start > end || end >= n {
+            return Err(format!(
+                "group ({}, {}) is outside the {} condition(s)",
+                start + 1,
+                end + 1,
+                n
+            ));
+        }
+        if groups[..i].contains(&(start, end)) {
+            return Err(format!(
+                "group ({}, {}) is listed twice",
+                start + 1,
+                end + 1
+            ));
+        }
+        // Groups must nest; partly overlapping parentheses have no meaning
+        let crosses = groups[..i].iter().any(|&(s, e)| {
+            (s < start && start <= e && e < end) || (start < s && s <= end && end < e)
+        });
+        if crosses {
+            return Err(format!(
+                "group ({}, {}) partly overlaps another group",
+                start + 1,
+                end + 1
+            ));
+        }
+    }
+    Ok(())
 }
 
+// What happens to a cell when none of the rules for its state fire
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NoMatchPolicy {
+    #[default]
+    Keep,
+    ToBackground,
+}
+
+impl NoMatchPolicy {
+    pub const ALL: [NoMatchPolicy; 2] = [NoMatchPolicy::Keep, NoMatchPolicy::ToBackground];
+
+    pub fn fallback_state(&self, current_state_id: u8, background_state_id: u8) -> u8 {
+        match self {
+            NoMatchPolicy::Keep => current_state_id,
+            NoMatchPolicy::ToBackground => background_state_id,
+        }
+    }
+}
+
+impl fmt::Display for NoMatchPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NoMatchPolicy::Keep => write!(f, "Keep current state"),
+            NoMatchPolicy::ToBackground => write!(f, "Decay to background"),
+        }
+    }
+}
+
+// Which of the rules that fire for a cell decides its next state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RuleEvalMode {
+    #[default]
+    FirstMatch,
+    // Later rules override earlier ones, as if every rule were applied in order
+    LastMatch,
+}
+
+impl RuleEvalMode {
+    pub const ALL: [RuleEvalMode; 2] = [RuleEvalMode::FirstMatch, RuleEvalMode::LastMatch];
+}
+
+impl fmt::Display for RuleEvalMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleEvalMode::FirstMatch => write!(f, "First matching rule"),
+            RuleEvalMode::LastMatch => write!(f, "Last matching rule"),
+        }
+    }
+}
+
+// Inclusive rectangle of cells a rule is limited to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellRegion {
+    pub row_min: usize,
+    pub col_min: usize,
+    pub row_max: usize,
+    pub col_max: usize,
+}
+
+impl CellRegion {
+    // Builds a region from two opposite corners in any order
+    pub fn from_corners(r0: usize, c0: usize, r1: usize, c1: usize) -> Self {
+        CellRegion {
+            row_min: r0.min(r1),
+            col_min: c0.min(c1),
+            row_max: r0.max(r1),
+            col_max: c0.max(c1),
+        }
+    }
+
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        (self.row_min..=self.row_max).contains(&row) && (self.col_min..=self.col_max).contains(&col)
+    }
+}
+
+impl fmt::Display for CellRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({}, {}, {}, {})",
+            self.row_min, self.col_min, self.row_max, self.col_max
+        )
+    }
+}
+
+// Neighbor "state" of a `count(!self)` condition: neighbors in any state other than
+// the cell's own. Real states never get this id
+pub const NOT_SELF_ID: u8 = u8::MAX;
+pub const NOT_SELF_NAME: &str = "!self";
+
 // Represents a single transition rule
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransitionRule {
     pub current_state_id: u8,
 
     pub neighbor_state_id_to_count: Vec<u8>,
     pub operator: Vec<RelationalOperator>,
-    pub neighbor_count_threshold: Vec<u8>,
+    pub neighbor_count_threshold: Vec<u16>,
     pub combiner: Vec<ConditionCombiner>,
     pub probability: f32,
 
@@ -90,9 +351,302 @@ pub struct TransitionRule {
     pub current_state_name: String,
     pub neighbor_state_names: Vec<String>,
     pub next_state_name: String,
+    // Cells outside the region never match this rule; `None` applies everywhere
+    #[serde(default)]
+    pub region: Option<CellRegion>,
+    // Weighted (next state id, probability) outcomes, summing to 1. When empty the
+    // rule always leads to `next_state_id`
+    #[serde(default)]
+    pub next_distribution: Vec<(u8, f32)>,
+    #[serde(default)]
+    pub next_distribution_names: Vec<String>,
+    // Parenthesized (first, last) condition index ranges, evaluated before the
+    // conditions around them. Without groups conditions chain strictly left to right
+    #[serde(default)]
+    pub groups: Vec<(usize, usize)>,
+    // Population gate over the whole grid; `None` never blocks the rule
+    #[serde(default)]
+    pub global_condition: Option<GlobalCondition>,
+    // Directions each condition counts neighbors in. Empty when every condition
+    // counts the whole neighborhood
+    #[serde(default)]
+    pub directions: Vec<DirectionMask>,
+    // State the cell must have had in the previous generation. With no previous
+    // generation (the first step, or right after the grid is edited) it never holds
+    #[serde(default)]
+    pub prev_state_id: Option<u8>,
+    #[serde(default)]
+    pub prev_state_name: String,
 }
 
 impl TransitionRule {
+    // Every condition needs an operator and threshold, joined by n - 1 combiners
+    pub fn validate(&self) -> Result<(), String> {
+        let n = self.neighbor_state_id_to_count.len();
+        if self.operator.len() != n {
+            return Err(format!(
+                "{} condition(s) but {} operator(s)",
+                n,
+                self.operator.len()
+            ));
+        }
+        if self.neighbor_count_threshold.len() != n {
+            return Err(format!(
+                "{} condition(s) but {} threshold(s)",
+                n,
+                self.neighbor_count_threshold.len()
+            ));
+        }
+        if self.combiner.len() != n.saturating_sub(1) {
+            return Err(format!(
+                "{} condition(s) need {} combiner(s), found {}",
+                n,
+                n.saturating_sub(1),
+                self.combiner.len()
+            ));
+        }
+        if !self.directions.is_empty() && self.directions.len() != n {
+            return Err(format!(
+                "{} condition(s) but {} direction mask(s)",
+                n,
+                self.directions.len()
+            ));
+        }
+        validate_groups(&self.groups, n)?;
+        // Outcomes are written out by name, so each needs one
+        if self.next_distribution_names.len() != self.next_distribution.len() {
+            return Err(format!(
+                "{} outcome(s) but {} outcome name(s)",
+                self.next_distribution.len(),
+                self.next_distribution_names.len()
+            ));
+        }
+        if !self.next_distribution.is_empty() {
+            if self
+                .next_distribution
+                .iter()
+                .any(|&(_, p)| !(0.0..=1.0).contains(&p))
+            {
+                return Err("outcome probabilities must be between 0.0 and 1.0".into());
+            }
+            let total: f32 = self.next_distribution.iter().map(|&(_, p)| p).sum();
+            if (total - 1.0).abs() > 0.001 {
+                return Err(format!("outcome probabilities sum to {}, not 1", total));
+            }
+        }
+        Ok(())
+    }
+
+    // Directions condition `i` counts neighbors in
+    pub fn direction_mask(&self, i: usize) -> DirectionMask {
+        self.directions.get(i).copied().unwrap_or_default()
+    }
+
+    // Updates the display names stored for `state_id` after the state is renamed
+    pub fn rename_state(&mut self, state_id: u8, name: &str) {
+        if self.current_state_id == state_id {
+            self.current_state_name = name.to_string();
+        }
+        if self.next_state_id == state_id {
+            self.next_state_name = name.to_string();
+        }
+        for (id, stored) in self
+            .neighbor_state_id_to_count
+            .iter()
+            .zip(self.neighbor_state_names.iter_mut())
+            .chain(
+                self.next_distribution
+                    .iter()
+                    .map(|(id, _)| id)
+                    .zip(self.next_distribution_names.iter_mut()),
+            )
+        {
+            if *id == state_id {
+                *stored = name.to_string();
+            }
+        }
+        if let Some(global) = self
+            .global_condition
+            .as_mut()
+            .filter(|g| g.state_id == state_id)
+        {
+            global.state_name = name.to_string();
+        }
+        if self.prev_state_id == Some(state_id) {
+            self.prev_state_name = name.to_string();
+        }
+    }
+
+    // Whether the rule starts in, leads to, or has any condition on `state_id`
+    pub fn mentions_state(&self, state_id: u8) -> bool {
+        self.current_state_id == state_id
+            || self.next_state_id == state_id
+            || self.neighbor_state_id_to_count.contains(&state_id)
+            || self.next_distribution.iter().any(|&(id, _)| id == state_id)
+            || self
+                .global_condition
+                .as_ref()
+                .is_some_and(|global| global.state_id == state_id)
+            || self.prev_state_id == Some(state_id)
+    }
+
+    // Rewrites the rule for a model where `state_id` no longer exists. Every count of
+    // that state is then 0, so a condition on it always gives the same answer: it is
+    // dropped when that answer leaves the rest of the rule unchanged (true after AND,
+    // false after OR/XOR). Returns false when the rule can't be kept as it behaved
+    // before: it starts in, leads to or requires a previous generation in the state,
+    // or a condition on it would turn the rule into something else (including one
+    // that can never fire)
+    pub fn forget_state(&mut self, state_id: u8) -> bool {
+        if self.current_state_id == state_id
+            || self.next_state_id == state_id
+            || self.next_distribution.iter().any(|&(id, _)| id == state_id)
+            || self.prev_state_id == Some(state_id)
+        {
+            return false;
+        }
+
+        let global_holds = self
+            .global_condition
+            .as_ref()
+            .filter(|global| global.state_id == state_id)
+            .map(|global| global.holds(&[]));
+        match global_holds {
+            Some(false) => return false,
+            Some(true) => self.global_condition = None,
+            None => {}
+        }
+
+        while let Some(i) = self
+            .neighbor_state_id_to_count
+            .iter()
+            .position(|&id| id == state_id)
+        {
+            if self
+                .groups
+                .iter()
+                .any(|&(start, end)| start <= i && i <= end)
+            {
+                return false;
+            }
+            let value = match (self.operator.get(i), self.neighbor_count_threshold.get(i)) {
+                (Some(op), Some(&threshold)) => op.evaluate(0, threshold),
+                _ => false,
+            };
+
+            // Combiner joining this condition to the rest: the one before it, or for
+            // the first condition the one after it
+            let joint = if i > 0 { i - 1 } else { 0 };
+            let neutral = match self.combiner.get(joint) {
+                None => value, // Only condition: the rule keeps firing only if it held
+                Some(ConditionCombiner::And) => value,
+                Some(ConditionCombiner::Or | ConditionCombiner::Xor) => !value,
+            };
+            if !neutral {
+                return false;
+            }
+
+            if joint < self.combiner.len() {
+                self.combiner.remove(joint);
+            }
+            self.neighbor_state_id_to_count.remove(i);
+            if i < self.neighbor_state_names.len() {
+                self.neighbor_state_names.remove(i);
+            }
+            if i < self.operator.len() {
+                self.operator.remove(i);
+            }
+            if i < self.neighbor_count_threshold.len() {
+                self.neighbor_count_threshold.remove(i);
+            }
+            if i < self.directions.len() {
+                self.directions.remove(i);
+            }
+            for (start, end) in &mut self.groups {
+                if *start > i {
+                    *start -= 1;
+                }
+                if *end > i {
+                    *end -= 1;
+                }
+            }
+        }
+        true
+    }
+
+    // Condition expression with the groups applied. Expects a validated rule
+    pub fn condition_tree(&self) -> ConditionExpr {
+        let n = self.neighbor_state_id_to_count.len();
+        if n == 0 {
+            return ConditionExpr::Group(Vec::new());
+        }
+        let mut groups = self.groups.clone();
+        groups.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        groups.dedup();
+        ConditionExpr::Group(build_condition_group(0, n - 1, &groups))
+    }
+
+    // Picks the state a matching cell moves to, sampling `next_distribution` if set.
+    // One roll over the weights, scaled by their total in case it has drifted from 1
+    pub fn sample_next_state<R: Rng + ?Sized>(&self, rng: &mut R) -> u8 {
+        if self.next_distribution.is_empty() {
+            return self.next_state_id;
+        }
+
+        let total: f32 = self.next_distribution.iter().map(|&(_, p)| p).sum();
+        let mut roll = rng.random::<f32>() * total;
+        for &(state_id, p) in &self.next_distribution {
+            if roll < p {
+                return state_id;
+            }
+            roll -= p;
+        }
+        // Rounding can leave a sliver past the last window
+        self.next_distribution
+            .last()
+            .map_or(self.next_state_id, |&(state_id, _)| state_id)
+    }
+
+    // "'A' (0.7), 'B' (0.3)" for weighted rules, "'A'" otherwise
+    pub fn next_as_string(&self) -> String {
+        if self.next_distribution.is_empty() {
+            return format!("'{}'", self.next_state_name);
+        }
+        self.next_distribution
+            .iter()
+            .enumerate()
+            .map(|(i, &(state_id, p))| {
+                let name = self
+                    .next_distribution_names
+                    .get(i)
+                    .cloned()
+                    .unwrap_or_else(|| format!("State {}", state_id));
+                format!("'{}' ({})", name, p)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    // Text-format suffix for the region, empty for global rules
+    pub fn region_as_string(&self) -> String {
+        self.region
+            .map(|region| format!(" IN REGION {}", region))
+            .unwrap_or_default()
+    }
+
+    pub fn global_as_string(&self) -> String {
+        self.global_condition
+            .as_ref()
+            .map(|global| format!(" WHEN {}", global))
+            .unwrap_or_default()
+    }
+
+    pub fn prev_as_string(&self) -> String {
+        self.prev_state_id
+            .map(|_| format!(" AND previous is '{}'", self.prev_state_name))
+            .unwrap_or_default()
+    }
+
     pub fn conditions_as_string(&self) -> String {
         let n = self.neighbor_state_id_to_count.len();
         if n == 0 {
@@ -120,7 +674,18 @@ impl TransitionRule {
                 .map(|t| t.to_string())
                 .unwrap_or("?".to_string());
 
-            let cond = format!("count({}) {} {}", neighbor_name, op, thr);
+            let mask = self.direction_mask(i);
+            let mut cond = if mask == DirectionMask::ALL {
+                format!("count({}) {} {}", neighbor_name, op, thr)
+            } else {
+                format!("count({}@{}) {} {}", neighbor_name, mask, op, thr)
+            };
+            for _ in self.groups.iter().filter(|&&(start, _)| start == i) {
+                cond.insert_str(0, "( ");
+            }
+            for _ in self.groups.iter().filter(|&&(_, end)| end == i) {
+                cond.push_str(" )");
+            }
 
             if i == 0 {
                 parts.push(cond);
@@ -137,3 +702,170 @@ impl TransitionRule {
         parts.join(" ")
     }
 }
+
+// Probability budget of all rules that share the same current state
+#[derive(Debug, Clone)]
+pub struct ProbabilityBudget {
+    pub current_state_name: String,
+    pub rule_count: usize,
+    pub total_probability: f32,
+    // Chance that at least one rule fires when all their conditions hold
+    pub chance_any_fires: f32,
+}
+
+impl ProbabilityBudget {
+    // Rules are tried in order and the first one that passes its roll wins, so
+    // probabilities above 1.0 in total never add up the way users tend to expect
+    pub fn exceeds_one(&self) -> bool {
+        self.rule_count > 1 && self.total_probability > 1.0 + f32::EPSILON
+    }
+}
+
+pub fn probability_budgets(rules: &[TransitionRule]) -> Vec<ProbabilityBudget> {
+    let mut budgets: Vec<(u8, ProbabilityBudget)> = Vec::new();
+
+    for rule in rules {
+        let idx = match budgets
+            .iter()
+            .position(|(id, _)| *id == rule.current_state_id)
+        {
+            Some(idx) => idx,
+            None => {
+                budgets.push((
+                    rule.current_state_id,
+                    ProbabilityBudget {
+                        current_state_name: rule.current_state_name.clone(),
+                        rule_count: 0,
+                        total_probability: 0.0,
+                        chance_any_fires: 0.0,
+                    },
+                ));
+                budgets.len() - 1
+            }
+        };
+
+        let budget = &mut budgets[idx].1;
+        budget.rule_count += 1;
+        budget.total_probability += rule.probability;
+        budget.chance_any_fires = 1.0 - (1.0 - budget.chance_any_fires) * (1.0 - rule.probability);
+    }
+
+    budgets.into_iter().map(|(_, budget)| budget).collect()
+}
+
+// Margolus block rule: a 2x2 block whose cells are `input` becomes `output`. Cells
+// are listed top-left, top-right, bottom-left, bottom-right
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockRule {
+    pub input: [u8; 4],
+    pub output: [u8; 4],
+}
+
+// Billiard-ball model: a lone ball crosses to the opposite corner of its block, and
+// two balls meeting head-on on a diagonal bounce off along the other diagonal.
+// Every other block is left as it is
+pub fn billiard_ball_rules(empty: u8, ball: u8) -> Vec<BlockRule> {
+    let block = |balls: &[usize]| {
+        let mut cells = [empty; 4];
+        for &i in balls {
+            cells[i] = ball;
+        }
+        cells
+    };
+    let mut rules: Vec<BlockRule> = (0..4)
+        .map(|i| BlockRule {
+            input: block(&[i]),
+            output: block(&[3 - i]),
+        })
+        .collect();
+    rules.push(BlockRule {
+        input: block(&[0, 3]),
+        output: block(&[1, 2]),
+    });
+    rules.push(BlockRule {
+        input: block(&[1, 2]),
+        output: block(&[0, 3]),
+    });
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Rule for state 0 with three conditions on states 1, 2 and 3, where the one on
+    // state 2 is "2 == 0" when `holds_without` and "2 > 0" otherwise
+    fn three_condition_rule(holds_without: bool) -> TransitionRule {
+        let middle = if holds_without {
+            RelationalOperator::Equals
+        } else {
+            RelationalOperator::GreaterThan
+        };
+        TransitionRule {
+            current_state_id: 0,
+            neighbor_state_id_to_count: vec![1, 2, 3],
+            operator: vec![
+                RelationalOperator::GreaterOrEqual,
+                middle,
+                RelationalOperator::LessThan,
+            ],
+            neighbor_count_threshold: vec![1, 0, 4],
+            combiner: vec![ConditionCombiner::And, ConditionCombiner::Or],
+            probability: 1.0,
+            next_state_id: 1,
+            current_state_name: "A".into(),
+            neighbor_state_names: vec!["B".into(), "C".into(), "D".into()],
+            next_state_name: "B".into(),
+            region: None,
+            next_distribution: Vec::new(),
+            next_distribution_names: Vec::new(),
+            groups: Vec::new(),
+            global_condition: None,
+            directions: Vec::new(),
+            prev_state_id: None,
+            prev_state_name: String::new(),
+        }
+    }
+
+    #[test]
+    fn forget_state_drops_neutral_condition() {
+        let mut rule = three_condition_rule(true);
+        assert!(rule.forget_state(2));
+        assert_eq!(rule.neighbor_state_id_to_count, vec![1, 3]);
+        assert_eq!(rule.neighbor_state_names, vec!["B", "D"]);
+        assert_eq!(
+            rule.operator,
+            vec![
+                RelationalOperator::GreaterOrEqual,
+                RelationalOperator::LessThan
+            ]
+        );
+        assert_eq!(rule.neighbor_count_threshold, vec![1, 4]);
+        // The AND joining the removed condition goes; the OR now joins 1 and 3
+        assert_eq!(rule.combiner, vec![ConditionCombiner::Or]);
+    }
+
+    #[test]
+    fn forget_state_drops_rule_when_condition_matters() {
+        let mut rule = three_condition_rule(false);
+        assert!(!rule.forget_state(2));
+    }
+
+    #[test]
+    fn validate_rejects_unnamed_outcomes() {
+        let mut rule = three_condition_rule(true);
+        assert!(rule.validate().is_ok());
+        rule.next_distribution = vec![(1, 0.5), (2, 0.5)];
+        rule.next_distribution_names = vec!["B".into()];
+        assert!(rule.validate().is_err());
+        rule.next_distribution_names.push("C".into());
+        assert!(rule.validate().is_ok());
+    }
+
+    #[test]
+    fn forget_state_drops_rule_when_condition_is_grouped() {
+        let mut rule = three_condition_rule(true);
+        rule.groups = vec![(1, 2)];
+        assert!(!rule.forget_state(2));
+    }
+}
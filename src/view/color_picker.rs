@@ -0,0 +1,117 @@
+use crate::messages::Message;
+use iced::widget::canvas::{self, event, Event, Geometry, Path};
+use iced::{mouse, Color, Point, Rectangle, Renderer, Size, Theme};
+
+const HUE_COLUMNS: usize = 36;
+const SHADE_ROWS: usize = 10; // From pale tints at the top to dark shades at the bottom
+
+// Palette of hues across and shades down, with a gray ramp as its last row. Clicking
+// a swatch emits `StateColorPicked` with exactly the color drawn there
+pub struct ColorPicker;
+
+// Color at `hue` (0.0-1.0) and `shade` (0.0-1.0): the top half fades in saturation
+// from white, the bottom half fades in value down to black
+fn palette_color(hue: f32, shade: f32) -> Color {
+    let (saturation, value) = if shade < 0.5 {
+        (shade * 2.0, 1.0)
+    } else {
+        (1.0, 2.0 - shade * 2.0)
+    };
+    hsv(hue, saturation, value)
+}
+
+fn hsv(hue: f32, saturation: f32, value: f32) -> Color {
+    let h = (hue.fract() * 6.0).max(0.0);
+    let c = value * saturation;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = value - c;
+    // Rounded to 8 bits, the precision colors are stored and edited in
+    let [r, g, b, _] = Color::from_rgb(r + m, g + m, b + m).into_rgba8();
+    Color::from_rgb8(r, g, b)
+}
+
+// Color of the swatch at (row, col); the row after the shades is the gray ramp
+fn swatch_color(row: usize, col: usize) -> Color {
+    if row == SHADE_ROWS {
+        let gray = 1.0 - col as f32 / (HUE_COLUMNS - 1) as f32;
+        return hsv(0.0, 0.0, gray);
+    }
+    palette_color(
+        col as f32 / HUE_COLUMNS as f32,
+        (row as f32 + 0.5) / SHADE_ROWS as f32,
+    )
+}
+
+fn swatch_size(bounds: Size) -> Size {
+    Size::new(
+        bounds.width / HUE_COLUMNS as f32,
+        bounds.height / (SHADE_ROWS + 1) as f32,
+    )
+}
+
+impl canvas::Program<Message> for ColorPicker {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event else {
+            return (event::Status::Ignored, None);
+        };
+        let Some(position) = cursor.position_in(bounds) else {
+            return (event::Status::Ignored, None);
+        };
+
+        let swatch = swatch_size(bounds.size());
+        let col = ((position.x / swatch.width) as usize).min(HUE_COLUMNS - 1);
+        let row = ((position.y / swatch.height) as usize).min(SHADE_ROWS);
+        (
+            event::Status::Captured,
+            Some(Message::StateColorPicked(swatch_color(row, col))),
+        )
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        let swatch = swatch_size(bounds.size());
+        for row in 0..=SHADE_ROWS {
+            for col in 0..HUE_COLUMNS {
+                let origin = Point::new(col as f32 * swatch.width, row as f32 * swatch.height);
+                frame.fill(&Path::rectangle(origin, swatch), swatch_color(row, col));
+            }
+        }
+        vec![frame.into_geometry()]
+    }
+
+    fn mouse_interaction(
+        &self,
+        _state: &Self::State,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        if cursor.is_over(bounds) {
+            mouse::Interaction::Crosshair
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
@@ -1,8 +1,116 @@
+use crate::app::simulator::{BorderMode, CanvasTool, TRANSITION_FLASH_STEPS};
 use crate::messages::Message;
 use crate::CASimulator;
 use iced::widget::canvas;
 use iced::widget::canvas::{Geometry, Path, Stroke};
 use iced::{Color, Point, Rectangle, Renderer, Size, Theme, Vector};
+use std::ops::Range;
+
+// Extra cells drawn around the visible window so partially visible edges are never missed
+const CULL_MARGIN: usize = 1;
+
+// Diff overlay: matching cells are darkened, differing ones painted bright magenta
+const DIFF_DIM_FACTOR: f32 = 0.25;
+const DIFF_HIGHLIGHT_COLOR: Color = Color::from_rgb(1.0, 0.0, 1.0);
+
+// Age shading: a cell darkens as it holds its state, reaching the full blend
+// toward black after AGE_SHADING_SPAN steps, so fresh changes stand out
+const AGE_SHADING_SPAN: f32 = 32.0;
+const AGE_SHADING_MAX_BLEND: f32 = 0.75;
+
+// Maps between canvas and grid coordinates. `draw` and `update` both build it with
+// `CASimulator::grid_transform`, so clicks land on the cell drawn under the cursor
+// whatever the canvas size, zoom and pan
+struct GridTransform {
+    cell_size: f32, // Side of a square cell before zooming
+    origin: Vector, // Offset that centers the grid in the canvas, before zooming
+    zoom: f32,
+    offset: Point, // Pan, in screen pixels
+}
+
+impl GridTransform {
+    // Unscaled grid-space point (cells are `cell_size` apart) under a canvas position
+    fn grid_point(&self, position: Point) -> Point {
+        Point::new(
+            (position.x - self.offset.x) / self.zoom - self.origin.x,
+            (position.y - self.offset.y) / self.zoom - self.origin.y,
+        )
+    }
+}
+
+// Range of rows and columns that intersect the viewport after the pan/zoom transform
+fn visible_cell_range(
+    viewport: Size,
+    offset: Point,
+    zoom: f32,
+    cell_size: Size,
+    rows: usize,
+    cols: usize,
+) -> (Range<usize>, Range<usize>) {
+    let axis_range = |screen_len: f32, offset: f32, cell_len: f32, count: usize| {
+        let start = (-offset / zoom / cell_len).floor();
+        let end = ((screen_len - offset) / zoom / cell_len).ceil();
+
+        let start = (start.max(0.0) as usize)
+            .saturating_sub(CULL_MARGIN)
+            .min(count);
+        let end = (end.max(0.0) as usize + CULL_MARGIN).min(count);
+        start..end.max(start)
+    };
+
+    (
+        axis_range(viewport.height, offset.y, cell_size.height, rows),
+        axis_range(viewport.width, offset.x, cell_size.width, cols),
+    )
+}
+
+impl CASimulator {
+    // Cell under `position` (relative to the canvas), undoing the pan and zoom.
+    // `None` in the margin around the centered grid
+    fn cell_at(&self, position: Point, bounds: Rectangle) -> Option<(usize, usize)> {
+        let (row, col) = self.fractional_cell_at(position, bounds);
+        let inside = row >= 0.0
+            && col >= 0.0
+            && (row as usize) < self.grid.height
+            && (col as usize) < self.grid.width;
+        inside.then_some((row as usize, col as usize))
+    }
+
+    // Like `cell_at`, but a position in the margin snaps to the nearest edge cell
+    fn clamped_cell_at(&self, position: Point, bounds: Rectangle) -> (usize, usize) {
+        let (row, col) = self.fractional_cell_at(position, bounds);
+        (
+            (row.max(0.0) as usize).min(self.grid.height.saturating_sub(1)),
+            (col.max(0.0) as usize).min(self.grid.width.saturating_sub(1)),
+        )
+    }
+
+    // (row, col) under `position`, rounded down but not limited to the grid
+    fn fractional_cell_at(&self, position: Point, bounds: Rectangle) -> (f32, f32) {
+        let transform = self.grid_transform(bounds.size());
+        let point = transform.grid_point(position);
+        (
+            (point.y / transform.cell_size).floor(),
+            (point.x / transform.cell_size).floor(),
+        )
+    }
+
+    // Current pan and zoom over square cells centered in a canvas of `size`
+    fn grid_transform(&self, size: Size) -> GridTransform {
+        let cell_size = (size.width / self.grid.width.max(1) as f32)
+            .min(size.height / self.grid.height.max(1) as f32);
+        let origin = Vector::new(
+            (size.width - cell_size * self.grid.width as f32) / 2.0,
+            (size.height - cell_size * self.grid.height as f32) / 2.0,
+        );
+        GridTransform {
+            cell_size,
+            origin,
+            zoom: self.zoom.get().max(0.1),
+            offset: self.offset.get(),
+        }
+    }
+}
 
 impl canvas::Program<Message> for CASimulator {
     fn draw(
@@ -28,44 +136,143 @@ impl canvas::Program<Message> for CASimulator {
             }
 
             frame.with_save(|frame| {
-                let zoom = self.zoom.get().max(0.1);
-                let offset = self.offset.get();
+                let GridTransform {
+                    cell_size,
+                    origin,
+                    zoom,
+                    offset,
+                } = self.grid_transform(frame.size());
 
                 frame.translate(Vector::new(offset.x, offset.y));
                 frame.scale(zoom);
+                frame.translate(origin);
+
+                // Square cells, centered on the canvas
+                let cell_width = cell_size;
+                let cell_height = cell_size;
+                let grid_width = cell_width * self.grid.width as f32;
+                let grid_height = cell_height * self.grid.height as f32;
 
-                let cell_width = frame.width() / self.grid.width as f32;
-                let cell_height = frame.height() / self.grid.height as f32;
+                // Only cells inside the visible window (in grid space) are drawn
+                let (row_range, col_range) = visible_cell_range(
+                    frame.size(),
+                    Point::new(offset.x + origin.x * zoom, offset.y + origin.y * zoom),
+                    zoom,
+                    Size::new(cell_width, cell_height),
+                    self.grid.height,
+                    self.grid.width,
+                );
 
-                for r in 0..self.grid.height {
-                    for c in 0..self.grid.width {
+                // Diff overlay only when the comparison grid lines up cell for cell
+                let comparison = self
+                    .comparison_grid
+                    .as_ref()
+                    .filter(|_| self.show_comparison_diff)
+                    .filter(|other| {
+                        other.width == self.grid.width && other.height == self.grid.height
+                    });
+
+                for r in row_range.clone() {
+                    for c in col_range.clone() {
                         let state_id = self.grid.cells[r][c];
-                        let cell_color = self
+                        let mut cell_color = self
                             .states
                             .iter()
                             .find(|s| s.id == state_id)
                             .map_or(Color::new(1.0, 0.0, 0.0, 1.0), |s| s.color);
 
+                        if self.show_age_shading {
+                            let age = self
+                                .cell_ages
+                                .get(r * self.grid.width + c)
+                                .copied()
+                                .unwrap_or(0);
+                            let blend =
+                                (age as f32 / AGE_SHADING_SPAN).min(1.0) * AGE_SHADING_MAX_BLEND;
+                            cell_color = Color::from_rgb(
+                                cell_color.r * (1.0 - blend),
+                                cell_color.g * (1.0 - blend),
+                                cell_color.b * (1.0 - blend),
+                            );
+                        }
+
+                        if let Some(other) = comparison {
+                            cell_color = if other.cells[r][c] == state_id {
+                                Color::from_rgb(
+                                    cell_color.r * DIFF_DIM_FACTOR,
+                                    cell_color.g * DIFF_DIM_FACTOR,
+                                    cell_color.b * DIFF_DIM_FACTOR,
+                                )
+                            } else {
+                                DIFF_HIGHLIGHT_COLOR
+                            };
+                        }
+
                         let top_left = Point::new(c as f32 * cell_width, r as f32 * cell_height);
                         let size = Size::new(cell_width, cell_height);
 
                         frame.fill_rectangle(top_left, size, cell_color);
+
+                        // Fading border on cells that recently changed state
+                        let flash = if self.show_transition_flashes {
+                            self.transition_flash
+                                .get(r * self.grid.width + c)
+                                .copied()
+                                .unwrap_or(0)
+                        } else {
+                            0
+                        };
+                        if flash > 0 {
+                            let alpha = flash as f32 / TRANSITION_FLASH_STEPS as f32;
+                            let flash_color = Color::from_rgba(
+                                1.0 - cell_color.r,
+                                1.0 - cell_color.g,
+                                1.0 - cell_color.b,
+                                alpha,
+                            );
+                            frame.stroke(
+                                &Path::rectangle(top_left, size),
+                                Stroke::default()
+                                    .with_width((cell_width.min(cell_height) * 0.15).max(0.5))
+                                    .with_color(flash_color),
+                            );
+                        }
+                    }
+                }
+
+                // Annotation labels, kept at a constant on-screen size
+                for (&(r, c), label) in &self.annotations {
+                    if !row_range.contains(&r) || !col_range.contains(&c) {
+                        continue;
                     }
+                    frame.fill_text(canvas::Text {
+                        content: label.clone(),
+                        position: Point::new(c as f32 * cell_width, r as f32 * cell_height),
+                        color: Color::WHITE,
+                        size: iced::Pixels(12.0 / zoom),
+                        ..Default::default()
+                    });
                 }
 
-                let min_cell_pixels = 1.5;
+                // Frame coordinates are scaled by `zoom`, so screen widths are divided by it
+                let stroke_width = match self.border_mode {
+                    BorderMode::ScaleWithZoom => (self.border_width / zoom)
+                        .clamp(self.border_width / 3.0, self.border_width * 2.0),
+                    BorderMode::FixedScreen => self.border_width / zoom,
+                };
+                // Lines are left out once they would cover too much of each cell
+                let min_cell_pixels = (stroke_width * zoom * 3.0).max(1.5);
                 let draw_horizontal = cell_height * zoom >= min_cell_pixels;
                 let draw_vertical = cell_width * zoom >= min_cell_pixels;
 
-                if draw_horizontal || draw_vertical {
-                    let stroke_width = (1.5 / zoom).clamp(0.5, 3.0);
+                if stroke_width > 0.0 && (draw_horizontal || draw_vertical) {
                     let stroke_color = Color::from_rgb(0.2, 0.2, 0.2);
 
                     // Linhas horizontais
                     if draw_horizontal {
-                        for r in 0..=self.grid.height {
+                        for r in row_range.start..=row_range.end {
                             let y = r as f32 * cell_height;
-                            let path = Path::line(Point::new(0.0, y), Point::new(frame.width(), y));
+                            let path = Path::line(Point::new(0.0, y), Point::new(grid_width, y));
                             frame.stroke(
                                 &path,
                                 Stroke::default()
@@ -76,10 +283,9 @@ impl canvas::Program<Message> for CASimulator {
                     }
 
                     if draw_vertical {
-                        for c in 0..=self.grid.width {
+                        for c in col_range.start..=col_range.end {
                             let x = c as f32 * cell_width;
-                            let path =
-                                Path::line(Point::new(x, 0.0), Point::new(x, frame.height()));
+                            let path = Path::line(Point::new(x, 0.0), Point::new(x, grid_height));
                             frame.stroke(
                                 &path,
                                 Stroke::default()
@@ -106,12 +312,43 @@ impl canvas::Program<Message> for CASimulator {
     ) -> (canvas::event::Status, Option<Message>) {
         if let canvas::Event::Mouse(mouse_event) = event {
             match mouse_event {
-                iced::mouse::Event::ButtonPressed(iced::mouse::Button::Left) => {
+                // Only presses that start on the canvas begin a paint stroke
+                iced::mouse::Event::ButtonPressed(iced::mouse::Button::Left)
+                    if cursor.is_over(bounds) =>
+                {
+                    // The Line tool works on single clicks, never on drags
+                    if self.canvas_tool == CanvasTool::Line {
+                        let Some(position) = cursor.position_in(bounds) else {
+                            return (canvas::event::Status::Ignored, None);
+                        };
+                        let (row, col) = self.clamped_cell_at(position, bounds);
+                        let message = match self.line_anchor {
+                            Some((r0, c0)) => Message::PaintLine(r0, c0, row, col),
+                            None => Message::LineAnchorSet(row, col),
+                        };
+                        return (canvas::event::Status::Captured, Some(message));
+                    }
+                    if self.canvas_tool == CanvasTool::Rectangle {
+                        let start = cursor
+                            .position_in(bounds)
+                            .map(|position| self.clamped_cell_at(position, bounds));
+                        self.rect_start.set(start);
+                        return (canvas::event::Status::Captured, None);
+                    }
                     self.mouse_pressed.set(true);
                 }
                 iced::mouse::Event::ButtonReleased(iced::mouse::Button::Left) => {
                     self.mouse_pressed.set(false);
                     *self.last_painted_cell.borrow_mut() = None;
+
+                    // Released anywhere, even off the canvas: the corner is clamped to the grid
+                    let end = cursor
+                        .position_from(bounds.position())
+                        .map(|position| self.clamped_cell_at(position, bounds));
+                    if let (Some((r0, c0)), Some((r1, c1))) = (self.rect_start.take(), end) {
+                        let message = Message::PaintRect(r0, c0, r1, c1, self.rect_filled);
+                        return (canvas::event::Status::Captured, Some(message));
+                    }
                 }
 
                 iced::mouse::Event::ButtonPressed(iced::mouse::Button::Right) => {
@@ -149,6 +386,11 @@ impl canvas::Program<Message> for CASimulator {
                 }
 
                 iced::mouse::Event::CursorMoved { position } => {
+                    let hovered = cursor
+                        .position_in(bounds)
+                        .and_then(|position| self.cell_at(position, bounds));
+                    let hover_changed = self.hovered_cell.replace(hovered) != hovered;
+
                     if self.right_mouse_pressed.get() {
                         self.grid_cache.clear();
                         let mut offset = self.offset.get();
@@ -164,6 +406,12 @@ impl canvas::Program<Message> for CASimulator {
                     } else {
                         *self.last_mouse_pos.borrow_mut() = Some(position);
                     }
+                    if hover_changed && !self.mouse_pressed.get() {
+                        return (canvas::event::Status::Ignored, Some(Message::CellHovered));
+                    }
+                }
+                iced::mouse::Event::CursorLeft if self.hovered_cell.take().is_some() => {
+                    return (canvas::event::Status::Ignored, Some(Message::CellHovered));
                 }
 
                 _ => {}
@@ -173,29 +421,28 @@ impl canvas::Program<Message> for CASimulator {
         if self.mouse_pressed.get()
             && let Some(position) = cursor.position_in(bounds)
         {
-            let offset = self.offset.get();
-            let adjusted_x = (position.x - offset.x) / self.zoom.get();
-            let adjusted_y = (position.y - offset.y) / self.zoom.get();
-
-            let cell_width = bounds.width / self.grid.width as f32;
-            let cell_height = bounds.height / self.grid.height as f32;
-
-            let col = (adjusted_x / cell_width) as usize;
-            let row = (adjusted_y / cell_height) as usize;
-
-            if row < self.grid.height && col < self.grid.width {
+            // Clicks in the margin around the centered grid land outside it
+            if let Some((row, col)) = self.cell_at(position, bounds) {
                 let mut last = self.last_painted_cell.borrow_mut();
                 if last.is_none() || last.unwrap() != (row, col) {
                     *last = Some((row, col));
-                    return (
-                        canvas::event::Status::Captured,
-                        Some(Message::PaintCell(row, col, self.selected_paint_state_id)),
-                    );
+                    let message = match self.canvas_tool {
+                        CanvasTool::Paint => {
+                            Message::PaintCell(row, col, self.selected_paint_state_id)
+                        }
+                        CanvasTool::Annotate => Message::AnnotationCellSelected(row, col),
+                        CanvasTool::Poke => Message::PokeCell(row, col),
+                        CanvasTool::Line | CanvasTool::Rectangle => {
+                            return (canvas::event::Status::Ignored, None);
+                        }
+                    };
+                    return (canvas::event::Status::Captured, Some(message));
                 }
             }
         }
 
-        self.grid_cache.clear();
+        // Anything else (hovering, releasing a button) leaves the picture as it is;
+        // paint strokes clear the cache in the handler of the message they send
         (canvas::event::Status::Ignored, None)
     }
 
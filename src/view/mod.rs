@@ -1,2 +1,5 @@
+pub mod color_picker;
 pub mod grid_view;
+pub mod modal;
+pub mod population_chart;
 pub mod tabs;
@@ -0,0 +1,312 @@
+use crate::app::simulator::Modal;
+use crate::messages::Message;
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::overlay;
+use iced::advanced::renderer;
+use iced::advanced::widget::{self, Tree, Widget};
+use iced::advanced::{Clipboard, Shell};
+use iced::event::{self, Event};
+use iced::mouse;
+use iced::widget::{button, column, container, row, text};
+use iced::{theme, Alignment, Color, Element, Length, Point, Rectangle, Size, Vector};
+
+// Shade drawn over the window behind a dialog
+const BACKDROP: Color = Color {
+    a: 0.7,
+    ..Color::BLACK
+};
+
+// Shows `dialog` centered over `base`, which stays visible but no longer receives the mouse
+pub struct ModalLayer<'a, Message, Theme, Renderer> {
+    base: Element<'a, Message, Theme, Renderer>,
+    dialog: Element<'a, Message, Theme, Renderer>,
+}
+
+impl<'a, Message, Theme, Renderer> ModalLayer<'a, Message, Theme, Renderer> {
+    pub fn new(
+        base: impl Into<Element<'a, Message, Theme, Renderer>>,
+        dialog: impl Into<Element<'a, Message, Theme, Renderer>>,
+    ) -> Self {
+        Self {
+            base: base.into(),
+            dialog: dialog.into(),
+        }
+    }
+}
+
+impl<Message, Theme, Renderer> Widget<Message, Theme, Renderer>
+    for ModalLayer<'_, Message, Theme, Renderer>
+where
+    Renderer: iced::advanced::Renderer,
+{
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.base), Tree::new(&self.dialog)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&[&self.base, &self.dialog]);
+    }
+
+    fn size(&self) -> Size<Length> {
+        self.base.as_widget().size()
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        self.base
+            .as_widget()
+            .layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        self.base.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.base.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor,
+            viewport,
+        );
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        _renderer: &Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        Some(overlay::Element::new(Box::new(DialogOverlay {
+            position: layout.position() + translation,
+            size: layout.bounds().size(),
+            dialog: &mut self.dialog,
+            tree: &mut tree.children[1],
+        })))
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.base.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout,
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn widget::Operation<Message>,
+    ) {
+        self.base
+            .as_widget()
+            .operate(&mut tree.children[0], layout, renderer, operation);
+    }
+}
+
+// Covers the whole base widget, so clicks outside the dialog never reach what is below
+struct DialogOverlay<'a, 'b, Message, Theme, Renderer> {
+    position: Point,
+    size: Size,
+    dialog: &'b mut Element<'a, Message, Theme, Renderer>,
+    tree: &'b mut Tree,
+}
+
+impl<Message, Theme, Renderer> overlay::Overlay<Message, Theme, Renderer>
+    for DialogOverlay<'_, '_, Message, Theme, Renderer>
+where
+    Renderer: iced::advanced::Renderer,
+{
+    fn layout(&mut self, renderer: &Renderer, _bounds: Size) -> layout::Node {
+        let limits = layout::Limits::new(Size::ZERO, self.size)
+            .width(Length::Fill)
+            .height(Length::Fill);
+        let dialog = self
+            .dialog
+            .as_widget()
+            .layout(self.tree, renderer, &limits)
+            .align(Alignment::Center, Alignment::Center, limits.max());
+
+        layout::Node::with_children(self.size, vec![dialog]).move_to(self.position)
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+    ) -> event::Status {
+        let status = self.dialog.as_widget_mut().on_event(
+            self.tree,
+            event.clone(),
+            layout.children().next().unwrap(),
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            &layout.bounds(),
+        );
+        match event {
+            Event::Mouse(_) => event::Status::Captured,
+            _ => status,
+        }
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+    ) {
+        renderer.fill_quad(
+            renderer::Quad {
+                bounds: layout.bounds(),
+                ..renderer::Quad::default()
+            },
+            BACKDROP,
+        );
+        self.dialog.as_widget().draw(
+            self.tree,
+            renderer,
+            theme,
+            style,
+            layout.children().next().unwrap(),
+            cursor,
+            &layout.bounds(),
+        );
+    }
+
+    fn operate(
+        &mut self,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn widget::Operation<Message>,
+    ) {
+        self.dialog.as_widget().operate(
+            self.tree,
+            layout.children().next().unwrap(),
+            renderer,
+            operation,
+        );
+    }
+
+    fn mouse_interaction(
+        &self,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.dialog.as_widget().mouse_interaction(
+            self.tree,
+            layout.children().next().unwrap(),
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn overlay<'c>(
+        &'c mut self,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+    ) -> Option<overlay::Element<'c, Message, Theme, Renderer>> {
+        self.dialog.as_widget_mut().overlay(
+            self.tree,
+            layout.children().next().unwrap(),
+            renderer,
+            Vector::ZERO,
+        )
+    }
+}
+
+impl<'a, Message, Theme, Renderer> From<ModalLayer<'a, Message, Theme, Renderer>>
+    for Element<'a, Message, Theme, Renderer>
+where
+    Message: 'a,
+    Theme: 'a,
+    Renderer: 'a + iced::advanced::Renderer,
+{
+    fn from(modal: ModalLayer<'a, Message, Theme, Renderer>) -> Self {
+        Element::new(modal)
+    }
+}
+
+// The box drawn for `modal`, with its answer buttons
+pub fn dialog(modal: &Modal) -> Element<'_, Message> {
+    let (title, message) = match modal {
+        Modal::Info { title, message } | Modal::Confirm { title, message, .. } => (title, message),
+    };
+    let buttons = match modal {
+        Modal::Info { .. } => row![button("OK").on_press(Message::ModalOk).padding(5)],
+        Modal::Confirm { .. } => row![
+            button("Cancel")
+                .on_press(Message::ModalCancel)
+                .style(theme::Button::Secondary)
+                .padding(5),
+            button("OK").on_press(Message::ModalOk).padding(5),
+        ],
+    };
+
+    container(
+        column![text(title).size(20), text(message), buttons.spacing(10)]
+            .spacing(15)
+            .align_items(Alignment::End),
+    )
+    .padding(20)
+    .max_width(450)
+    .style(theme::Container::Box)
+    .into()
+}
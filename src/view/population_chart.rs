@@ -0,0 +1,63 @@
+use crate::messages::Message;
+use crate::state::CAState;
+use iced::widget::canvas::{self, Geometry, Path, Stroke};
+use iced::{mouse, Color, Point, Rectangle, Renderer, Theme};
+use std::collections::VecDeque;
+
+const AXIS_COLOR: Color = Color::from_rgb(0.4, 0.4, 0.4);
+
+// Line chart of the population of each state over the recent generations, one line
+// per state in its own color. Generations enter on the right once `capacity` are shown
+pub struct PopulationChart<'a> {
+    pub history: &'a VecDeque<Vec<usize>>, // Cells per state id, oldest first
+    pub states: &'a [CAState],
+    pub capacity: usize,
+    pub total_cells: usize,
+}
+
+impl canvas::Program<Message> for PopulationChart<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        let (width, height) = (frame.width(), frame.height());
+
+        let axes = Path::new(|p| {
+            p.move_to(Point::ORIGIN);
+            p.line_to(Point::new(0.0, height));
+            p.line_to(Point::new(width, height));
+        });
+        frame.stroke(&axes, Stroke::default().with_color(AXIS_COLOR));
+
+        let x_step = width / self.capacity.saturating_sub(1).max(1) as f32;
+        let y_scale = height / self.total_cells.max(1) as f32;
+        if self.history.len() >= 2 {
+            for state in self.states {
+                let line = Path::new(|p| {
+                    for (i, counts) in self.history.iter().enumerate() {
+                        let count = counts.get(state.id as usize).copied().unwrap_or(0);
+                        let point = Point::new(i as f32 * x_step, height - count as f32 * y_scale);
+                        if i == 0 {
+                            p.move_to(point);
+                        } else {
+                            p.line_to(point);
+                        }
+                    }
+                });
+                frame.stroke(
+                    &line,
+                    Stroke::default().with_width(1.5).with_color(state.color),
+                );
+            }
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
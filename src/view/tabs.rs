@@ -1,25 +1,195 @@
+use crate::app::simulator::{
+    BorderMode, CanvasTool, ExampleParam, ImportMode, StopConditionKind, MAX_BRUSH_SIZE,
+    MAX_POPULATION_HISTORY, MAX_RUN_UNTIL_STEPS,
+};
+use crate::app::sweep::SWEEPABLE_PARAMS;
 use crate::app::CASimulator;
 use crate::messages::Message;
-use crate::state::ca_grid::Neighborhood;
+use crate::state::ca_grid::{
+    BoundaryMode, Direction, Neighborhood, SeedPattern, MAX_MOORE_RADIUS, MAX_RING_RADIUS,
+};
 use crate::state::exemple::ExampleModel;
-use crate::state::transition_rule::{ConditionCombiner, RelationalOperator};
+use crate::state::pattern::PatternPlacement;
+use crate::state::transition_rule::{
+    probability_budgets, ConditionCombiner, NoMatchPolicy, RelationalOperator, RuleEvalMode,
+};
+use crate::state::CAState;
+use crate::view::color_picker::ColorPicker;
+use crate::view::population_chart::PopulationChart;
 use iced::widget::{
-    button, column, row, text, text_input, Canvas, Column, Container, PickList, Scrollable, Slider,
-    Space,
+    button, checkbox, column, container, progress_bar, row, text, text_input, tooltip, Canvas,
+    Column, Container, PickList, Row, Scrollable, Slider, Space,
 };
-use iced::{theme, Alignment, Color, Element, Length};
+use iced::{theme, Alignment, Border, Color, Element, Length, Theme};
+
+const CONTROLS_PANEL_WIDTH: f32 = 480.0;
+
+// Inline validation message shown under the offending field
+fn field_error(err: &Option<String>) -> Option<Element<'_, Message>> {
+    err.as_ref()
+        .map(|e| text(e).size(14).style(Color::from_rgb8(255, 0, 0)).into())
+}
+
+// Text input outlined in red while its value is invalid
+struct InvalidInput;
+
+impl InvalidInput {
+    fn outlined(appearance: text_input::Appearance) -> text_input::Appearance {
+        text_input::Appearance {
+            border: Border {
+                color: Color::from_rgb8(255, 0, 0),
+                ..appearance.border
+            },
+            ..appearance
+        }
+    }
+}
+
+impl text_input::StyleSheet for InvalidInput {
+    type Style = Theme;
+
+    fn active(&self, style: &Theme) -> text_input::Appearance {
+        Self::outlined(style.active(&theme::TextInput::Default))
+    }
+
+    fn focused(&self, style: &Theme) -> text_input::Appearance {
+        Self::outlined(style.focused(&theme::TextInput::Default))
+    }
+
+    fn placeholder_color(&self, style: &Theme) -> Color {
+        style.placeholder_color(&theme::TextInput::Default)
+    }
+
+    fn value_color(&self, style: &Theme) -> Color {
+        style.value_color(&theme::TextInput::Default)
+    }
+
+    fn disabled_color(&self, style: &Theme) -> Color {
+        style.disabled_color(&theme::TextInput::Default)
+    }
+
+    fn selection_color(&self, style: &Theme) -> Color {
+        style.selection_color(&theme::TextInput::Default)
+    }
+
+    fn disabled(&self, style: &Theme) -> text_input::Appearance {
+        Self::outlined(style.disabled(&theme::TextInput::Default))
+    }
+}
+
+// Small square filled with a state's color, shown beside its name
+fn color_swatch<'a>(color: Color) -> Element<'a, Message> {
+    container(Space::new(Length::Fixed(16.0), Length::Fixed(16.0)))
+        .style(container::Appearance {
+            background: Some(color.into()),
+            border: Border {
+                color: Color::from_rgb(0.5, 0.5, 0.5),
+                width: 1.0,
+                radius: 2.0.into(),
+            },
+            ..Default::default()
+        })
+        .into()
+}
+
+// Wraps a form widget with a hover help box
+fn with_help<'a>(content: impl Into<Element<'a, Message>>, help: String) -> Element<'a, Message> {
+    tooltip(
+        content,
+        container(text(help).size(14))
+            .padding(8)
+            .style(theme::Container::Box),
+        tooltip::Position::Top,
+    )
+    .into()
+}
 
 impl CASimulator {
     pub fn view_definition_tab(&self) -> Element<'_, Message> {
+        let param_input = |label: &'static str, value: &str, param: ExampleParam| {
+            row![
+                text(label),
+                text_input("", value)
+                    .on_input(move |val| Message::ExampleParamChanged(param, val))
+                    .padding(3)
+                    .width(Length::Fixed(80.0)),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center)
+        };
+        let example_params: Option<Element<Message>> = match self.selected_example {
+            Some(model @ ExampleModel::ForestFire) => Some(
+                column![
+                    param_input(
+                        "Burn-out probability:",
+                        &self.forest_burn_out_input,
+                        ExampleParam::ForestBurnOut
+                    ),
+                    param_input(
+                        "Spread probability:",
+                        &self.forest_spread_input,
+                        ExampleParam::ForestSpread
+                    ),
+                    param_input(
+                        "Growth probability:",
+                        &self.forest_growth_input,
+                        ExampleParam::ForestGrowth
+                    ),
+                    param_input(
+                        "Lightning probability:",
+                        &self.forest_lightning_input,
+                        ExampleParam::ForestLightning
+                    ),
+                    button("Reload with parameters").on_press(Message::ExampleModelSelected(model)),
+                ]
+                .spacing(5)
+                .into(),
+            ),
+            Some(model @ ExampleModel::GameOfLife) => Some(
+                column![
+                    param_input(
+                        "Birth counts:",
+                        &self.life_birth_input,
+                        ExampleParam::LifeBirth
+                    ),
+                    param_input(
+                        "Survival counts:",
+                        &self.life_survival_input,
+                        ExampleParam::LifeSurvival
+                    ),
+                    button("Reload with parameters").on_press(Message::ExampleModelSelected(model)),
+                ]
+                .spacing(5)
+                .into(),
+            ),
+            _ => None,
+        };
         let model_selector = column![
             text("Load Example Model").size(20),
             PickList::new(
                 ExampleModel::ALL.to_vec(),
-                None::<ExampleModel>,
+                self.selected_example,
                 Message::ExampleModelSelected,
             )
             .placeholder("Select a model"),
         ]
+        .push_maybe(example_params)
+        .push_maybe(field_error(&self.example_params_error))
+        .push(text("Or build a Life-like rule from B/S notation:"))
+        .push(
+            row![
+                text_input("B3/S23", &self.life_like_input)
+                    .on_input(Message::LifeLikeNotationChanged)
+                    .on_submit(Message::ApplyLifeLike(self.life_like_input.clone()))
+                    .padding(5)
+                    .width(Length::Fixed(150.0)),
+                button("Build Rules")
+                    .on_press(Message::ApplyLifeLike(self.life_like_input.clone())),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+        )
+        .push_maybe(field_error(&self.life_like_error))
         .spacing(10)
         .width(Length::Fill);
         let state_creation_panel = column![
@@ -27,6 +197,25 @@ impl CASimulator {
             text_input("State Name (e.g., Alive)", &self.new_state_name)
                 .on_input(Message::StateNameChanged)
                 .padding(5),
+            Canvas::new(ColorPicker)
+                .width(Length::Fixed(360.0))
+                .height(Length::Fixed(88.0)),
+            row![
+                text("Color:"),
+                match self.new_state_color() {
+                    Ok(color) => color_swatch(color),
+                    Err(_) => text("invalid")
+                        .size(14)
+                        .style(Color::from_rgb8(255, 0, 0))
+                        .into(),
+                },
+                checkbox("Enter RGB values", self.show_rgb_fields)
+                    .on_toggle(Message::RgbFieldsToggled),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+        ]
+        .push_maybe(self.show_rgb_fields.then(|| {
             row![
                 text("R:"),
                 text_input("0-255", &self.new_state_color_r)
@@ -45,9 +234,10 @@ impl CASimulator {
                     .width(Length::Fixed(60.0)),
             ]
             .spacing(5)
-            .align_items(Alignment::Center),
-            button("Add State").on_press(Message::AddState).padding(5),
-        ]
+            .align_items(Alignment::Center)
+        }))
+        .push(button("Add State").on_press(Message::AddState).padding(5))
+        .push_maybe(field_error(&self.state_form_error))
         .spacing(10)
         .width(Length::Fill);
 
@@ -57,20 +247,32 @@ impl CASimulator {
                 .spacing(10)
                 .width(Length::Fill)
         } else {
+            let channel_input = |idx: usize, channel: usize, value: u8| {
+                text_input("0", &value.to_string())
+                    .on_input(move |val| Message::StateColorEdited(idx, channel, val))
+                    .padding(5)
+                    .width(Length::Fixed(45.0))
+            };
             let mut column = Column::new().spacing(10).width(Length::Fill);
             for (idx, state) in self.states.iter().enumerate() {
                 column = column.push(
                     row![
+                        color_swatch(state.color),
                         // Nome
-                        text(&state.name).width(Length::Fixed(120.0)),
+                        text_input("Name", self.state_name_input(state))
+                            .on_input(move |val| Message::StateNameEdited(idx, val))
+                            .style(if self.state_name_input(state).trim() != state.name {
+                                theme::TextInput::Custom(Box::new(InvalidInput))
+                            } else {
+                                theme::TextInput::Default
+                            })
+                            .padding(5)
+                            .width(Length::Fixed(120.0)),
                         // Cor
-                        text(format!(
-                            "RGB: ({}, {}, {})",
-                            (state.color.r * 255.0) as u8,
-                            (state.color.g * 255.0) as u8,
-                            (state.color.b * 255.0) as u8
-                        ))
-                        .width(Length::Fixed(150.0)),
+                        text("RGB:").style(state.color),
+                        channel_input(idx, 0, state.color.into_rgba8()[0]),
+                        channel_input(idx, 1, state.color.into_rgba8()[1]),
+                        channel_input(idx, 2, state.color.into_rgba8()[2]),
                         // Peso
                         text("Weight:").width(Length::Fixed(60.0)),
                         text_input("Weight", &state.weight.to_string())
@@ -95,6 +297,20 @@ impl CASimulator {
             Scrollable::new(states_list)
                 .height(Length::Fixed(150.0))
                 .width(Length::Fill),
+            row![
+                text("Background State:"),
+                PickList::new(
+                    self.states.clone(),
+                    self.states
+                        .iter()
+                        .find(|s| s.id == self.background_state_id)
+                        .cloned(),
+                    Message::BackgroundStateSelected,
+                )
+                .placeholder("Select Background State"),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
         ]
         .spacing(10)
         .width(Length::Fill);
@@ -103,7 +319,11 @@ impl CASimulator {
         let available_states_for_picklist = self.states.clone();
 
         let mut rule_creation_panel = column![
-            text("Create New Transition Rule").size(20),
+            text(match self.editing_rule_index {
+                Some(idx) => format!("Editing Rule #{}", idx + 1),
+                None => "Create New Transition Rule".to_string(),
+            })
+            .size(20),
             // Current State
             text("IF Current State is:"),
             PickList::new(
@@ -112,23 +332,55 @@ impl CASimulator {
                 Message::RuleCurrentStateSelected,
             )
             .placeholder("Select Current State"),
-            text("AND the following conditions are met:"),
-        ];
+        ]
+        .push_maybe(field_error(&self.rule_form_current_error))
+        .push(text("AND the following conditions are met:"))
+        .push(
+            text(
+                "Combiners chain left to right with no precedence: \
+                 A OR B AND C means (A OR B) AND C. Group conditions to change that.",
+            )
+            .size(14),
+        );
+
+        let operator_help = RelationalOperator::ALL
+            .iter()
+            .map(|op| op.description())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let combiner_help = format!(
+            "{}\nConditions are combined strictly left to right, with no precedence:\n\
+             A OR B AND C is read as (A OR B) AND C.\n\
+             Group conditions to evaluate them first.\n\
+             The first rule that matches the cell wins.",
+            ConditionCombiner::ALL
+                .iter()
+                .map(|c| c.description())
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+
+        // A condition may also count every neighbor that differs from the cell
+        let mut neighbor_picklist = available_states_for_picklist.clone();
+        neighbor_picklist.push(CAState::not_self());
 
         for idx in 0..self.rule_form_conditions.len() {
             let cond = &self.rule_form_conditions[idx];
 
             let mut condition_row = row![
                 PickList::new(
-                    available_states_for_picklist.clone(),
+                    neighbor_picklist.clone(),
                     cond.neighbor_state.clone(),
                     move |s| Message::RuleNeighborStateSelected(idx, s)
                 )
                 .placeholder("Neighbor State"),
-                PickList::new(RelationalOperator::ALL.to_vec(), cond.operator, move |op| {
-                    Message::RuleOperatorSelected(idx, op)
-                })
-                .placeholder("Operator"),
+                with_help(
+                    PickList::new(RelationalOperator::ALL.to_vec(), cond.operator, move |op| {
+                        Message::RuleOperatorSelected(idx, op)
+                    })
+                    .placeholder("Operator"),
+                    operator_help.clone()
+                ),
                 text_input("Count (e.g., 3)", &cond.threshold)
                     .on_input(move |val| Message::RuleThresholdChanged(idx, val))
                     .padding(5)
@@ -138,7 +390,7 @@ impl CASimulator {
             .spacing(5);
 
             if idx < self.rule_form_conditions.len() - 1 {
-                condition_row = condition_row.push(
+                condition_row = condition_row.push(with_help(
                     PickList::new(
                         ConditionCombiner::ALL.to_vec(),
                         cond.combiner.clone(),
@@ -146,10 +398,43 @@ impl CASimulator {
                     )
                     .placeholder("Combiner")
                     .width(Length::Fixed(80.0)),
-                );
+                    combiner_help.clone(),
+                ));
             }
 
             rule_creation_panel = rule_creation_panel.push(condition_row);
+            rule_creation_panel = rule_creation_panel.push(
+                Direction::ALL
+                    .into_iter()
+                    .fold(
+                        row![with_help(
+                            text("Directions:").size(14),
+                            "Only neighbors in the checked directions are counted. \
+                             With every direction checked the whole neighborhood counts."
+                                .to_string(),
+                        )],
+                        |directions_row, direction| {
+                            directions_row.push(
+                                checkbox(
+                                    direction.to_string(),
+                                    cond.directions.contains(direction),
+                                )
+                                .on_toggle(move |_| Message::RuleDirectionToggled(idx, direction))
+                                .size(14)
+                                .text_size(14),
+                            )
+                        },
+                    )
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+            );
+            for err in &cond.errors {
+                rule_creation_panel = rule_creation_panel.push(
+                    text(format!("Condição {}: {}", idx + 1, err))
+                        .size(14)
+                        .style(Color::from_rgb8(255, 0, 0)),
+                );
+            }
         }
 
         rule_creation_panel = rule_creation_panel.push(
@@ -158,6 +443,42 @@ impl CASimulator {
                 .padding(5),
         );
 
+        // Parentheses around conditions, evaluated before their neighbors
+        if self.rule_form_conditions.len() >= 3 {
+            rule_creation_panel = rule_creation_panel.push(
+                row![
+                    text("Group conditions"),
+                    text_input("from", &self.rule_form_group_inputs[0])
+                        .on_input(|val| Message::RuleGroupBoundChanged(0, val))
+                        .padding(5)
+                        .width(Length::Fixed(60.0)),
+                    text("to"),
+                    text_input("to", &self.rule_form_group_inputs[1])
+                        .on_input(|val| Message::RuleGroupBoundChanged(1, val))
+                        .padding(5)
+                        .width(Length::Fixed(60.0)),
+                    button("( )")
+                        .on_press(Message::AddConditionGroup)
+                        .padding(5),
+                ]
+                .spacing(5)
+                .align_items(Alignment::Center),
+            );
+            for (idx, &(start, end)) in self.rule_form_groups.iter().enumerate() {
+                rule_creation_panel = rule_creation_panel.push(
+                    row![
+                        text(format!("(conditions {} to {})", start + 1, end + 1)).size(14),
+                        button("-").on_press(Message::RemoveConditionGroup(idx)),
+                    ]
+                    .spacing(5)
+                    .align_items(Alignment::Center),
+                );
+            }
+            rule_creation_panel = rule_creation_panel
+                .push_maybe(field_error(&self.rule_form_group_error))
+                .push(text(format!("Evaluated as: {}", self.condition_preview())).size(14));
+        }
+
         rule_creation_panel = rule_creation_panel.push(text("THEN Next State is:")).push(
             PickList::new(
                 available_states_for_picklist.clone(),
@@ -166,17 +487,172 @@ impl CASimulator {
             )
             .placeholder("Select Next State"),
         );
+        rule_creation_panel =
+            rule_creation_panel.push_maybe(field_error(&self.rule_form_next_error));
 
+        rule_creation_panel =
+            rule_creation_panel.push(text("OR one of several weighted outcomes:"));
+        for (idx, outcome) in self.rule_form_outcomes.iter().enumerate() {
+            rule_creation_panel = rule_creation_panel
+                .push(
+                    row![
+                        PickList::new(
+                            available_states_for_picklist.clone(),
+                            outcome.state.clone(),
+                            move |s| Message::OutcomeStateSelected(idx, s)
+                        )
+                        .placeholder("Next State"),
+                        text_input("Probability", &outcome.probability)
+                            .on_input(move |val| Message::OutcomeProbabilityChanged(idx, val))
+                            .padding(5)
+                            .width(Length::Fixed(100.0)),
+                        button("-").on_press(Message::RemoveOutcome(idx)),
+                    ]
+                    .spacing(5),
+                )
+                .push_maybe(field_error(&outcome.error));
+        }
         rule_creation_panel = rule_creation_panel
-            .push(text("Probability (0.0 - 1.0):"))
             .push(
-                text_input("e.g., 0.8", &self.rule_form_probability)
+                button("+ Add Outcome")
+                    .on_press(Message::AddOutcome)
+                    .padding(5),
+            )
+            .push_maybe(field_error(&self.rule_form_outcomes_error));
+
+        let (probability_label, probability_placeholder) = if self.probability_as_percent {
+            ("Probability (0 - 100%):", "e.g., 80%")
+        } else {
+            ("Probability (0.0 - 1.0):", "e.g., 0.8")
+        };
+        rule_creation_panel = rule_creation_panel.push(text(probability_label)).push(
+            row![
+                text_input(probability_placeholder, &self.rule_form_probability)
                     .on_input(Message::RuleProbabilityChanged)
+                    .style(match self.rule_form_probability_error {
+                        Some(_) => theme::TextInput::Custom(Box::new(InvalidInput)),
+                        None => theme::TextInput::Default,
+                    })
                     .padding(5)
                     .width(Length::Fixed(100.0)),
-            );
+                checkbox("Show as percentage", self.probability_as_percent)
+                    .on_toggle(Message::ProbabilityPercentToggled),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+        );
         rule_creation_panel =
-            rule_creation_panel.push(button("Add Rule").on_press(Message::AddRule).padding(5));
+            rule_creation_panel.push_maybe(field_error(&self.rule_form_probability_error));
+        rule_creation_panel = rule_creation_panel.push(
+            checkbox(
+                "Only while the grid-wide population matches",
+                self.rule_form_global_enabled,
+            )
+            .on_toggle(Message::RuleGlobalToggled),
+        );
+        if self.rule_form_global_enabled {
+            rule_creation_panel = rule_creation_panel
+                .push(
+                    row![
+                        text("total("),
+                        PickList::new(
+                            available_states_for_picklist.clone(),
+                            self.rule_form_global_state.clone(),
+                            Message::RuleGlobalStateSelected,
+                        )
+                        .placeholder("State"),
+                        text(")"),
+                        with_help(
+                            PickList::new(
+                                RelationalOperator::ALL.to_vec(),
+                                self.rule_form_global_operator,
+                                Message::RuleGlobalOperatorSelected,
+                            )
+                            .placeholder("Operator"),
+                            "Compares how many cells of the whole grid are in the state, \
+                             counted once per step before any cell changes. \
+                             While it fails the rule is skipped everywhere."
+                                .to_string(),
+                        ),
+                        text_input("Total (e.g., 50)", &self.rule_form_global_threshold)
+                            .on_input(Message::RuleGlobalThresholdChanged)
+                            .padding(5)
+                            .width(Length::Fixed(120.0)),
+                    ]
+                    .spacing(5)
+                    .align_items(Alignment::Center),
+                )
+                .push_maybe(field_error(&self.rule_form_global_error));
+        }
+
+        rule_creation_panel = rule_creation_panel.push(
+            checkbox(
+                "Only if the cell's previous state was",
+                self.rule_form_prev_enabled,
+            )
+            .on_toggle(Message::RulePrevToggled),
+        );
+        if self.rule_form_prev_enabled {
+            rule_creation_panel = rule_creation_panel
+                .push(with_help(
+                    PickList::new(
+                        available_states_for_picklist.clone(),
+                        self.rule_form_prev_state.clone(),
+                        Message::RulePrevStateSelected,
+                    )
+                    .placeholder("Previous state"),
+                    "The state the cell had one generation earlier. On the first step, \
+                     and on the step right after the grid is edited, there is no earlier \
+                     generation and the rule is skipped."
+                        .to_string(),
+                ))
+                .push_maybe(field_error(&self.rule_form_prev_error));
+        }
+
+        rule_creation_panel = rule_creation_panel.push(
+            checkbox("Limit to region", self.rule_form_region_enabled)
+                .on_toggle(Message::RuleRegionToggled),
+        );
+        if self.rule_form_region_enabled {
+            let region_row = ["Row from", "Col from", "Row to", "Col to"]
+                .iter()
+                .enumerate()
+                .fold(Row::new().spacing(5), |row, (idx, placeholder)| {
+                    row.push(
+                        text_input(placeholder, &self.rule_form_region[idx])
+                            .on_input(move |val| Message::RuleRegionChanged(idx, val))
+                            .padding(5)
+                            .width(Length::Fixed(80.0)),
+                    )
+                });
+            rule_creation_panel = rule_creation_panel
+                .push(region_row)
+                .push_maybe(field_error(&self.rule_form_region_error));
+        }
+        rule_creation_panel = rule_creation_panel.push(
+            row![
+                button(if self.editing_rule_index.is_some() {
+                    "Update Rule"
+                } else {
+                    "Add Rule"
+                })
+                .on_press_maybe(
+                    self.parse_probability_input()
+                        .is_some()
+                        .then_some(Message::AddRule),
+                )
+                .padding(5),
+                button(if self.editing_rule_index.is_some() {
+                    "Cancel Edit"
+                } else {
+                    "Clear Form"
+                })
+                .on_press(Message::ClearRuleForm)
+                .style(theme::Button::Secondary)
+                .padding(5),
+            ]
+            .spacing(10),
+        );
 
         if let Some(err) = &self.rule_form_error {
             rule_creation_panel =
@@ -195,13 +671,33 @@ impl CASimulator {
                         col.push(
                             row![
                                 text(format!(
-                                    "IF current is '{}' AND {} THEN next is '{}' WITH PROB '{}'",
+                                    "IF current is '{}'{} AND {} THEN next is {} WITH PROB '{}'{}{}",
                                     rule.current_state_name,
+                                    rule.prev_as_string(),
                                     rule.conditions_as_string(),
-                                    rule.next_state_name,
-                                    rule.probability
+                                    rule.next_as_string(),
+                                    self.format_probability(rule.probability),
+                                    rule.region_as_string(),
+                                    rule.global_as_string()
                                 ))
                                 .width(Length::Fill),
+                                button(text("▲"))
+                                    .on_press_maybe((idx > 0).then_some(Message::MoveRuleUp(idx)))
+                                    .padding(5),
+                                button(text("▼"))
+                                    .on_press_maybe(
+                                        (idx + 1 < self.rules.len())
+                                            .then_some(Message::MoveRuleDown(idx))
+                                    )
+                                    .padding(5),
+                                button(text("Edit"))
+                                    .on_press(Message::EditRule(idx))
+                                    .style(if self.editing_rule_index == Some(idx) {
+                                        theme::Button::Primary
+                                    } else {
+                                        theme::Button::Secondary
+                                    })
+                                    .padding(5),
                                 button(text("Remove"))
                                     .on_press(Message::RemoveRule(idx))
                                     .style(theme::Button::Destructive)
@@ -216,18 +712,64 @@ impl CASimulator {
         };
 
         let export_import_row = row![
+            button("Save Project").on_press(Message::SaveProject),
+            button("Load Project").on_press(Message::LoadProject),
             button("Export Rules").on_press(Message::ExportRules),
             button("Import Rules").on_press(Message::ImportRules),
+            button("Export Config").on_press(Message::ExportConfig),
+            button("Import Config").on_press(Message::ImportConfig),
+            button("Copy").on_press(Message::CopyRulesToClipboard),
+            button("Paste").on_press(Message::PasteRulesFromClipboard),
+            PickList::new(
+                ImportMode::ALL.to_vec(),
+                Some(self.import_mode),
+                Message::ImportModeSelected,
+            ),
         ]
         .spacing(10)
         .align_items(Alignment::Center);
 
+        let import_errors = self
+            .import_errors
+            .iter()
+            .fold(Column::new().spacing(2).width(Length::Fill), |col, err| {
+                col.push(text(err).size(14).style(Color::from_rgb8(255, 0, 0)))
+            });
+
+        let probability_summary = probability_budgets(&self.rules).into_iter().fold(
+            Column::new().spacing(5).width(Length::Fill),
+            |col, budget| {
+                let line = text(format!(
+                    "'{}': {} rule(s), total {}, chance any fires {}",
+                    budget.current_state_name,
+                    budget.rule_count,
+                    self.format_probability(budget.total_probability),
+                    self.format_probability(budget.chance_any_fires),
+                ));
+                if budget.exceeds_one() {
+                    col.push(line.style(Color::from_rgb8(255, 200, 0))).push(
+                        text(
+                            "  Only the first matching rule fires; a rule that fails its \
+                             probability roll falls through to the next one.",
+                        )
+                        .size(14)
+                        .style(Color::from_rgb8(255, 200, 0)),
+                    )
+                } else {
+                    col.push(line)
+                }
+            },
+        );
+
         let rules_panel = column![
             text("Defined Rules").size(20),
             Scrollable::new(rules_list)
                 .height(Length::Fixed(200.0))
                 .width(Length::Fill),
             export_import_row,
+            import_errors,
+            text("Probability Summary (per current state)").size(16),
+            probability_summary,
         ]
         .spacing(10)
         .width(Length::Fill);
@@ -235,6 +777,17 @@ impl CASimulator {
         Scrollable::new(
             Container::new(
                 column![
+                    row![
+                        button("Undo")
+                            .on_press_maybe(self.can_undo().then_some(Message::Undo))
+                            .padding(5),
+                        button("Redo")
+                            .on_press_maybe(self.can_redo().then_some(Message::Redo))
+                            .padding(5),
+                        text("Ctrl+Z / Ctrl+Y").size(14),
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center),
                     model_selector,
                     iced::widget::horizontal_rule(10),
                     state_creation_panel,
@@ -258,13 +811,22 @@ impl CASimulator {
     }
 
     pub fn view_simulation_tab(&self) -> Element<'_, Message> {
+        let step_back = (!self.step_history.is_empty()).then_some(Message::StepBack);
+        let paint_state = self
+            .states
+            .iter()
+            .find(|s| s.id == self.selected_paint_state_id);
         if self.fullscreen_mode {
             let controls = row![
                 button(if self.is_simulating { "Pause" } else { "Start" })
                     .on_press(Message::ToggleSimulation)
                     .padding(5),
+                button("Step Back").on_press_maybe(step_back).padding(5),
                 button("Next Step").on_press(Message::NextStep).padding(5),
                 button("Reset Grid").on_press(Message::ResetGrid).padding(5),
+                button("Fit to View")
+                    .on_press(Message::ResetView)
+                    .padding(5),
                 button("Exit Fullscreen")
                     .on_press(Message::ToggleFullscreen)
                     .padding(5),
@@ -274,6 +836,7 @@ impl CASimulator {
 
             column![
                 controls,
+                self.view_hover_readout(),
                 Canvas::new(self).width(Length::Fill).height(Length::Fill)
             ]
             .spacing(20)
@@ -282,6 +845,226 @@ impl CASimulator {
             .height(Length::Fill)
             .into()
         } else {
+            let bulk_pair = self
+                .bulk_from_state
+                .as_ref()
+                .zip(self.bulk_to_state.as_ref())
+                .map(|(from, to)| (from.id, to.id));
+
+            let wall_state_id = match self.grid.boundary {
+                BoundaryMode::Fixed(id) => id,
+                BoundaryMode::Bounded | BoundaryMode::Toroidal => self.background_state_id,
+            };
+            let mut boundary_row = row![
+                text("Boundary:"),
+                PickList::new(
+                    vec![
+                        BoundaryMode::Bounded,
+                        BoundaryMode::Fixed(wall_state_id),
+                        BoundaryMode::Toroidal,
+                    ],
+                    Some(self.grid.boundary),
+                    Message::BoundaryModeChanged
+                ),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center);
+            if let BoundaryMode::Fixed(id) = self.grid.boundary {
+                boundary_row = boundary_row.push(
+                    PickList::new(
+                        self.states.clone(),
+                        self.states.iter().find(|s| s.id == id).cloned(),
+                        Message::BoundaryWallStateSelected,
+                    )
+                    .placeholder("Wall State"),
+                );
+            }
+
+            let tool_panel: Element<Message> =
+                match (self.canvas_tool, self.selected_annotation_cell) {
+                    (CanvasTool::Annotate, Some((r, c))) => row![
+                        text(format!("Label at ({}, {}):", r, c)),
+                        text_input("e.g., glider gun here", &self.annotation_input)
+                            .on_input(Message::AnnotationTextChanged)
+                            .on_submit(Message::SaveAnnotation)
+                            .padding(3)
+                            .width(Length::Fixed(200.0)),
+                        button("Set").on_press(Message::SaveAnnotation).padding(5),
+                        button("Remove")
+                            .on_press(Message::RemoveAnnotation)
+                            .style(theme::Button::Destructive)
+                            .padding(5),
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .into(),
+                    (CanvasTool::Annotate, None) => text("Click a cell to label it.").into(),
+                    (CanvasTool::Poke, _) => text(
+                        self.poke_report
+                            .as_deref()
+                            .unwrap_or("Click a cell to apply the rules to it alone."),
+                    )
+                    .into(),
+                    (CanvasTool::Line, _) => text(match self.line_anchor {
+                        Some((r, c)) => {
+                            format!("Line from ({}, {}): click the cell where it ends.", r, c)
+                        }
+                        None => "Click the cell where the line starts.".to_string(),
+                    })
+                    .into(),
+                    (CanvasTool::Rectangle, _) => row![
+                        text("Drag from one corner to the opposite one."),
+                        checkbox("Filled", self.rect_filled).on_toggle(Message::RectFilledToggled),
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .into(),
+                    (CanvasTool::Paint, _) => Space::with_height(0).into(),
+                };
+
+            let mut comparison_row = row![button("Load Comparison Grid")
+                .on_press(Message::LoadComparisonGrid)
+                .padding(5)]
+            .spacing(10)
+            .align_items(Alignment::Center);
+            if self.comparison_grid.is_some() {
+                let diff_summary = match self.comparison_diff_count() {
+                    Some(count) => format!("{} cell(s) differ", count),
+                    None => "Sizes differ".to_string(),
+                };
+                comparison_row = comparison_row
+                    .push(
+                        checkbox("Show diff", self.show_comparison_diff)
+                            .on_toggle(Message::ComparisonDiffToggled),
+                    )
+                    .push(text(diff_summary))
+                    .push(button("Clear").on_press(Message::ClearComparisonGrid));
+            }
+
+            let mut neighborhoods = vec![
+                Neighborhood::VonNeumann,
+                Neighborhood::Moore,
+                Neighborhood::ExtendedMoore,
+                Neighborhood::Margolus,
+            ];
+            // The ring entry carries whatever bounds are typed in (or the active ones)
+            if let Some(ring) = self.ring_from_inputs().or(match self.grid.neighborhood {
+                ring @ Neighborhood::Ring { .. } => Some(ring),
+                _ => None,
+            }) {
+                neighborhoods.push(ring);
+            }
+            if let Some(moore) = self
+                .moore_radius_from_input()
+                .or(match self.grid.neighborhood {
+                    moore @ Neighborhood::MooreRadius(_) => Some(moore),
+                    _ => None,
+                })
+            {
+                neighborhoods.push(moore);
+            }
+            let mut neighborhood_row = row![PickList::new(
+                neighborhoods,
+                Some(self.grid.neighborhood),
+                Message::NeighborhoodChanged
+            )
+            .placeholder("Select Neighborhood")]
+            .spacing(10)
+            .align_items(Alignment::Center);
+            if matches!(self.grid.neighborhood, Neighborhood::Ring { .. }) {
+                neighborhood_row = neighborhood_row
+                    .push(text("Ring inner:"))
+                    .push(
+                        text_input("2", &self.ring_inner_input)
+                            .on_input(Message::RingInnerChanged)
+                            .padding(3)
+                            .width(Length::Fixed(40.0)),
+                    )
+                    .push(text("outer:"))
+                    .push(
+                        text_input("3", &self.ring_outer_input)
+                            .on_input(Message::RingOuterChanged)
+                            .padding(3)
+                            .width(Length::Fixed(40.0)),
+                    );
+                if self.ring_from_inputs().is_none() {
+                    neighborhood_row = neighborhood_row.push(
+                        text(format!("1 <= inner <= outer <= {}", MAX_RING_RADIUS))
+                            .size(14)
+                            .style(Color::from_rgb8(255, 0, 0)),
+                    );
+                }
+            }
+            if matches!(self.grid.neighborhood, Neighborhood::MooreRadius(_)) {
+                neighborhood_row = neighborhood_row.push(text("Radius:")).push(
+                    text_input("3", &self.moore_radius_input)
+                        .on_input(Message::NeighborhoodRadiusChanged)
+                        .padding(3)
+                        .width(Length::Fixed(40.0)),
+                );
+                if self.moore_radius_from_input().is_none() {
+                    neighborhood_row = neighborhood_row.push(
+                        text(format!("1 <= radius <= {}", MAX_MOORE_RADIUS))
+                            .size(14)
+                            .style(Color::from_rgb8(255, 0, 0)),
+                    );
+                }
+            }
+            if self.grid.neighborhood == Neighborhood::Margolus {
+                neighborhood_row = neighborhood_row
+                    .push(with_help(
+                        text(format!("{} block rule(s)", self.block_rules.len())),
+                        "Each step updates 2x2 blocks, shifted by one cell on odd \
+                         generations, with block rules instead of the transition rules. \
+                         Blocks no rule matches stay as they are."
+                            .to_string(),
+                    ))
+                    .push(button("Billiard balls").on_press(Message::LoadBilliardBallRules))
+                    .push(button("Clear").on_press(Message::ClearBlockRules));
+            }
+
+            let exporting = self.export_in_progress;
+            let image_export_row = row![
+                button("Export PNG")
+                    .on_press_maybe((!exporting).then_some(Message::ExportPng))
+                    .padding(5),
+                checkbox("Caption", self.export_caption).on_toggle(Message::ExportCaptionToggled),
+                checkbox(
+                    format!("Record GIF ({} frames)", self.gif_frames.len()),
+                    self.gif_recording
+                )
+                .on_toggle(Message::GifRecordingToggled),
+                button("Export GIF")
+                    .on_press_maybe(
+                        (!exporting && !self.gif_frames.is_empty()).then_some(Message::ExportGif)
+                    )
+                    .padding(5),
+                button("Clear")
+                    .on_press_maybe(
+                        (!self.gif_frames.is_empty()).then_some(Message::ClearGifFrames)
+                    )
+                    .padding(5),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center);
+            let export_status: Element<Message> = match &self.export_status {
+                Some(status) => text(status).size(14).into(),
+                None => Space::with_height(0).into(),
+            };
+
+            let stable_report: Element<Message> = match &self.stable_report {
+                Some(report) => text(report)
+                    .size(14)
+                    .style(Color::from_rgb8(255, 200, 0))
+                    .into(),
+                None => Space::with_height(0).into(),
+            };
+
+            let seed_report: Element<Message> = match &self.seed_search_report {
+                Some(report) => text(report).size(14).into(),
+                None => Space::with_height(0).into(),
+            };
+
             let controls = column![
                 text("Simulation Controls").size(20),
                 row![
@@ -298,24 +1081,126 @@ impl CASimulator {
                     button("Apply Size")
                         .on_press(Message::ApplyGridSize)
                         .padding(5),
+                    checkbox("Preserve contents", self.preserve_grid_contents)
+                        .on_toggle(Message::PreserveGridContentsToggled),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                row![
                     button("Save Grid").on_press(Message::SaveGrid).padding(5),
                     button("Load Grid").on_press(Message::LoadGrid).padding(5),
+                    button("Export CSV").on_press(Message::ExportCsv).padding(5),
+                    button("Import CSV").on_press(Message::ImportCsv).padding(5),
                     button("Fullscreen")
                         .on_press(Message::ToggleFullscreen)
                         .padding(5),
+                    button("Fit to View")
+                        .on_press(Message::ResetView)
+                        .padding(5),
                 ]
                 .spacing(10)
-                .align_items(Alignment::Center)
+                .align_items(Alignment::Center),
+                row![
+                    text("Import pattern:"),
+                    with_help(
+                        row![
+                            button("RLE").on_press(Message::ImportRle).padding(5),
+                            button(".cells").on_press(Message::ImportCells).padding(5),
+                        ]
+                        .spacing(5),
+                        "Live cells are painted in the paint state, dead ones in the background state"
+                            .to_string()
+                    ),
+                    PickList::new(
+                        PatternPlacement::ALL.to_vec(),
+                        Some(self.pattern_placement),
+                        Message::PatternPlacementSelected
+                    ),
+                ]
                 .spacing(10)
                 .align_items(Alignment::Center),
+                comparison_row,
+                image_export_row,
+                export_status,
                 row![
                     button(if self.is_simulating { "Pause" } else { "Start" })
                         .on_press(Message::ToggleSimulation)
                         .padding(5),
+                    button("Step Back").on_press_maybe(step_back).padding(5),
                     button("Next Step").on_press(Message::NextStep).padding(5),
                     button("Reset Grid").on_press(Message::ResetGrid).padding(5),
+                    with_help(
+                        button("Clear").on_press(Message::ClearGrid).padding(5),
+                        "Fills every cell with the paint state".to_string(),
+                    ),
                 ]
                 .spacing(10),
+                row![
+                    text(format!("Generation: {}", self.generation)),
+                    text("Pause at generation:"),
+                    text_input("never", &self.stop_at_input)
+                        .on_input(Message::StopAtChanged)
+                        .padding(3)
+                        .width(Length::Fixed(80.0)),
+                    checkbox("Pause when stable", self.stop_on_stable)
+                        .on_toggle(Message::StopOnStableToggled),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                stable_report,
+                self.view_population_panel(),
+                self.view_run_until_panel(),
+                text(format!("Grid checksum: {:016x}", self.grid.checksum())).size(14),
+                row![
+                    text("Seed:"),
+                    with_help(
+                        text_input("e.g., 42", &self.seed_input)
+                            .on_input(Message::SeedChanged)
+                            .padding(3)
+                            .width(Length::Fixed(180.0)),
+                        "Reset Grid and every step draw from this seed: the same seed, \
+                         model and starting grid give the same generations."
+                            .to_string(),
+                    ),
+                    button("New Seed")
+                        .on_press(Message::RandomizeSeed)
+                        .padding(5),
+                    with_help(
+                        checkbox("Deterministic", self.force_deterministic)
+                            .on_toggle(Message::DeterministicToggled),
+                        "Every rule fires as if it were WITH PROB 1.0. Rules with weighted \
+                         outcomes still draw one."
+                            .to_string(),
+                    ),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                row![
+                    text("Seed pattern:"),
+                    PickList::new(
+                        SeedPattern::ALL.to_vec(),
+                        None::<SeedPattern>,
+                        Message::SeedPatternSelected
+                    )
+                    .placeholder("Background + paint state"),
+                    button("Find Interesting Seed")
+                        .on_press(Message::FindInterestingSeed)
+                        .padding(5),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                seed_report,
+                row![
+                    checkbox("Autosave session every", self.autosave_enabled)
+                        .on_toggle(Message::AutosaveToggled),
+                    text_input("60", &self.autosave_interval_input)
+                        .on_input(Message::AutosaveIntervalChanged)
+                        .padding(3)
+                        .width(Length::Fixed(60.0)),
+                    text("seconds"),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
                 row![
                     text("Speed (Slow -> Fast):"),
                     Slider::new(
@@ -327,50 +1212,349 @@ impl CASimulator {
                 ]
                 .spacing(10)
                 .align_items(Alignment::Center),
+                row![
+                    text("Canvas tool:"),
+                    PickList::new(
+                        CanvasTool::ALL.to_vec(),
+                        Some(self.canvas_tool),
+                        Message::CanvasToolSelected
+                    ),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                tool_panel,
+                row![
+                    text("Arrow key pan step (px):"),
+                    text_input("20", &self.pan_step_input)
+                        .on_input(Message::PanStepChanged)
+                        .padding(5)
+                        .width(Length::Fixed(60.0)),
+                    text("+/- to zoom while paused").size(14),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
                 text("Click on grid to paint state:"),
-                PickList::new(
-                    self.states.clone(),
-                    self.states
-                        .iter()
-                        .find(|s| s.id == self.selected_paint_state_id)
-                        .cloned(),
-                    Message::PaintStateSelected
-                )
-                .placeholder("Select Paint State"),
-                PickList::new(
-                    vec![
-                        Neighborhood::VonNeumann,
-                        Neighborhood::Moore,
-                        Neighborhood::ExtendedMoore
-                    ],
-                    Some(self.grid.neighborhood),
-                    Message::NeighborhoodChanged
-                )
-                .placeholder("Select Neighborhood"),
+                row![
+                    color_swatch(paint_state.map_or(Color::TRANSPARENT, |s| s.color)),
+                    PickList::new(
+                        self.states.clone(),
+                        paint_state.cloned(),
+                        Message::PaintStateSelected
+                    )
+                    .placeholder("Select Paint State"),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                row![
+                    text(format!("Brush size: {0}x{0}", self.brush_size)),
+                    Slider::new(
+                        1..=MAX_BRUSH_SIZE,
+                        self.brush_size as u8,
+                        Message::BrushSizeChanged
+                    )
+                    .step(2u8)
+                    .width(Length::Fixed(150.0)),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                checkbox("Pause simulation while painting", self.pause_while_painting)
+                    .on_toggle(Message::PauseWhilePaintingToggled),
+                checkbox("Flash cells on transition", self.show_transition_flashes)
+                    .on_toggle(Message::TransitionFlashesToggled),
+                checkbox("Darken cells by age", self.show_age_shading)
+                    .on_toggle(Message::AgeShadingToggled),
+                row![
+                    text("Cell borders (px):"),
+                    text_input("1.5", &self.border_width_input)
+                        .on_input(Message::BorderWidthChanged)
+                        .padding(3)
+                        .width(Length::Fixed(50.0)),
+                    PickList::new(
+                        BorderMode::ALL.to_vec(),
+                        Some(self.border_mode),
+                        Message::BorderModeSelected
+                    ),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                neighborhood_row,
+                boundary_row,
+                row![
+                    text("When no rule matches:"),
+                    PickList::new(
+                        NoMatchPolicy::ALL.to_vec(),
+                        Some(self.no_match_policy),
+                        Message::NoMatchPolicySelected
+                    ),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                row![
+                    text("Next state decided by:"),
+                    with_help(
+                        PickList::new(
+                            RuleEvalMode::ALL.to_vec(),
+                            Some(self.rule_eval_mode),
+                            Message::RuleEvalModeSelected
+                        ),
+                        "With several rules firing for a cell, the first one in the list \
+                         or the last one sets its next state."
+                            .to_string(),
+                    ),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                text("Replace / swap states across the grid:"),
+                row![
+                    PickList::new(
+                        self.states.clone(),
+                        self.bulk_from_state.clone(),
+                        Message::BulkFromStateSelected
+                    )
+                    .placeholder("From"),
+                    PickList::new(
+                        self.states.clone(),
+                        self.bulk_to_state.clone(),
+                        Message::BulkToStateSelected
+                    )
+                    .placeholder("To"),
+                    button("Replace")
+                        .on_press_maybe(bulk_pair.map(|(a, b)| Message::ReplaceState(a, b)))
+                        .padding(5),
+                    button("Swap")
+                        .on_press_maybe(bulk_pair.map(|(a, b)| Message::SwapStates(a, b)))
+                        .padding(5),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                self.view_sweep_panel(),
             ]
             .spacing(15)
             .width(Length::Fill);
 
-            let canvas = Canvas::new(self)
-                .width(Length::Fixed(600.0))
-                .height(Length::Fixed(600.0));
+            // Controls scroll in a side panel; the canvas takes the rest of the window
+            let canvas = Canvas::new(self).width(Length::Fill).height(Length::Fill);
 
-            Scrollable::new(
+            row![
+                Scrollable::new(controls.padding([0, 15, 0, 0]))
+                    .width(Length::Fixed(CONTROLS_PANEL_WIDTH))
+                    .height(Length::Fill),
+                column![self.view_hover_readout(), canvas].spacing(5),
+            ]
+            .spacing(20)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+        }
+    }
+
+    // "(row, col): state" of the cell under the cursor, blank while it is off the grid
+    fn view_hover_readout(&self) -> Element<'_, Message> {
+        // The grid may have shrunk since the cursor last moved
+        let hovered = self.hovered_cell.get().and_then(|(r, c)| {
+            let id = *self.grid.cells.get(r)?.get(c)?;
+            Some((r, c, id))
+        });
+        let readout = hovered.map_or_else(String::new, |(r, c, id)| {
+            let name = self
+                .states
+                .iter()
+                .find(|s| s.id == id)
+                .map_or_else(|| format!("missing state {}", id), |s| s.name.clone());
+            format!("({}, {}): {}", r, c, name)
+        });
+        text(readout).size(14).into()
+    }
+
+    // Live count of cells in each state; the view is rebuilt after every update, so this
+    // is recounted whenever the grid (and its cache) changes
+    fn view_population_panel(&self) -> Element<'_, Message> {
+        let num_ids = self
+            .states
+            .iter()
+            .map(|s| s.id)
+            .max()
+            .map_or(0, |id| id.saturating_add(1));
+        let counts = self.grid.state_counts(num_ids);
+        let total = (self.grid.width * self.grid.height).max(1);
+
+        self.states
+            .iter()
+            .fold(
                 column![
-                    controls,
-                    row![
-                        Space::with_width(Length::Fill),
-                        canvas,
-                        Space::with_width(Length::Fill),
-                    ]
-                    .width(Length::Fill)
+                    text("Population").size(18),
+                    Canvas::new(PopulationChart {
+                        history: &self.population_history,
+                        states: &self.states,
+                        capacity: MAX_POPULATION_HISTORY,
+                        total_cells: self.grid.width * self.grid.height,
+                    })
+                    .width(Length::Fixed(360.0))
+                    .height(Length::Fixed(120.0)),
                 ]
-                .spacing(20)
-                .width(Length::Fill),
+                .spacing(4),
+                |panel, state| {
+                    let count = counts.get(state.id as usize).copied().unwrap_or(0);
+                    panel.push(
+                        row![
+                            text("██").style(state.color),
+                            text(&state.name).width(Length::Fixed(120.0)),
+                            text(count).width(Length::Fixed(70.0)),
+                            text(format!("{:.1}%", count as f32 * 100.0 / total as f32)).size(14),
+                        ]
+                        .spacing(10)
+                        .align_items(Alignment::Center),
+                    )
+                },
             )
-            .width(Length::Fill)
-            .height(Length::Fill)
             .into()
+    }
+
+    // Steps in one go until a chosen condition holds, then reports what stopped it
+    fn view_run_until_panel(&self) -> Element<'_, Message> {
+        let mut condition_row = row![PickList::new(
+            StopConditionKind::ALL.to_vec(),
+            Some(self.run_until_kind),
+            Message::RunUntilKindSelected
+        )]
+        .spacing(5)
+        .align_items(Alignment::Center);
+
+        let value_input = |placeholder| {
+            text_input(placeholder, &self.run_until_value)
+                .on_input(Message::RunUntilValueChanged)
+                .padding(3)
+                .width(Length::Fixed(60.0))
+        };
+        match self.run_until_kind {
+            StopConditionKind::Population => {
+                condition_row = condition_row
+                    .push(
+                        PickList::new(
+                            self.states.clone(),
+                            self.run_until_state.clone(),
+                            Message::RunUntilStateSelected,
+                        )
+                        .placeholder("State"),
+                    )
+                    .push(PickList::new(
+                        RelationalOperator::ALL.to_vec(),
+                        self.run_until_operator,
+                        Message::RunUntilOperatorSelected,
+                    ))
+                    .push(value_input("0"));
+            }
+            StopConditionKind::Generation => {
+                condition_row = condition_row.push(value_input("1000"));
+            }
+            StopConditionKind::Stable | StopConditionKind::Cycle => {}
+        }
+
+        column![row![
+            with_help(
+                button("Run Until")
+                    .on_press_maybe(self.run_until_condition().map(Message::RunUntil))
+                    .padding(5),
+                format!(
+                    "Steps without redrawing until the condition holds, \
+                     giving up after {} steps.",
+                    MAX_RUN_UNTIL_STEPS
+                ),
+            ),
+            condition_row,
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)]
+        .spacing(5)
+        .push_maybe(
+            self.run_until_report
+                .as_ref()
+                .map(|report| text(report).size(14)),
+        )
+        .into()
+    }
+
+    // Runs the Forest Fire preset over a range of one parameter and tabulates the outcome
+    fn view_sweep_panel(&self) -> Element<'_, Message> {
+        let running = self.sweep_running;
+        let input = |idx: usize, placeholder: &'static str| {
+            text_input(placeholder, &self.sweep_inputs[idx])
+                .on_input(move |val| Message::SweepInputChanged(idx, val))
+                .padding(3)
+                .width(Length::Fixed(60.0))
+        };
+
+        let mut panel = column![
+            text("Parameter sweep (Forest Fire)").size(18),
+            row![
+                PickList::new(
+                    SWEEPABLE_PARAMS.to_vec(),
+                    Some(self.sweep_param),
+                    Message::SweepParamSelected
+                ),
+                text("from"),
+                input(0, "0.1"),
+                text("to"),
+                input(1, "0.9"),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            row![
+                input(2, "9"),
+                text("values,"),
+                input(3, "100"),
+                text(format!("steps each, from seed {}", self.seed)).size(14),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            row![
+                button("Run Sweep")
+                    .on_press_maybe((!running).then_some(Message::StartSweep))
+                    .padding(5),
+                button("Cancel")
+                    .on_press_maybe(running.then_some(Message::CancelSweep))
+                    .padding(5),
+                button("Export CSV")
+                    .on_press_maybe(
+                        self.sweep
+                            .as_ref()
+                            .filter(|run| !running && !run.results.is_empty())
+                            .map(|_| Message::ExportSweepCsv)
+                    )
+                    .padding(5),
+            ]
+            .spacing(10),
+        ]
+        .spacing(8)
+        .push_maybe(field_error(&self.sweep_error));
+
+        if let Some(run) = &self.sweep {
+            panel = panel.push(
+                progress_bar(0.0..=run.values.len() as f32, run.results.len() as f32)
+                    .height(Length::Fixed(10.0)),
+            );
+            panel = panel.push(
+                text(format!(
+                    "{} | {} | entropy",
+                    run.param,
+                    run.state_names.join(" | ")
+                ))
+                .size(14),
+            );
+            for result in &run.results {
+                let counts: Vec<String> = result.population.iter().map(|n| n.to_string()).collect();
+                panel = panel.push(
+                    text(format!(
+                        "{:.3} | {} | {:.3}",
+                        result.value,
+                        counts.join(" | "),
+                        result.entropy
+                    ))
+                    .size(14),
+                );
+            }
         }
+
+        panel.into()
     }
 }